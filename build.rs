@@ -1,6 +1,7 @@
 fn main() {
-    prost_build::Config::new()
+    tonic_build::configure()
         .out_dir("src/")
-        .compile_protos(&["proto/sensor.proto"], &["proto"])
+        .build_client(false)
+        .compile(&["proto/sensor.proto"], &["proto"])
         .unwrap();
 }
@@ -0,0 +1,261 @@
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+use crate::acoustics::levels::leq;
+use crate::series::Sample;
+
+/// Penalty added to evening levels in Lden, in dB.
+pub const EVENING_PENALTY_DB: f64 = 5.0;
+/// Penalty added to night levels in Ldn and Lden, in dB.
+pub const NIGHT_PENALTY_DB: f64 = 10.0;
+
+/// Local time zone and period boundaries (whole hours) used for day-evening-night indicators.
+///
+/// A reporting day runs from `day_start` on its date to `day_start` on the next date, so each
+/// day's night period is contiguous. Lden uses day `[day_start, evening_start)`, evening
+/// `[evening_start, night_start)` and night `[night_start, day_start)`. Ldn has no evening
+/// period and uses its own night boundary, `ldn_night_start`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoisePeriods {
+    pub timezone: Tz,
+    pub day_start: u32,
+    pub evening_start: u32,
+    pub night_start: u32,
+    pub ldn_night_start: u32,
+}
+
+impl Default for NoisePeriods {
+    /// EU Environmental Noise Directive periods for Lden and the customary 22:00 night for Ldn.
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            day_start: 7,
+            evening_start: 19,
+            night_start: 23,
+            ldn_night_start: 22,
+        }
+    }
+}
+
+/// Day-evening-night indicators for one reporting day, all in dB.
+///
+/// A period level is `None` when the period has no samples; the combined indicators are `None`
+/// unless every period they need has samples.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DailyIndicators {
+    pub date: NaiveDate,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub samples: usize,
+    pub ld: Option<f64>,
+    pub le: Option<f64>,
+    pub ln: Option<f64>,
+    pub lden: Option<f64>,
+    pub ldn: Option<f64>,
+}
+
+impl NoisePeriods {
+    /// Checks that the boundaries are valid hours in day, evening, night order.
+    pub fn check(&self) -> Result<(), String> {
+        if self.night_start > 23 || self.ldn_night_start > 23 {
+            return Err("period boundaries must be hours between 0 and 23".to_string());
+        }
+        if !(self.day_start < self.evening_start && self.evening_start < self.night_start) {
+            return Err("day, evening and night must start in that order within a day".to_string());
+        }
+        if self.ldn_night_start <= self.day_start {
+            return Err("the Ldn night must start after the day starts".to_string());
+        }
+        Ok(())
+    }
+
+    /// Converts a local hour on `date` to UTC, skipping forward over a DST gap.
+    fn at(&self, date: NaiveDate, hour: u32) -> DateTime<Utc> {
+        let local = date.and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or_default());
+        let resolved = self
+            .timezone
+            .from_local_datetime(&local)
+            .earliest()
+            .or_else(|| {
+                self.timezone
+                    .from_local_datetime(&(local + chrono::Duration::hours(1)))
+                    .earliest()
+            });
+        match resolved {
+            Some(at) => at.with_timezone(&Utc),
+            None => Utc.from_utc_datetime(&local),
+        }
+    }
+
+    /// The UTC bounds of the reporting day for `date`.
+    pub fn day_bounds(&self, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        let next = date + Days::new(1);
+        (self.at(date, self.day_start), self.at(next, self.day_start))
+    }
+
+    /// The reporting date whose day contains `at`.
+    pub fn date_containing(&self, at: DateTime<Utc>) -> NaiveDate {
+        let local = at.with_timezone(&self.timezone).date_naive();
+        if at < self.day_bounds(local).0 {
+            local - Days::new(1)
+        } else {
+            local
+        }
+    }
+
+    /// Computes the indicators for `date` from samples sorted by time.
+    pub fn indicators(&self, date: NaiveDate, samples: &[Sample]) -> DailyIndicators {
+        let next = date + Days::new(1);
+        let day_start = self.at(date, self.day_start);
+        let evening_start = self.at(date, self.evening_start);
+        let night_start = self.at(date, self.night_start);
+        let ldn_night_start = self.at(date, self.ldn_night_start);
+        let end = self.at(next, self.day_start);
+
+        let day = Period::new(samples, day_start, evening_start, 0.0);
+        let evening = Period::new(samples, evening_start, night_start, EVENING_PENALTY_DB);
+        let night = Period::new(samples, night_start, end, NIGHT_PENALTY_DB);
+        let ldn_day = Period::new(samples, day_start, ldn_night_start, 0.0);
+        let ldn_night = Period::new(samples, ldn_night_start, end, NIGHT_PENALTY_DB);
+
+        DailyIndicators {
+            date,
+            start: day_start,
+            end,
+            samples: day.count + evening.count + night.count,
+            ld: day.level,
+            le: evening.level,
+            ln: night.level,
+            lden: combine(&[&day, &evening, &night]),
+            ldn: combine(&[&ldn_day, &ldn_night]),
+        }
+    }
+}
+
+/// One period of a reporting day: its length, penalty and energy-equivalent level.
+struct Period {
+    seconds: f64,
+    penalty: f64,
+    level: Option<f64>,
+    count: usize,
+}
+
+impl Period {
+    fn new(samples: &[Sample], start: DateTime<Utc>, end: DateTime<Utc>, penalty: f64) -> Self {
+        let from = samples.partition_point(|s| s.timestamp < start);
+        let to = samples.partition_point(|s| s.timestamp < end);
+        let values: Vec<f64> = samples[from..to.max(from)]
+            .iter()
+            .map(|s| s.value)
+            .collect();
+        Self {
+            seconds: (end - start).num_seconds().max(0) as f64,
+            penalty,
+            level: leq(&values),
+            count: values.len(),
+        }
+    }
+}
+
+/// Time-weighted energy combination of penalized period levels.
+fn combine(periods: &[&Period]) -> Option<f64> {
+    let mut energy = 0.0;
+    let mut seconds = 0.0;
+    for period in periods {
+        energy += period.seconds * 10f64.powf((period.level? + period.penalty) / 10.0);
+        seconds += period.seconds;
+    }
+    (seconds > 0.0).then(|| 10.0 * (energy / seconds).log10())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    fn constant_day(periods: &NoisePeriods, date: NaiveDate, level: f64) -> Vec<Sample> {
+        let (start, end) = periods.day_bounds(date);
+        let mut samples = Vec::new();
+        let mut at = start;
+        while at < end {
+            samples.push(Sample {
+                timestamp: at,
+                value: level,
+            });
+            at += chrono::Duration::minutes(10);
+        }
+        samples
+    }
+
+    #[test]
+    fn constant_level_gets_standard_penalties() {
+        let periods = NoisePeriods::default();
+        let date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let day = periods.indicators(date, &constant_day(&periods, date, 50.0));
+
+        assert!(close(day.ld.unwrap(), 50.0));
+        assert!(close(day.le.unwrap(), 50.0));
+        assert!(close(day.ln.unwrap(), 50.0));
+        // 12 h at 50, 4 h at 55, 8 h at 60.
+        let expected_lden =
+            10.0 * ((12.0 * 1e5 + 4.0 * 10f64.powf(5.5) + 8.0 * 1e6) / 24.0_f64).log10();
+        assert!(close(day.lden.unwrap(), expected_lden));
+        // 15 h at 50, 9 h at 60.
+        let expected_ldn = 10.0 * ((15.0 * 1e5 + 9.0 * 1e6) / 24.0_f64).log10();
+        assert!(close(day.ldn.unwrap(), expected_ldn));
+    }
+
+    #[test]
+    fn missing_period_leaves_combined_indicators_empty() {
+        let periods = NoisePeriods::default();
+        let date = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+        let (start, _) = periods.day_bounds(date);
+        let samples = vec![Sample {
+            timestamp: start,
+            value: 50.0,
+        }];
+        let day = periods.indicators(date, &samples);
+        assert_eq!(day.samples, 1);
+        assert!(day.ld.is_some());
+        assert!(day.ln.is_none());
+        assert!(day.lden.is_none());
+        assert!(day.ldn.is_none());
+    }
+
+    #[test]
+    fn day_bounds_follow_time_zone_and_dst() {
+        let periods = NoisePeriods {
+            timezone: chrono_tz::Europe::Amsterdam,
+            ..Default::default()
+        };
+        // The night of 29-30 March 2025 loses an hour.
+        let date = NaiveDate::from_ymd_opt(2025, 3, 29).unwrap();
+        let (start, end) = periods.day_bounds(date);
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 3, 29, 6, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2025, 3, 30, 5, 0, 0).unwrap());
+        assert_eq!(periods.date_containing(start), date);
+        assert_eq!(
+            periods.date_containing(start - chrono::Duration::seconds(1)),
+            date - Days::new(1)
+        );
+    }
+
+    #[test]
+    fn check_rejects_out_of_order_periods() {
+        assert!(NoisePeriods::default().check().is_ok());
+        let bad = NoisePeriods {
+            evening_start: 6,
+            ..Default::default()
+        };
+        assert!(bad.check().is_err());
+        let bad = NoisePeriods {
+            night_start: 24,
+            ..Default::default()
+        };
+        assert!(bad.check().is_err());
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{Days, NaiveDate, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::acoustics::daily::DailyIndicators;
+use crate::app_state::AppState;
+use crate::consts::errors::ERR_DAILY_INDICATORS;
+use crate::consts::health::JOB_DAILY_INDICATORS;
+use crate::consts::redis::{
+    DAILY_INDICATOR_LOCK, REDIS_CMD_TS_ADD, REDIS_LABEL_DEVICE_ID, REDIS_LABEL_KIND,
+    REDIS_LABEL_METRIC, REDIS_LABEL_UNIT, REDIS_LABELS_LABEL, REDIS_ON_DUPLICATE_LABEL,
+    REDIS_ON_DUPLICATE_LAST,
+};
+use crate::devices::status;
+use crate::sensor::Domain;
+use crate::series;
+
+/// How often the job looks for a newly completed reporting day.
+const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Most past reporting days a run fills in, so a long outage does not read months of samples at
+/// once.
+const MAX_BACKFILL_DAYS: u64 = 31;
+/// Indicators stored per day, each as its own series.
+const METRICS: [&str; 5] = ["ld", "le", "ln", "lden", "ldn"];
+
+/// Value of the `kind` label on daily indicator series.
+pub const KIND_DAILY_INDICATOR: &str = "daily_indicator";
+
+/// Spawns a background task that, once an hour, computes the day-evening-night indicators of
+/// every device for each completed reporting day since the last one stored, up to
+/// [`MAX_BACKFILL_DAYS`] back, and stores them as their own series
+/// (`<prefix>:<device_id>:daily:<metric>`), timestamped at the start of the day.
+///
+/// Only one replica runs the job at a time, holding a lock in Redis like the alert evaluator.
+/// Writes overwrite any earlier value for the same day, so re-running is harmless.
+pub fn spawn_daily_indicator_job(state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    state
+        .jobs
+        .register(JOB_DAILY_INDICATORS, Some(RUN_INTERVAL));
+    tokio::spawn(async move {
+        let instance = Uuid::new_v4().to_string();
+        let mut ticker = tokio::time::interval(RUN_INTERVAL);
+        loop {
+            ticker.tick().await;
+            // A run left to the replica holding the lock counts as a successful one.
+            let result = match state
+                .redis
+                .acquire_lock(DAILY_INDICATOR_LOCK, &instance, RUN_INTERVAL * 2)
+                .await
+            {
+                Ok(true) => run_once(&state).await,
+                Ok(false) => Ok(()),
+                Err(e) => Err(e),
+            };
+            state.jobs.record(JOB_DAILY_INDICATORS, &result);
+            if let Err(e) = result {
+                error!(error = %e, "{ERR_DAILY_INDICATORS}");
+            }
+        }
+    })
+}
+
+async fn run_once(state: &AppState) -> anyhow::Result<()> {
+    let periods = state.noise_periods;
+    let last = periods.date_containing(Utc::now()) - Days::new(1);
+    let earliest = last - Days::new(MAX_BACKFILL_DAYS - 1);
+    let domain = Domain::SoundPressureLevel.as_str_name();
+
+    let mut stored = 0;
+    for device_id in status::list_seen_devices(&state.redis).await? {
+        let key = state.series_keys.series(&device_id, domain);
+        let mut date = match last_stored_date(state, &device_id).await? {
+            Some(stored) => (stored + Days::new(1)).max(earliest),
+            None => earliest,
+        };
+        while date <= last {
+            let (start, end) = periods.day_bounds(date);
+            let Some(samples) = series::range(&state.redis, &key, start, end).await? else {
+                break;
+            };
+            if !samples.is_empty() {
+                let indicators = periods.indicators(date, &samples);
+                store_indicators(state, &device_id, &indicators).await?;
+                stored += 1;
+            }
+            date = date + Days::new(1);
+        }
+    }
+
+    info!(through = %last, days = stored, "Stored daily noise indicators");
+    Ok(())
+}
+
+/// The latest reporting day with indicators stored for a device. A day may lack some of them,
+/// so every metric's series is checked.
+async fn last_stored_date(state: &AppState, device_id: &str) -> anyhow::Result<Option<NaiveDate>> {
+    let mut latest = None;
+    for metric in METRICS {
+        let key = state.series_keys.daily_indicator(device_id, metric);
+        if let Some(sample) = series::latest(&state.redis, &key).await? {
+            latest = latest.max(Some(sample.timestamp));
+        }
+    }
+    Ok(latest.map(|start| state.noise_periods.date_containing(start)))
+}
+
+async fn store_indicators(
+    state: &AppState,
+    device_id: &str,
+    indicators: &DailyIndicators,
+) -> anyhow::Result<()> {
+    let timestamp = series::to_series_timestamp(indicators.start);
+    let values = [
+        indicators.ld,
+        indicators.le,
+        indicators.ln,
+        indicators.lden,
+        indicators.ldn,
+    ];
+
+    let mut pipe = redis::pipe();
+    for (metric, value) in METRICS.into_iter().zip(values) {
+        let Some(value) = value else {
+            continue;
+        };
+        let key = state.series_keys.daily_indicator(device_id, metric);
+        pipe.cmd(REDIS_CMD_TS_ADD)
+            .arg(key)
+            .arg(timestamp)
+            .arg(value)
+            .arg(REDIS_ON_DUPLICATE_LABEL)
+            .arg(REDIS_ON_DUPLICATE_LAST)
+            .arg(REDIS_LABELS_LABEL)
+            .arg(REDIS_LABEL_DEVICE_ID)
+            .arg(device_id)
+            .arg(REDIS_LABEL_METRIC)
+            .arg(metric)
+            .arg(REDIS_LABEL_KIND)
+            .arg(KIND_DAILY_INDICATOR)
+            .arg(REDIS_LABEL_UNIT)
+            .arg("dB")
+            .ignore();
+    }
+
+    let mut conn = state.redis.get_connection_manager().await?;
+    pipe.query_async::<_, ()>(&mut conn).await?;
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::series::Sample;
+
+/// Summary sound levels over a set of SPL samples, all in dB.
+///
+/// Samples are assumed to be evenly spaced in time, so each one carries equal weight.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Levels {
+    pub count: usize,
+    /// Energy-equivalent continuous level.
+    pub leq: f64,
+    pub lmax: f64,
+    pub lmin: f64,
+    /// Level exceeded 10% of the time.
+    pub l10: f64,
+    /// Level exceeded 50% of the time (the median).
+    pub l50: f64,
+    /// Level exceeded 90% of the time, a common measure of background noise.
+    pub l90: f64,
+}
+
+/// Levels for one window of a windowed query. `levels` is `None` when the window has no samples.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WindowLevels {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub levels: Option<Levels>,
+}
+
+/// Energy-equivalent level: the dB values are averaged as sound energy, not arithmetically.
+pub fn leq(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mean_energy =
+        values.iter().map(|l| 10f64.powf(l / 10.0)).sum::<f64>() / values.len() as f64;
+    Some(10.0 * mean_energy.log10())
+}
+
+/// The level exceeded `percent`% of the time, interpolating between samples.
+///
+/// `sorted` must be in ascending order and non-empty.
+pub fn exceedance_level(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (1.0 - percent / 100.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let fraction = rank - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+/// Computes all summary levels, or `None` for an empty set.
+pub fn levels(values: &[f64]) -> Option<Levels> {
+    let leq = leq(values)?;
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    Some(Levels {
+        count: sorted.len(),
+        leq,
+        lmax: sorted[sorted.len() - 1],
+        lmin: sorted[0],
+        l10: exceedance_level(&sorted, 10.0),
+        l50: exceedance_level(&sorted, 50.0),
+        l90: exceedance_level(&sorted, 90.0),
+    })
+}
+
+/// Splits `[from, to)` into consecutive windows of `window` length (the last one may be shorter)
+/// and computes levels for the samples falling in each. `samples` must be sorted by time.
+pub fn windowed(
+    samples: &[Sample],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    window: Duration,
+) -> Vec<WindowLevels> {
+    let Ok(step) = chrono::Duration::from_std(window) else {
+        return Vec::new();
+    };
+    if step <= chrono::Duration::zero() {
+        return Vec::new();
+    }
+
+    let mut windows = Vec::new();
+    let mut remaining = samples;
+    let mut start = from;
+    while start < to {
+        let end = (start + step).min(to);
+        let skip = remaining.partition_point(|s| s.timestamp < start);
+        remaining = &remaining[skip..];
+        let take = remaining.partition_point(|s| s.timestamp < end);
+        let values: Vec<f64> = remaining[..take].iter().map(|s| s.value).collect();
+        remaining = &remaining[take..];
+        windows.push(WindowLevels {
+            start,
+            end,
+            levels: levels(&values),
+        });
+        start = end;
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn leq_is_energy_average() {
+        assert!(close(leq(&[60.0, 60.0]).unwrap(), 60.0));
+        // Averaging 70 dB with a far quieter sample halves the energy: about 3 dB down.
+        assert!(close(leq(&[70.0, 0.0]).unwrap(), 66.9897));
+        assert!(leq(&[]).is_none());
+    }
+
+    #[test]
+    fn exceedance_levels() {
+        let values: Vec<f64> = (1..=11).map(|v| v as f64 * 10.0).collect();
+        let l = levels(&values).unwrap();
+        assert_eq!(l.count, 11);
+        assert!(close(l.lmax, 110.0));
+        assert!(close(l.lmin, 10.0));
+        assert!(close(l.l10, 100.0));
+        assert!(close(l.l50, 60.0));
+        assert!(close(l.l90, 20.0));
+    }
+
+    #[test]
+    fn windowed_splits_range_and_leaves_gaps_empty() {
+        let base = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let at = |secs| base + chrono::Duration::seconds(secs);
+        let samples = vec![
+            Sample {
+                timestamp: at(0),
+                value: 50.0,
+            },
+            Sample {
+                timestamp: at(30),
+                value: 50.0,
+            },
+            Sample {
+                timestamp: at(150),
+                value: 70.0,
+            },
+        ];
+
+        let windows = windowed(&samples, base, at(170), Duration::from_secs(60));
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].levels.as_ref().unwrap().count, 2);
+        assert!(windows[1].levels.is_none());
+        assert_eq!(windows[2].end, at(170));
+        assert!(close(windows[2].levels.as_ref().unwrap().leq, 70.0));
+    }
+}
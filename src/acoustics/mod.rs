@@ -0,0 +1,10 @@
+pub mod daily;
+pub mod daily_job;
+pub mod levels;
+
+// Re-export commonly used items
+pub use daily::DailyIndicators;
+pub use daily::NoisePeriods;
+pub use daily_job::spawn_daily_indicator_job;
+pub use levels::Levels;
+pub use levels::WindowLevels;
@@ -0,0 +1,217 @@
+use chrono::{DateTime, Timelike, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::alerts::rules::{self, AlertRule, Condition};
+use crate::alerts::state::{self, AlertStatus, Observation};
+use crate::alerts::webhook::{self, AlertNotification, REQUEST_TIMEOUT};
+use crate::app_state::AppState;
+use crate::consts::errors::{ERR_ALERT_EVALUATION, ERR_ALERT_WEBHOOK};
+use crate::consts::health::JOB_ALERT_EVALUATOR;
+use crate::consts::redis::ALERT_EVALUATION_LOCK;
+use crate::devices::status;
+use crate::series;
+
+/// How often rules are evaluated and how hard webhooks are retried.
+#[derive(Clone, Copy, Debug)]
+pub struct AlertingPolicy {
+    pub evaluation_interval: Duration,
+    pub webhook_max_attempts: u32,
+}
+
+/// Spawns a background task that evaluates every enabled rule once per evaluation interval,
+/// moving alerts between pending, firing and resolved and POSTing a notification to the rule's
+/// webhook whenever one fires or resolves.
+///
+/// Only one replica evaluates at a time: the evaluator holds a lock in Redis that it renews on
+/// every run and that expires after two missed runs, so another replica takes over.
+pub fn spawn_alert_evaluator(state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    state.jobs.register(
+        JOB_ALERT_EVALUATOR,
+        Some(state.alerting.evaluation_interval),
+    );
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                error!(error = %e, "{ERR_ALERT_EVALUATION}");
+                return;
+            }
+        };
+        let instance = Uuid::new_v4().to_string();
+        let mut ticker = tokio::time::interval(state.alerting.evaluation_interval);
+        loop {
+            ticker.tick().await;
+            // A run left to the replica holding the lock counts as a successful one.
+            let lock_ttl = state.alerting.evaluation_interval * 2;
+            let result = match state
+                .redis
+                .acquire_lock(ALERT_EVALUATION_LOCK, &instance, lock_ttl)
+                .await
+            {
+                Ok(true) => run_once(&state, &client).await,
+                Ok(false) => Ok(()),
+                Err(e) => Err(e),
+            };
+            state.jobs.record(JOB_ALERT_EVALUATOR, &result);
+            if let Err(e) = result {
+                error!(error = %e, "{ERR_ALERT_EVALUATION}");
+            }
+        }
+    })
+}
+
+async fn run_once(state: &AppState, client: &reqwest::Client) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let rules = rules::list_rules(&state.redis).await?;
+    let seen = status::list_seen_devices(&state.redis).await?;
+
+    for rule in rules.iter().filter(|r| r.enabled) {
+        let devices = match &rule.device_id {
+            Some(device_id) => std::slice::from_ref(device_id),
+            None => seen.as_slice(),
+        };
+        for device_id in devices {
+            // One unreadable series must not stop every other rule from being evaluated.
+            let result = match observe(state, rule, device_id, now).await {
+                Ok(observation) => step(state, client, rule, device_id, observation, now).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!(
+                    rule_id = %rule.id,
+                    device_id = %device_id,
+                    error = %e,
+                    "{ERR_ALERT_EVALUATION}"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates a rule's condition for one device as of `now`.
+async fn observe(
+    state: &AppState,
+    rule: &AlertRule,
+    device_id: &str,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Observation> {
+    if let Some(hours) = &rule.active_hours {
+        let hour = now.with_timezone(&state.noise_periods.timezone).hour();
+        if !hours.contains(hour) {
+            return Ok(Observation {
+                breaching: false,
+                value: None,
+            });
+        }
+    }
+
+    match &rule.condition {
+        Condition::Threshold {
+            domain,
+            statistic,
+            window_secs,
+            operator,
+            threshold,
+        } => {
+            let key = state.series_keys.series(device_id, domain);
+            let from = now - chrono::Duration::seconds(*window_secs as i64);
+            let samples = series::range(&state.redis, &key, from, now)
+                .await?
+                .unwrap_or_default();
+            let values: Vec<f64> = samples.iter().map(|s| s.value).collect();
+            let value = statistic.compute(&values);
+            Ok(Observation {
+                breaching: value.is_some_and(|v| operator.holds(v, *threshold)),
+                value,
+            })
+        }
+        Condition::Offline { after_secs } => {
+            let Some(activity) = status::get_activity(&state.redis, device_id).await? else {
+                return Ok(Observation {
+                    breaching: false,
+                    value: None,
+                });
+            };
+            let silent_secs = (now - activity.last_seen).num_seconds();
+            Ok(Observation {
+                breaching: silent_secs > *after_secs as i64,
+                value: Some(silent_secs as f64),
+            })
+        }
+    }
+}
+
+/// Applies one observation to the stored alert state and sends any resulting notification.
+async fn step(
+    state: &AppState,
+    client: &reqwest::Client,
+    rule: &AlertRule,
+    device_id: &str,
+    observation: Observation,
+    now: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let previous = state::get_state(&state.redis, &rule.id, device_id).await?;
+    let hold = Duration::from_secs(rule.for_secs);
+    let transition = state::advance(
+        previous.as_ref(),
+        rule.id,
+        device_id,
+        observation,
+        hold,
+        now,
+    );
+
+    let Some(next) = transition.state else {
+        if previous.is_some() {
+            state::delete_state(&state.redis, &rule.id, device_id).await?;
+        }
+        return Ok(());
+    };
+    state::save_state(&state.redis, &next).await?;
+    if !transition.notify {
+        return Ok(());
+    }
+
+    let fired_at = match (next.status, &previous) {
+        (AlertStatus::Resolved, Some(previous)) => previous.since,
+        _ => next.since,
+    };
+    if next.status == AlertStatus::Firing {
+        warn!(
+            event = "alert_firing",
+            rule_id = %rule.id,
+            rule = %rule.name,
+            device_id = %device_id,
+            value = ?next.value,
+            "Alert firing"
+        );
+    } else {
+        info!(
+            event = "alert_resolved",
+            rule_id = %rule.id,
+            rule = %rule.name,
+            device_id = %device_id,
+            "Alert resolved"
+        );
+    }
+
+    // Delivery retries can take minutes, so it must not hold up the rest of the evaluation.
+    let notification = AlertNotification::new(rule, &next, fired_at);
+    let client = client.clone();
+    let url = rule.webhook_url.clone();
+    let max_attempts = state.alerting.webhook_max_attempts;
+    tokio::spawn(async move {
+        if let Err(e) = webhook::deliver(&client, &url, &notification, max_attempts).await {
+            error!(
+                fingerprint = %notification.fingerprint,
+                error = %e,
+                "{ERR_ALERT_WEBHOOK}"
+            );
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,10 @@
+pub mod evaluator;
+pub mod rules;
+pub mod state;
+pub mod webhook;
+
+// Re-export commonly used items
+pub use evaluator::AlertingPolicy;
+pub use evaluator::spawn_alert_evaluator;
+pub use rules::AlertRule;
+pub use state::{AlertState, AlertStatus};
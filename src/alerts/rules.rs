@@ -0,0 +1,325 @@
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::acoustics::levels::{self, Levels};
+use crate::consts::redis::{ALERT_RULE_KEY_PREFIX, ALL_ALERT_RULES};
+use crate::redis::RedisStore;
+
+/// Longest window a threshold condition may aggregate over.
+const MAX_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+/// How a measured value is compared with a rule's threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Operator {
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Operator::Gt => value > threshold,
+            Operator::Gte => value >= threshold,
+            Operator::Lt => value < threshold,
+            Operator::Lte => value <= threshold,
+        }
+    }
+}
+
+/// Statistic computed over the samples in a threshold condition's window.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Statistic {
+    /// Energy-equivalent level; only meaningful for dB domains.
+    Leq,
+    Lmax,
+    Lmin,
+    L10,
+    L50,
+    L90,
+    /// Arithmetic mean, for linear domains such as temperature.
+    Mean,
+}
+
+impl Statistic {
+    /// Computes the statistic, or `None` for an empty window.
+    pub fn compute(&self, values: &[f64]) -> Option<f64> {
+        let level = |pick: fn(&Levels) -> f64| levels::levels(values).map(|l| pick(&l));
+        match self {
+            Statistic::Leq => level(|l| l.leq),
+            Statistic::Lmax => level(|l| l.lmax),
+            Statistic::Lmin => level(|l| l.lmin),
+            Statistic::L10 => level(|l| l.l10),
+            Statistic::L50 => level(|l| l.l50),
+            Statistic::L90 => level(|l| l.l90),
+            Statistic::Mean => {
+                (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+            }
+        }
+    }
+}
+
+/// What a rule watches for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// A statistic over the last `window_secs` of a domain's series crosses `threshold`.
+    /// A window without samples never breaches.
+    Threshold {
+        domain: String,
+        statistic: Statistic,
+        window_secs: u64,
+        operator: Operator,
+        threshold: f64,
+    },
+    /// The device has not reported for more than `after_secs`.
+    Offline { after_secs: u64 },
+}
+
+/// Local hours (in `NOISE_TIMEZONE`) during which a rule is evaluated, from `start_hour` up to
+/// but excluding `end_hour`. The range may wrap past midnight, e.g. 22 to 7.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveHours {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl ActiveHours {
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// An alerting rule, evaluated by the background evaluator for one device or for every device
+/// that has reported.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub id: Uuid,
+    pub name: String,
+    /// Device the rule applies to; `None` applies it to every device.
+    pub device_id: Option<String>,
+    pub condition: Condition,
+    /// Outside these hours the condition is treated as not breached.
+    pub active_hours: Option<ActiveHours>,
+    /// How long the condition must hold before a pending alert fires.
+    pub for_secs: u64,
+    pub webhook_url: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Payload for creating an alerting rule.
+#[derive(Debug, Deserialize)]
+pub struct CreateAlertRule {
+    pub name: String,
+    pub device_id: Option<String>,
+    pub condition: Condition,
+    pub active_hours: Option<ActiveHours>,
+    #[serde(default)]
+    pub for_secs: u64,
+    pub webhook_url: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl CreateAlertRule {
+    /// Validates the rule, returning a message suitable for a 422 response.
+    pub fn check(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+        if self.device_id.as_deref() == Some("") {
+            return Err("device_id must not be empty".to_string());
+        }
+        if !(self.webhook_url.starts_with("http://") || self.webhook_url.starts_with("https://")) {
+            return Err("webhook_url must be an http or https URL".to_string());
+        }
+        match &self.condition {
+            Condition::Threshold {
+                window_secs,
+                threshold,
+                ..
+            } => {
+                if *window_secs == 0 || *window_secs > MAX_WINDOW_SECS {
+                    return Err(format!(
+                        "window_secs must be between 1 and {MAX_WINDOW_SECS}"
+                    ));
+                }
+                if !threshold.is_finite() {
+                    return Err("threshold must be a finite number".to_string());
+                }
+            }
+            Condition::Offline { after_secs } => {
+                if *after_secs == 0 {
+                    return Err("after_secs must be greater than zero".to_string());
+                }
+            }
+        }
+        if let Some(hours) = &self.active_hours {
+            if hours.start_hour > 23 || hours.end_hour > 23 {
+                return Err("active hours must be between 0 and 23".to_string());
+            }
+            if hours.start_hour == hours.end_hour {
+                return Err("active hours must not start and end at the same hour".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AlertRule {
+    pub fn from_create(req: CreateAlertRule, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: req.name,
+            device_id: req.device_id,
+            condition: req.condition,
+            active_hours: req.active_hours,
+            for_secs: req.for_secs,
+            webhook_url: req.webhook_url,
+            enabled: req.enabled,
+            created_at: now,
+        }
+    }
+}
+
+fn rule_key(id: &Uuid) -> String {
+    format!("{ALERT_RULE_KEY_PREFIX}{id}")
+}
+
+pub async fn save_rule(redis: &RedisStore, rule: &AlertRule) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json = serde_json::to_string(rule)?;
+    conn.set::<_, _, ()>(rule_key(&rule.id), json).await?;
+    conn.sadd::<_, _, ()>(ALL_ALERT_RULES, rule.id.to_string())
+        .await?;
+    Ok(())
+}
+
+pub async fn get_rule(redis: &RedisStore, id: &Uuid) -> anyhow::Result<Option<AlertRule>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json: Option<String> = conn.get(rule_key(id)).await?;
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns every rule, ordered by creation time.
+pub async fn list_rules(redis: &RedisStore) -> anyhow::Result<Vec<AlertRule>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let ids: Vec<String> = conn.smembers(ALL_ALERT_RULES).await?;
+
+    let mut rules: Vec<AlertRule> = Vec::with_capacity(ids.len());
+    for id in ids {
+        let json: Option<String> = conn.get(format!("{ALERT_RULE_KEY_PREFIX}{id}")).await?;
+        if let Some(json) = json {
+            rules.push(serde_json::from_str(&json)?);
+        }
+    }
+    rules.sort_by_key(|r| r.created_at);
+    Ok(rules)
+}
+
+/// Removes a rule. Returns `false` if it did not exist.
+pub async fn delete_rule(redis: &RedisStore, id: &Uuid) -> anyhow::Result<bool> {
+    let mut conn = redis.get_connection_manager().await?;
+    let removed: usize = conn.del(rule_key(id)).await?;
+    conn.srem::<_, _, ()>(ALL_ALERT_RULES, id.to_string())
+        .await?;
+    Ok(removed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold_rule() -> CreateAlertRule {
+        serde_json::from_str(
+            r#"{
+                "name": "Night noise",
+                "device_id": "dev-1",
+                "condition": {
+                    "type": "threshold",
+                    "domain": "SOUND_PRESSURE_LEVEL",
+                    "statistic": "leq",
+                    "window_secs": 300,
+                    "operator": "gt",
+                    "threshold": 70.0
+                },
+                "active_hours": {"start_hour": 22, "end_hour": 7},
+                "webhook_url": "https://hooks.example.com/noise"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn create_payload_defaults() {
+        let rule = threshold_rule();
+        assert!(rule.check().is_ok());
+        assert!(rule.enabled);
+        assert_eq!(rule.for_secs, 0);
+    }
+
+    #[test]
+    fn check_rejects_bad_rules() {
+        let mut rule = threshold_rule();
+        rule.webhook_url = "ftp://example.com".to_string();
+        assert!(rule.check().is_err());
+
+        let mut rule = threshold_rule();
+        rule.active_hours = Some(ActiveHours {
+            start_hour: 7,
+            end_hour: 7,
+        });
+        assert!(rule.check().is_err());
+
+        let mut rule = threshold_rule();
+        rule.condition = Condition::Offline { after_secs: 0 };
+        assert!(rule.check().is_err());
+    }
+
+    #[test]
+    fn active_hours_wrap_past_midnight() {
+        let night = ActiveHours {
+            start_hour: 22,
+            end_hour: 7,
+        };
+        assert!(night.contains(23));
+        assert!(night.contains(0));
+        assert!(night.contains(6));
+        assert!(!night.contains(7));
+        assert!(!night.contains(12));
+
+        let day = ActiveHours {
+            start_hour: 7,
+            end_hour: 19,
+        };
+        assert!(day.contains(7));
+        assert!(!day.contains(19));
+    }
+
+    #[test]
+    fn statistics() {
+        assert_eq!(Statistic::Mean.compute(&[1.0, 2.0, 3.0]), Some(2.0));
+        assert_eq!(Statistic::Lmax.compute(&[50.0, 70.0]), Some(70.0));
+        assert!(Statistic::Leq.compute(&[]).is_none());
+        assert!(Operator::Gt.holds(70.1, 70.0));
+        assert!(!Operator::Gt.holds(70.0, 70.0));
+        assert!(Operator::Lte.holds(70.0, 70.0));
+    }
+}
@@ -0,0 +1,285 @@
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::consts::redis::{ALERT_STATE_KEY_PREFIX, ALL_ALERT_STATES};
+use crate::redis::RedisStore;
+
+/// Where an alert is in its lifecycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    /// The condition holds but has not yet held for the rule's `for_secs`.
+    Pending,
+    /// The condition has held long enough; a notification was sent.
+    Firing,
+    /// The condition stopped holding after firing; a notification was sent.
+    Resolved,
+}
+
+/// The state of one rule for one device.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlertState {
+    pub rule_id: Uuid,
+    pub device_id: String,
+    pub status: AlertStatus,
+    /// When the alert entered its current status.
+    pub since: DateTime<Utc>,
+    /// The last evaluated value, if the condition produced one.
+    pub value: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The result of evaluating a rule's condition for one device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    pub breaching: bool,
+    pub value: Option<f64>,
+}
+
+/// The next state of an alert and whether its change should be notified.
+#[derive(Debug, PartialEq)]
+pub struct Transition {
+    /// `None` when there is nothing to keep, e.g. a pending alert whose condition cleared.
+    pub state: Option<AlertState>,
+    pub notify: bool,
+}
+
+/// Advances an alert by one evaluation.
+///
+/// Only the moves to firing and to resolved are notified, so a condition that keeps holding
+/// produces a single notification rather than one per evaluation.
+pub fn advance(
+    previous: Option<&AlertState>,
+    rule_id: Uuid,
+    device_id: &str,
+    observation: Observation,
+    hold: Duration,
+    now: DateTime<Utc>,
+) -> Transition {
+    let previous_status = previous.map(|s| s.status);
+    let enter = |status: AlertStatus| AlertState {
+        rule_id,
+        device_id: device_id.to_string(),
+        status,
+        since: now,
+        value: observation.value,
+        updated_at: now,
+    };
+    let stay = |state: &AlertState| AlertState {
+        value: observation.value,
+        updated_at: now,
+        ..state.clone()
+    };
+
+    match (previous, previous_status, observation.breaching) {
+        (Some(state), Some(AlertStatus::Pending), true) => {
+            let held = (now - state.since).to_std().unwrap_or(Duration::ZERO);
+            if held >= hold {
+                Transition {
+                    state: Some(enter(AlertStatus::Firing)),
+                    notify: true,
+                }
+            } else {
+                Transition {
+                    state: Some(stay(state)),
+                    notify: false,
+                }
+            }
+        }
+        (Some(state), Some(AlertStatus::Firing), true) => Transition {
+            state: Some(stay(state)),
+            notify: false,
+        },
+        (_, _, true) => {
+            let status = if hold.is_zero() {
+                AlertStatus::Firing
+            } else {
+                AlertStatus::Pending
+            };
+            Transition {
+                state: Some(enter(status)),
+                notify: status == AlertStatus::Firing,
+            }
+        }
+        (Some(_), Some(AlertStatus::Firing), false) => Transition {
+            state: Some(enter(AlertStatus::Resolved)),
+            notify: true,
+        },
+        (Some(state), Some(AlertStatus::Resolved), false) => Transition {
+            state: Some(state.clone()),
+            notify: false,
+        },
+        (_, _, false) => Transition {
+            state: None,
+            notify: false,
+        },
+    }
+}
+
+fn state_member(rule_id: &Uuid, device_id: &str) -> String {
+    format!("{rule_id}:{device_id}")
+}
+
+fn state_key(member: &str) -> String {
+    format!("{ALERT_STATE_KEY_PREFIX}{member}")
+}
+
+pub async fn get_state(
+    redis: &RedisStore,
+    rule_id: &Uuid,
+    device_id: &str,
+) -> anyhow::Result<Option<AlertState>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json: Option<String> = conn
+        .get(state_key(&state_member(rule_id, device_id)))
+        .await?;
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn save_state(redis: &RedisStore, state: &AlertState) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let member = state_member(&state.rule_id, &state.device_id);
+    let json = serde_json::to_string(state)?;
+    conn.set::<_, _, ()>(state_key(&member), json).await?;
+    conn.sadd::<_, _, ()>(ALL_ALERT_STATES, member).await?;
+    Ok(())
+}
+
+pub async fn delete_state(
+    redis: &RedisStore,
+    rule_id: &Uuid,
+    device_id: &str,
+) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let member = state_member(rule_id, device_id);
+    conn.del::<_, ()>(state_key(&member)).await?;
+    conn.srem::<_, _, ()>(ALL_ALERT_STATES, member).await?;
+    Ok(())
+}
+
+/// Returns every stored alert state, firing first, then pending, then resolved.
+pub async fn list_states(redis: &RedisStore) -> anyhow::Result<Vec<AlertState>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let members: Vec<String> = conn.smembers(ALL_ALERT_STATES).await?;
+
+    let mut states: Vec<AlertState> = Vec::with_capacity(members.len());
+    for member in members {
+        let json: Option<String> = conn.get(state_key(&member)).await?;
+        if let Some(json) = json {
+            states.push(serde_json::from_str(&json)?);
+        }
+    }
+    states.sort_by_key(|s| {
+        let rank = match s.status {
+            AlertStatus::Firing => 0,
+            AlertStatus::Pending => 1,
+            AlertStatus::Resolved => 2,
+        };
+        (rank, s.since)
+    });
+    Ok(states)
+}
+
+/// Removes every state belonging to a rule.
+pub async fn delete_rule_states(redis: &RedisStore, rule_id: &Uuid) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let prefix = format!("{rule_id}:");
+    let members: Vec<String> = conn.smembers(ALL_ALERT_STATES).await?;
+    for member in members.into_iter().filter(|m| m.starts_with(&prefix)) {
+        conn.del::<_, ()>(state_key(&member)).await?;
+        conn.srem::<_, _, ()>(ALL_ALERT_STATES, member).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, 22, 0, 0).unwrap() + chrono::Duration::seconds(secs)
+    }
+
+    fn breaching(value: f64) -> Observation {
+        Observation {
+            breaching: true,
+            value: Some(value),
+        }
+    }
+
+    fn clear() -> Observation {
+        Observation {
+            breaching: false,
+            value: Some(60.0),
+        }
+    }
+
+    #[test]
+    fn pending_fires_after_hold_and_resolves_once() {
+        let rule = Uuid::new_v4();
+        let hold = Duration::from_secs(120);
+
+        let t = advance(None, rule, "dev", breaching(72.0), hold, at(0));
+        let pending = t.state.unwrap();
+        assert_eq!(pending.status, AlertStatus::Pending);
+        assert!(!t.notify);
+
+        let t = advance(Some(&pending), rule, "dev", breaching(73.0), hold, at(60));
+        let still_pending = t.state.unwrap();
+        assert_eq!(still_pending.status, AlertStatus::Pending);
+        assert_eq!(still_pending.since, at(0));
+        assert!(!t.notify);
+
+        let t = advance(
+            Some(&still_pending),
+            rule,
+            "dev",
+            breaching(74.0),
+            hold,
+            at(120),
+        );
+        let firing = t.state.unwrap();
+        assert_eq!(firing.status, AlertStatus::Firing);
+        assert!(t.notify);
+
+        let t = advance(Some(&firing), rule, "dev", breaching(75.0), hold, at(180));
+        assert_eq!(t.state.as_ref().unwrap().status, AlertStatus::Firing);
+        assert!(!t.notify, "a firing alert is not notified again");
+
+        let t = advance(Some(&firing), rule, "dev", clear(), hold, at(240));
+        let resolved = t.state.unwrap();
+        assert_eq!(resolved.status, AlertStatus::Resolved);
+        assert!(t.notify);
+
+        let t = advance(Some(&resolved), rule, "dev", clear(), hold, at(300));
+        assert_eq!(t.state.unwrap().since, at(240));
+        assert!(!t.notify);
+    }
+
+    #[test]
+    fn pending_that_clears_is_dropped_silently() {
+        let rule = Uuid::new_v4();
+        let hold = Duration::from_secs(120);
+        let pending = advance(None, rule, "dev", breaching(72.0), hold, at(0))
+            .state
+            .unwrap();
+        let t = advance(Some(&pending), rule, "dev", clear(), hold, at(60));
+        assert!(t.state.is_none());
+        assert!(!t.notify);
+    }
+
+    #[test]
+    fn zero_hold_fires_immediately() {
+        let rule = Uuid::new_v4();
+        let t = advance(None, rule, "dev", breaching(72.0), Duration::ZERO, at(0));
+        assert_eq!(t.state.unwrap().status, AlertStatus::Firing);
+        assert!(t.notify);
+    }
+}
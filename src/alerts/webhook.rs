@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::alerts::rules::{AlertRule, Condition};
+use crate::alerts::state::{AlertState, AlertStatus};
+
+/// Delay before the first retry; doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Upper bound on the delay between attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Timeout for a single delivery attempt.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body POSTed to a rule's webhook when an alert fires or resolves.
+#[derive(Clone, Debug, Serialize)]
+pub struct AlertNotification {
+    /// Stable for one firing episode, so receivers can drop duplicate deliveries.
+    pub fingerprint: String,
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    pub device_id: String,
+    pub status: AlertStatus,
+    pub value: Option<f64>,
+    pub condition: Condition,
+    pub since: DateTime<Utc>,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl AlertNotification {
+    /// `fired_at` is when the episode started firing; a resolution carries the same fingerprint
+    /// as the firing it ends.
+    pub fn new(rule: &AlertRule, state: &AlertState, fired_at: DateTime<Utc>) -> Self {
+        Self {
+            fingerprint: format!("{}:{}:{}", rule.id, state.device_id, fired_at.timestamp()),
+            rule_id: rule.id,
+            rule_name: rule.name.clone(),
+            device_id: state.device_id.clone(),
+            status: state.status,
+            value: state.value,
+            condition: rule.condition.clone(),
+            since: state.since,
+            sent_at: Utc::now(),
+        }
+    }
+}
+
+/// Delay before retry number `attempt` (1-based).
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// POSTs a notification, retrying with exponential backoff on connection errors and non-2xx
+/// responses until `max_attempts` attempts have been made.
+pub async fn deliver(
+    client: &reqwest::Client,
+    url: &str,
+    notification: &AlertNotification,
+    max_attempts: u32,
+) -> anyhow::Result<()> {
+    let mut attempt = 1;
+    loop {
+        let result = client
+            .post(url)
+            .json(notification)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= max_attempts => {
+                return Err(anyhow::anyhow!(
+                    "webhook delivery failed after {attempt} attempts: {e}"
+                ));
+            }
+            Err(e) => {
+                tracing::warn!(
+                    url = %url,
+                    attempt,
+                    error = %e,
+                    "Webhook delivery failed, retrying"
+                );
+                tokio::time::sleep(backoff(attempt)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        assert_eq!(backoff(64), MAX_BACKOFF);
+    }
+}
@@ -1,8 +1,33 @@
+use crate::acoustics::NoisePeriods;
+use crate::alerts::AlertingPolicy;
+use crate::config::ActiveConfig;
+use crate::devices::LivenessPolicy;
+use crate::domains::DomainCatalog;
+use crate::events::{LiveFeed, SampleStream};
+use crate::health::JobMonitor;
+use crate::ingest::WriteQueue;
+use crate::lifecycle::Lifecycle;
 use crate::redis::RedisStore;
+use crate::series::SeriesKeys;
+use crate::spool::Spool;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
+    pub alerting: AlertingPolicy,
+    pub config: ActiveConfig,
+    pub domains: Arc<DomainCatalog>,
+    pub jobs: JobMonitor,
+    pub lifecycle: Lifecycle,
+    pub live: LiveFeed,
+    pub liveness: LivenessPolicy,
+    pub noise_periods: NoisePeriods,
     pub redis: Arc<RedisStore>,
-    pub sensor_datum_prefix: String,
+    pub reject_unregistered_devices: bool,
+    pub sample_stream: Option<SampleStream>,
+    pub series_keys: SeriesKeys,
+    /// Keeps samples Redis could not store for later replay; `None` when spooling is disabled.
+    pub spool: Option<Spool>,
+    /// Batches the writes of `/ingest` requests.
+    pub write_queue: WriteQueue,
 }
@@ -1,52 +1,118 @@
+use crate::acoustics;
+use crate::alerts::{self, AlertingPolicy};
 use crate::app_state::AppState;
 use crate::auth;
+use crate::devices::{self, LivenessPolicy};
+use crate::domains::{self, DomainCatalog};
+use crate::events::{self, LiveFeed};
+use crate::grpc;
+use crate::health::JobMonitor;
+use crate::ingest::WriteQueue;
+use crate::lifecycle::{self, Lifecycle};
+use crate::metrics;
 use crate::redis::RedisStore;
+use crate::request_id;
 use crate::routes;
+use crate::spool::{self, Spool};
+use crate::telemetry::{self, Telemetry};
+use crate::tls;
 use axum::Router;
 use axum::middleware;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::config::Settings;
+use crate::config::{ActiveConfig, ConfigSource, Settings, reload};
 
 pub struct Application {
     settings: Settings,
     router: Router,
+    state: Arc<AppState>,
+    telemetry: Telemetry,
+    tls: Option<Arc<rustls::ServerConfig>>,
 }
 
 impl Application {
-    /// Builds a new instance of `Application`.
+    /// Builds a new instance of `Application` from already validated `settings`.
     ///
-    /// This method will return an error if the `Settings` cannot be built from the environment.
-    /// It will also initialize the global tracing subscriber with the configured log level.
+    /// It will initialize the global tracing subscriber with the configured log level, exporting
+    /// spans over OTLP when a collector endpoint is configured.
     ///
-    /// After building the settings and initializing the tracing subscriber, it will construct a
-    /// new `Router` instance with the routes from `health` and `ingest` merged into it.
+    /// After initializing the tracing subscriber, it will construct a new `Router` instance with
+    /// the routes from `health` and `ingest` merged into it.
     ///
     /// # Errors
     ///
-    /// This method will return an error if the settings cannot be built from the environment.
+    /// This method will return an error if Redis cannot be reached, the TLS files cannot be loaded
+    /// or the spool directory cannot be opened.
     ///
     /// # Examples
     ///
     ///
-    pub async fn build() -> anyhow::Result<Self> {
-        let env = std::env::vars().collect();
-        let settings = Settings::from_env_vars(&env)?;
+    pub async fn build(settings: Settings, source: ConfigSource) -> anyhow::Result<Self> {
+        let telemetry = Telemetry::init(&settings)?;
 
-        tracing_subscriber::fmt()
-            .with_max_level(settings.log_level)
-            .with_target(false)
-            .compact()
-            .init();
+        let redis = RedisStore::connect(&settings.redis_topology()).await?;
 
-        let redis = RedisStore::new(&settings.redis_url).await?;
+        let tls = match &settings.tls {
+            Some(tls_settings) => {
+                let (resolver, config) = tls::load(tls_settings)?;
+                tls::spawn_reloader(resolver, tls_settings.clone());
+                Some(Arc::new(config))
+            }
+            None => None,
+        };
+
+        let spool = match &settings.spool {
+            Some(spool_settings) => {
+                let spool = Spool::open(spool_settings.clone()).map_err(|e| {
+                    anyhow::anyhow!(
+                        "failed to open spool in {}: {e}",
+                        spool_settings.dir.display()
+                    )
+                })?;
+                info!(
+                    dir = %spool_settings.dir.display(),
+                    spooled_bytes = spool.usage().0,
+                    "Spooling samples while Redis is unreachable"
+                );
+                Some(spool)
+            }
+            None => None,
+        };
+
+        // Domains from the file are available even if those in Redis cannot be loaded.
+        let catalog = DomainCatalog::with_builtins();
+        catalog.replace_custom(settings.domains.clone());
+        match domains::store::load_custom_domains(&redis, &catalog, &settings.domains).await {
+            Ok(count) => info!("Loaded {} custom domains", count),
+            Err(e) => warn!("Failed to load custom domains: {:?}", e),
+        }
+
+        let redis = Arc::new(redis);
+        let write_queue = WriteQueue::start(redis.clone(), &settings.write_queue);
 
-        let sensor_datum_prefix = settings.sensor_datum_prefix.clone();
         let state = Arc::new(AppState {
-            redis: Arc::new(redis),
-            sensor_datum_prefix,
+            alerting: AlertingPolicy {
+                evaluation_interval: settings.alert_evaluation_interval,
+                webhook_max_attempts: settings.alert_webhook_max_attempts,
+            },
+            config: ActiveConfig::new(&settings),
+            domains: Arc::new(catalog),
+            jobs: JobMonitor::default(),
+            lifecycle: Lifecycle::new(),
+            live: LiveFeed::new(settings.live_pubsub_channel.clone()),
+            liveness: LivenessPolicy {
+                expected_batch_interval: settings.expected_batch_interval,
+                offline_after_intervals: settings.offline_after_intervals,
+            },
+            noise_periods: settings.noise_periods,
+            redis,
+            reject_unregistered_devices: settings.reject_unregistered_devices,
+            sample_stream: settings.sample_stream.clone(),
+            series_keys: settings.series_keys(),
+            spool,
+            write_queue,
         });
 
         // Bootstrap admin key if none exists
@@ -55,28 +121,189 @@ impl Application {
             // Continue application startup even if bootstrap fails
         }
 
+        devices::spawn_offline_monitor(state.clone());
+        acoustics::spawn_daily_indicator_job(state.clone());
+        alerts::spawn_alert_evaluator(state.clone());
+        events::spawn_pubsub_relay(state.clone());
+        spool::spawn_spool_replayer(state.clone());
+        reload::spawn_reloader(state.clone(), source, telemetry.log_filter());
+
         let router =
             Router::new()
                 .merge(routes::health::routes(state.clone()))
+                .merge(routes::metrics::routes(state.clone()))
                 .merge(
                     routes::ingest::routes(state.clone()).layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        auth::validate_device,
+                    )),
+                )
+                .merge(routes::acoustics::routes(state.clone()).layer(
+                    middleware::from_fn_with_state(state.clone(), auth::validate_api_key),
+                ))
+                .merge(
+                    routes::export::routes(state.clone()).layer(middleware::from_fn_with_state(
                         state.clone(),
                         auth::validate_api_key,
                     )),
                 )
+                .merge(
+                    routes::stream::routes(state.clone()).layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        auth::validate_api_key,
+                    )),
+                )
+                .merge(
+                    routes::config::routes(state.clone()).layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        auth::validate_admin_api_key,
+                    )),
+                )
+                .merge(routes::health::details_routes(state.clone()).layer(
+                    middleware::from_fn_with_state(state.clone(), auth::validate_admin_api_key),
+                ))
                 .merge(routes::apikeys::routes(state.clone()).layer(
                     middleware::from_fn_with_state(state.clone(), auth::validate_admin_api_key),
-                ));
+                ))
+                .merge(routes::devices::routes(state.clone()).layer(
+                    middleware::from_fn_with_state(state.clone(), auth::validate_admin_api_key),
+                ))
+                .merge(routes::domains::routes(state.clone()).layer(
+                    middleware::from_fn_with_state(state.clone(), auth::validate_admin_api_key),
+                ))
+                .merge(routes::interventions::routes(state.clone()).layer(
+                    middleware::from_fn_with_state(state.clone(), auth::validate_admin_api_key),
+                ))
+                .merge(
+                    routes::alerts::routes(state.clone()).layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        auth::validate_admin_api_key,
+                    )),
+                )
+                .merge(
+                    routes::import::routes(state.clone()).layer(middleware::from_fn_with_state(
+                        state.clone(),
+                        auth::validate_admin_api_key,
+                    )),
+                )
+                .layer(middleware::from_fn(metrics::track_http))
+                .layer(middleware::from_fn(telemetry::trace_http))
+                .layer(middleware::from_fn(request_id::propagate));
 
-        Ok(Self { settings, router })
+        Ok(Self {
+            settings,
+            router,
+            state,
+            telemetry,
+            tls,
+        })
     }
 
+    /// Serves HTTP, and gRPC when `GRPC_BIND_ADDRESS` is set, until SIGTERM or SIGINT, then shuts
+    /// down gracefully.
+    ///
+    /// On the signal `/readyz` starts failing at once. The server keeps accepting connections for
+    /// the readiness delay so load balancers can stop routing here, then stops accepting and gives
+    /// in-flight requests and background jobs the drain timeout to finish. Buffered spans are
+    /// flushed last.
     pub async fn run(self) -> anyhow::Result<()> {
         let addr: SocketAddr = self.settings.bind_address.parse()?;
         let tcp_listener = tokio::net::TcpListener::bind(addr).await?;
-        info!("Starting server on http://{}", addr);
+        let grpc_listener = match &self.settings.grpc_bind_address {
+            Some(grpc_addr) => {
+                let grpc_addr: SocketAddr = grpc_addr.parse()?;
+                Some((grpc_addr, tokio::net::TcpListener::bind(grpc_addr).await?))
+            }
+            None => None,
+        };
+
+        let lifecycle = self.state.lifecycle.clone();
+        let readiness_delay = self.settings.shutdown_readiness_delay;
+        let drain_timeout = self.settings.shutdown_drain_timeout;
+
+        let signal_lifecycle = lifecycle.clone();
+        let shutdown = async move {
+            let signal = lifecycle::shutdown_signal().await;
+            info!(
+                signal,
+                readiness_delay_secs = readiness_delay.as_secs(),
+                drain_timeout_secs = drain_timeout.as_secs(),
+                "Shutdown requested, draining"
+            );
+            signal_lifecycle.begin_drain();
+            tokio::time::sleep(readiness_delay).await;
+        };
+        let http = match self.tls.clone() {
+            Some(config) => {
+                info!("Starting server on https://{}", addr);
+                tokio::spawn(tls::serve(tcp_listener, config, self.router, shutdown))
+            }
+            None => {
+                info!("Starting server on http://{}", addr);
+                tokio::spawn(
+                    axum::serve(tcp_listener, self.router)
+                        .with_graceful_shutdown(shutdown)
+                        .into_future(),
+                )
+            }
+        };
+        // Stops accepting gRPC calls along with HTTP, once the readiness delay has passed.
+        let grpc_lifecycle = self.state.lifecycle.clone();
+        let grpc_shutdown = async move {
+            grpc_lifecycle.draining().await;
+            tokio::time::sleep(readiness_delay).await;
+        };
+        let grpc = match grpc_listener {
+            Some((grpc_addr, listener)) => {
+                let scheme = if self.tls.is_some() {
+                    "TLS"
+                } else {
+                    "plaintext"
+                };
+                info!("Starting gRPC server on {} ({})", grpc_addr, scheme);
+                tokio::spawn(grpc::serve(
+                    self.state.clone(),
+                    listener,
+                    self.tls.clone(),
+                    grpc_shutdown,
+                ))
+            }
+            None => tokio::spawn(async { Ok(()) }),
+        };
+        let mut server = tokio::spawn(async move {
+            tokio::try_join!(async { anyhow::Ok(http.await??) }, async { grpc.await? },).map(|_| ())
+        });
+
+        let result = tokio::select! {
+            result = &mut server => Some(result),
+            () = lifecycle.draining() => None,
+        };
+        let result = match result {
+            Some(result) => result,
+            None => {
+                let deadline = tokio::time::Instant::now() + readiness_delay + drain_timeout;
+                match tokio::time::timeout_at(deadline, &mut server).await {
+                    Ok(result) => {
+                        if tokio::time::timeout_at(deadline, lifecycle.wait_for_tasks())
+                            .await
+                            .is_err()
+                        {
+                            warn!("Drain timeout reached with background jobs still running");
+                        }
+                        result
+                    }
+                    Err(_) => {
+                        warn!("Drain timeout reached with requests still in flight");
+                        server.abort();
+                        Ok(Ok(()))
+                    }
+                }
+            }
+        };
 
-        axum::serve(tcp_listener, self.router).await?;
+        self.telemetry.shutdown();
+        result??;
+        info!("Shutdown complete");
         Ok(())
     }
 }
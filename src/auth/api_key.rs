@@ -1,7 +1,7 @@
 use axum::{
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{HeaderMap, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
@@ -11,30 +11,112 @@ use std::sync::Arc;
 use tracing::warn;
 
 use crate::app_state::AppState;
+use crate::auth::client_cert::{AuthenticatedDevice, ClientCertificate};
+use crate::metrics;
 
 pub const AUTH_HEADER: &str = "Authorization";
 pub const AUTH_SCHEME: &str = "SignalStash";
 pub const API_KEY_PREFIX: &str = "api_key:";
 pub const API_ADMIN_KEY_PREFIX: &str = "api_admin_key:";
 pub const ALL_ADMIN_KEYS: &str = "all_admin_keys";
+pub const ALL_API_KEYS: &str = "all_api_keys";
 
 pub const API_KEY_FORMAT_PREFIX: &str = "sk-sigstash-";
 pub const ADMIN_KEY_FORMAT_PREFIX: &str = "sk-sigstash-admin-";
 
+/// Why a request's API key was not accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthFailure {
+    MissingHeader,
+    MalformedHeader,
+    UnknownKey,
+    StoreError,
+}
+
+impl AuthFailure {
+    /// Short, fixed name for the failure, e.g. for metric labels.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthFailure::MissingHeader => "missing_header",
+            AuthFailure::MalformedHeader => "malformed_header",
+            AuthFailure::UnknownKey => "unknown_key",
+            AuthFailure::StoreError => "store_error",
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            AuthFailure::StoreError => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
 /// Extract API key from the Authorization header
 /// Format should be: "SignalStash {key}"
-fn extract_api_key_from_header(req: &Request<Body>) -> Result<&str, StatusCode> {
-    // Extract API key from Authorization header
-    let auth_header = match req.headers().get(AUTH_HEADER) {
-        Some(header) => header.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
-
-    // Parse the header value to extract the API key
-    match auth_header.strip_prefix(&format!("{AUTH_SCHEME} ")) {
-        Some(key) => Ok(key),
-        None => Err(StatusCode::UNAUTHORIZED),
+fn extract_api_key_from_header(headers: &HeaderMap) -> Result<&str, AuthFailure> {
+    let auth_header = headers
+        .get(AUTH_HEADER)
+        .map(|header| header.to_str().map_err(|_| AuthFailure::MalformedHeader))
+        .transpose()?;
+    parse_authorization(auth_header)
+}
+
+/// Extracts the key from an `Authorization` value of the form "SignalStash {key}", wherever the
+/// value was carried.
+pub fn parse_authorization(value: Option<&str>) -> Result<&str, AuthFailure> {
+    let value = value.ok_or(AuthFailure::MissingHeader)?;
+    value
+        .strip_prefix(&format!("{AUTH_SCHEME} "))
+        .ok_or(AuthFailure::MalformedHeader)
+}
+
+/// Checks that the request carries a key stored under `prefix`, counting failures by reason.
+#[tracing::instrument(name = "auth", skip(state, headers, prefix))]
+async fn check_api_key(
+    state: &AppState,
+    headers: &HeaderMap,
+    prefix: &str,
+    scope: &str,
+) -> Result<(), StatusCode> {
+    verify_api_key(state, extract_api_key_from_header(headers), prefix, scope)
+        .await
+        .map_err(|failure| failure.status())
+}
+
+/// Checks that `api_key` is stored under `prefix`, counting failures, including a key that could
+/// not be extracted, by reason.
+pub async fn verify_api_key(
+    state: &AppState,
+    api_key: Result<&str, AuthFailure>,
+    prefix: &str,
+    scope: &str,
+) -> Result<(), AuthFailure> {
+    let result = async {
+        let api_key = api_key?;
+
+        // Get Redis connection
+        let mut conn = state
+            .redis
+            .get_connection_manager()
+            .await
+            .map_err(|_| AuthFailure::StoreError)?;
+
+        // Check if API key exists in Redis
+        let key_exists: bool = conn
+            .exists(format!("{prefix}{api_key}"))
+            .await
+            .map_err(|_| AuthFailure::StoreError)?;
+
+        if key_exists {
+            Ok(())
+        } else {
+            Err(AuthFailure::UnknownKey)
+        }
     }
+    .await;
+
+    result.inspect_err(|failure| metrics::auth_failure(scope, failure.as_str()))
 }
 
 pub async fn validate_api_key(
@@ -42,26 +124,26 @@ pub async fn validate_api_key(
     req: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let api_key = extract_api_key_from_header(&req)?;
-
-    // Get Redis connection
-    let mut conn = state
-        .redis
-        .get_connection_manager()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Check if API key exists in Redis
-    let key_exists: bool = conn
-        .exists(format!("{API_KEY_PREFIX}{api_key}"))
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    check_api_key(&state, req.headers(), API_KEY_PREFIX, "standard").await?;
+    Ok(next.run(req).await)
+}
 
-    if key_exists {
-        Ok(next.run(req).await)
-    } else {
-        Err(StatusCode::UNAUTHORIZED)
+/// Lets devices authenticate with a verified client certificate instead of an API key. A request
+/// that carries an `Authorization` header is always checked as an API key.
+pub async fn validate_device(
+    State(state): State<Arc<AppState>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !req.headers().contains_key(AUTH_HEADER)
+        && let Some(certificate) = req.extensions().get::<ClientCertificate>().cloned()
+    {
+        req.extensions_mut()
+            .insert(AuthenticatedDevice(certificate.device_id));
+        return Ok(next.run(req).await);
     }
+    check_api_key(&state, req.headers(), API_KEY_PREFIX, "standard").await?;
+    Ok(next.run(req).await)
 }
 
 pub async fn validate_admin_api_key(
@@ -69,26 +151,18 @@ pub async fn validate_admin_api_key(
     req: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    let api_key = extract_api_key_from_header(&req)?;
-
-    // Get Redis connection
-    let mut conn = state
-        .redis
-        .get_connection_manager()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Check if Admin API key exists in Redis
-    let key_exists: bool = conn
-        .exists(format!("{API_ADMIN_KEY_PREFIX}{api_key}"))
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    check_api_key(&state, req.headers(), API_ADMIN_KEY_PREFIX, "admin").await?;
+    Ok(next.run(req).await)
+}
 
-    if key_exists {
-        Ok(next.run(req).await)
-    } else {
-        Err(StatusCode::UNAUTHORIZED)
-    }
+/// Updates the issued key gauges from the key sets.
+pub async fn record_key_counts(state: &AppState) -> anyhow::Result<()> {
+    let mut conn = state.redis.get_connection_manager().await?;
+    let standard: usize = conn.scard(ALL_API_KEYS).await?;
+    let admin: usize = conn.scard(ALL_ADMIN_KEYS).await?;
+    metrics::set_api_keys("standard", standard);
+    metrics::set_api_keys("admin", admin);
+    Ok(())
 }
 
 /// Generates a secure API key with the given prefix followed by base64-encoded random data
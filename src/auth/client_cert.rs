@@ -0,0 +1,78 @@
+use rustls::pki_types::CertificateDer;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// A client certificate the TLS layer verified against the configured CA, reduced to the device ID
+/// it names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientCertificate {
+    pub device_id: String,
+}
+
+impl ClientCertificate {
+    /// Reads the device ID from the certificate's first DNS subject alternative name, falling back
+    /// to its subject common name. `None` when it names neither.
+    pub fn from_der(der: &CertificateDer<'_>) -> Option<Self> {
+        let (_, cert) = X509Certificate::from_der(der.as_ref()).ok()?;
+        let san = cert
+            .subject_alternative_name()
+            .ok()
+            .flatten()
+            .and_then(|ext| {
+                ext.value.general_names.iter().find_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some(dns.to_string()),
+                    _ => None,
+                })
+            });
+        let device_id = san.or_else(|| {
+            cert.subject()
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(str::to_owned)
+        })?;
+        (!device_id.is_empty()).then_some(Self { device_id })
+    }
+}
+
+/// Set on requests a client certificate authenticated instead of an API key; handlers use it to
+/// hold the device to the identity its certificate names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthenticatedDevice(pub String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn certificate(params: rcgen::CertificateParams) -> CertificateDer<'static> {
+        let key = rcgen::KeyPair::generate().unwrap();
+        params.self_signed(&key).unwrap().der().clone()
+    }
+
+    #[test]
+    fn prefers_dns_subject_alternative_name() {
+        let mut params = rcgen::CertificateParams::new(vec!["sensor-42".to_string()]).unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "Sensor 42");
+        let cert = ClientCertificate::from_der(&certificate(params)).unwrap();
+        assert_eq!(cert.device_id, "sensor-42");
+    }
+
+    #[test]
+    fn falls_back_to_common_name() {
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "sensor-7");
+        let cert = ClientCertificate::from_der(&certificate(params)).unwrap();
+        assert_eq!(cert.device_id, "sensor-7");
+    }
+
+    #[test]
+    fn names_nothing() {
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        assert_eq!(ClientCertificate::from_der(&certificate(params)), None);
+    }
+}
@@ -1,7 +1,9 @@
 pub mod api_key;
+pub mod client_cert;
 
 // Re-export commonly used items
 pub use api_key::bootstrap_admin_key;
 pub use api_key::generate_api_key;
 pub use api_key::validate_admin_api_key;
 pub use api_key::validate_api_key;
+pub use api_key::validate_device;
@@ -9,6 +9,7 @@ fn main() {
         datum: 42.5,
         domain: Domain::SoundPressureLevel as i32,
         device_id: b"testdevice".to_vec(),
+        domain_name: String::new(),
     };
     let mut buf = Vec::new();
     msg.encode(&mut buf).expect("encode failed");
@@ -0,0 +1,83 @@
+use crate::config::{ConfigSource, InvalidSettings, Settings, layers};
+use clap::Parser;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Command-line flags. Settings come from the configuration file, then the environment, then
+/// these flags, each overriding the one before.
+#[derive(Debug, Parser)]
+#[command(version, about = "Sensor data ingestion into RedisTimeSeries")]
+pub struct Cli {
+    /// TOML or YAML file to read settings from.
+    #[arg(long, short, env = crate::consts::env::CONFIG_FILE_ENV_VAR)]
+    pub config: Option<PathBuf>,
+
+    /// IP and port to bind to.
+    #[arg(long)]
+    pub bind_address: Option<String>,
+
+    /// Redis connection URL.
+    #[arg(long)]
+    pub redis_url: Option<String>,
+
+    /// Minimum level logged.
+    #[arg(long)]
+    pub log_level: Option<String>,
+
+    /// `compact` or `json`.
+    #[arg(long)]
+    pub log_format: Option<String>,
+
+    /// Any other setting, e.g. `--set shutdown.drain_timeout_secs=30`; may be repeated.
+    #[arg(long = "set", value_name = "NAME=VALUE", value_parser = layers::parse_flag)]
+    pub overrides: Vec<(String, String)>,
+
+    /// Validate the configuration, report every problem found and exit.
+    #[arg(long)]
+    pub check_config: bool,
+
+    /// Print the effective configuration as TOML and exit.
+    #[arg(long)]
+    pub print_config: bool,
+}
+
+impl Cli {
+    /// The configuration file and these flags; the environment is read by `ConfigSource::layers`.
+    pub fn source(&self) -> ConfigSource {
+        ConfigSource {
+            file: self.config.clone(),
+            flags: self.flags(),
+        }
+    }
+
+    fn flags(&self) -> HashMap<String, String> {
+        let named = [
+            (crate::consts::env::BIND_ADDRESS_ENV_VAR, &self.bind_address),
+            (crate::consts::env::REDIS_URL_ENV_VAR, &self.redis_url),
+            (crate::consts::env::LOG_LEVEL_ENV_VAR, &self.log_level),
+            (crate::consts::env::LOG_FORMAT_ENV_VAR, &self.log_format),
+        ];
+        let mut flags: HashMap<String, String> = self.overrides.iter().cloned().collect();
+        for (name, value) in named {
+            if let Some(value) = value {
+                flags.insert(name.to_string(), value.clone());
+            }
+        }
+        flags
+    }
+}
+
+/// Checks what reading the settings cannot: that the files they name load.
+pub fn check_files(settings: &Settings) -> Result<(), InvalidSettings> {
+    let mut problems = InvalidSettings::default();
+    if let Some(tls) = &settings.tls
+        && let Err(e) = crate::tls::load(tls)
+    {
+        problems.push(format!("TLS: {e:#}"));
+    }
+    if problems.0.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
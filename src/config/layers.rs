@@ -0,0 +1,544 @@
+use super::{InvalidSettings, Settings};
+use crate::consts::env::*;
+use crate::domains::catalog::{Aggregation, DomainKind, DomainSpec, builtin_domains};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Table of a configuration file holding custom domain definitions, one table per domain.
+pub const DOMAINS_TABLE: &str = "domains";
+
+/// Every setting a configuration file or `--set` flag may name, as its environment variable.
+const KNOWN_SETTINGS: &[&str] = &[
+    ALERT_EVALUATION_INTERVAL_SECS_ENV_VAR,
+    ALERT_WEBHOOK_MAX_ATTEMPTS_ENV_VAR,
+    BIND_ADDRESS_ENV_VAR,
+    ENV_SENSOR_DATUM_PREFIX,
+    EXPECTED_BATCH_INTERVAL_SECS_ENV_VAR,
+    GRPC_BIND_ADDRESS_ENV_VAR,
+    INGEST_BATCH_SIZE_ENV_VAR,
+    INGEST_FLUSH_INTERVAL_MS_ENV_VAR,
+    INGEST_QUEUE_DEPTH_ENV_VAR,
+    INGEST_WRITERS_ENV_VAR,
+    LIVE_PUBSUB_CHANNEL_ENV_VAR,
+    LOG_FORMAT_ENV_VAR,
+    LOG_LEVEL_ENV_VAR,
+    NOISE_DAY_START_HOUR_ENV_VAR,
+    NOISE_EVENING_START_HOUR_ENV_VAR,
+    NOISE_LDN_NIGHT_START_HOUR_ENV_VAR,
+    NOISE_NIGHT_START_HOUR_ENV_VAR,
+    NOISE_TIMEZONE_ENV_VAR,
+    OFFLINE_AFTER_INTERVALS_ENV_VAR,
+    OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR,
+    OTEL_SERVICE_NAME_ENV_VAR,
+    REDIS_MODE_ENV_VAR,
+    REDIS_NODES_ENV_VAR,
+    REDIS_SENTINEL_MASTER_ENV_VAR,
+    REDIS_URL_ENV_VAR,
+    REJECT_UNREGISTERED_DEVICES_ENV_VAR,
+    RUST_LOG_ENV_VAR,
+    SAMPLE_STREAM_KEY_ENV_VAR,
+    SAMPLE_STREAM_MAXLEN_ENV_VAR,
+    SERIES_HASH_TAGS_ENV_VAR,
+    SHUTDOWN_DRAIN_TIMEOUT_SECS_ENV_VAR,
+    SHUTDOWN_READINESS_DELAY_SECS_ENV_VAR,
+    SPOOL_DIR_ENV_VAR,
+    SPOOL_MAX_BYTES_ENV_VAR,
+    SPOOL_SEGMENT_BYTES_ENV_VAR,
+    TLS_CERT_PATH_ENV_VAR,
+    TLS_CLIENT_CA_PATH_ENV_VAR,
+    TLS_KEY_PATH_ENV_VAR,
+    TLS_RELOAD_INTERVAL_SECS_ENV_VAR,
+];
+
+/// The syntax of a configuration file, chosen by its extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml" | "yml") => Ok(ConfigFormat::Yaml),
+            _ => anyhow::bail!("{} must end in .toml, .yaml or .yml", path.display()),
+        }
+    }
+}
+
+/// Where the settings come from, kept so they can be read again when reloading.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigSource {
+    pub file: Option<PathBuf>,
+    /// Settings given as command-line flags, keyed by environment variable name.
+    pub flags: HashMap<String, String>,
+}
+
+impl ConfigSource {
+    /// Reads the configuration file and the process environment afresh. A file that cannot be
+    /// read or parsed is reported among the settings' problems.
+    pub fn layers(&self) -> Layers {
+        let file = match &self.file {
+            Some(path) => read_file(path).unwrap_or_else(|e| FileLayer {
+                problems: vec![format!("{e:#}")],
+                ..Default::default()
+            }),
+            None => FileLayer::default(),
+        };
+        Layers {
+            file: file.vars,
+            env: std::env::vars().collect(),
+            flags: self.flags.clone(),
+            domains: file.domains,
+            problems: file.problems,
+        }
+    }
+}
+
+/// What a configuration file holds.
+#[derive(Debug, Default)]
+pub struct FileLayer {
+    /// Settings keyed by environment variable name.
+    pub vars: HashMap<String, String>,
+    /// Custom domains defined in `[domains.<NAME>]` tables.
+    pub domains: Vec<DomainSpec>,
+    /// Problems found in the file, such as unknown settings or invalid domains.
+    pub problems: Vec<String>,
+}
+
+/// A custom domain as a configuration file defines it; the table name is the domain's name.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct DomainSettings {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    description: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    unit: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retention_ms: Option<u64>,
+    #[serde(default)]
+    aggregation: Aggregation,
+    #[serde(default)]
+    kind: DomainKind,
+}
+
+impl DomainSettings {
+    fn into_spec(self, name: &str) -> DomainSpec {
+        DomainSpec {
+            name: name.to_string(),
+            description: self.description,
+            unit: self.unit,
+            min: self.min,
+            max: self.max,
+            retention_ms: self.retention_ms,
+            aggregation: self.aggregation,
+            kind: self.kind,
+            builtin: false,
+        }
+    }
+}
+
+impl From<&DomainSpec> for DomainSettings {
+    fn from(spec: &DomainSpec) -> Self {
+        Self {
+            description: spec.description.clone(),
+            unit: spec.unit.clone(),
+            min: spec.min,
+            max: spec.max,
+            retention_ms: spec.retention_ms,
+            aggregation: spec.aggregation,
+            kind: spec.kind,
+        }
+    }
+}
+
+/// Configuration sources, each keyed by environment variable name. Later layers win: a file's
+/// values are overridden by the environment, which is overridden by command-line flags.
+#[derive(Debug, Default)]
+pub struct Layers {
+    pub file: HashMap<String, String>,
+    pub env: HashMap<String, String>,
+    pub flags: HashMap<String, String>,
+    /// Custom domains defined in the file.
+    pub domains: Vec<DomainSpec>,
+    /// Problems found in the file, such as unknown settings, reported alongside invalid values.
+    pub problems: Vec<String>,
+}
+
+impl Layers {
+    pub fn merged(&self) -> HashMap<String, String> {
+        let mut vars = self.file.clone();
+        vars.extend(self.env.clone());
+        vars.extend(self.flags.clone());
+        vars
+    }
+
+    /// Reads the settings from the merged layers, reporting the file's problems and every invalid
+    /// value together.
+    pub fn settings(&self) -> anyhow::Result<Settings> {
+        let result = Settings::from_env_vars(&self.merged()).map(|settings| Settings {
+            domains: self.domains.clone(),
+            ..settings
+        });
+        if self.problems.is_empty() {
+            return result;
+        }
+        let mut problems = InvalidSettings(self.problems.clone());
+        if let Err(e) = result {
+            match e.downcast::<InvalidSettings>() {
+                Ok(invalid) => problems.0.extend(invalid.0),
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+        Err(problems.into())
+    }
+}
+
+/// Reads a TOML or YAML configuration file into a layer.
+///
+/// Keys are setting names in lower case, optionally grouped into tables whose names are joined
+/// with `_`: `[shutdown] drain_timeout_secs = 30` and `shutdown_drain_timeout_secs = 30` both set
+/// `SHUTDOWN_DRAIN_TIMEOUT_SECS`. Custom domains are defined in `[domains.<NAME>]` tables with the
+/// fields of `POST /api/domains`, and checked the same way.
+pub fn read_file(path: &Path) -> anyhow::Result<FileLayer> {
+    let format = ConfigFormat::from_path(path)?;
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_file(&contents, format).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+pub fn parse_file(contents: &str, format: ConfigFormat) -> anyhow::Result<FileLayer> {
+    let mut value = match format {
+        ConfigFormat::Toml => serde_json::to_value(toml::from_str::<toml::Table>(contents)?)?,
+        ConfigFormat::Yaml => match serde_yaml::from_str::<serde_json::Value>(contents)? {
+            // An empty YAML document is null.
+            serde_json::Value::Null => serde_json::Value::Object(Default::default()),
+            value => value,
+        },
+    };
+    let mut file = FileLayer::default();
+    let domains = match &mut value {
+        serde_json::Value::Object(table) => table.remove(DOMAINS_TABLE),
+        _ => None,
+    };
+    if let Some(domains) = domains {
+        parse_domains(domains, &mut file);
+    }
+    flatten("", &value, &mut file.vars, &mut file.problems);
+    Ok(file)
+}
+
+/// Reads the `[domains]` table, checking each definition as registering it would.
+fn parse_domains(domains: serde_json::Value, file: &mut FileLayer) {
+    let serde_json::Value::Object(domains) = domains else {
+        let problem = format!("{DOMAINS_TABLE} must be a table of domain definitions");
+        file.problems.push(problem);
+        return;
+    };
+    let builtins = builtin_domains();
+    for (name, definition) in domains {
+        let spec = match serde_json::from_value::<DomainSettings>(definition) {
+            Ok(settings) => settings.into_spec(&name),
+            Err(e) => {
+                file.problems.push(format!("{DOMAINS_TABLE}.{name}: {e}"));
+                continue;
+            }
+        };
+        if builtins.iter().any(|builtin| builtin.name == name) {
+            let problem = format!("{DOMAINS_TABLE}.{name}: built-in domains cannot be redefined");
+            file.problems.push(problem);
+        } else if let Err(msg) = spec.check_definition() {
+            file.problems.push(format!("{DOMAINS_TABLE}.{name}: {msg}"));
+        } else {
+            file.domains.push(spec);
+        }
+    }
+}
+
+fn flatten(
+    prefix: &str,
+    value: &serde_json::Value,
+    vars: &mut HashMap<String, String>,
+    problems: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    let scalar = match value {
+        Value::Object(table) => {
+            for (key, value) in table {
+                let name = if prefix.is_empty() {
+                    setting_name(key)
+                } else {
+                    format!("{prefix}_{}", setting_name(key))
+                };
+                flatten(&name, value, vars, problems);
+            }
+            return;
+        }
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => return,
+        Value::Array(_) => {
+            problems.push(format!("{prefix} must be a single value, not a list"));
+            return;
+        }
+    };
+    if prefix.is_empty() {
+        problems.push("the configuration must be a table of settings".to_string());
+    } else if !KNOWN_SETTINGS.contains(&prefix) {
+        problems.push(format!("unknown setting {prefix}"));
+    } else {
+        vars.insert(prefix.to_string(), scalar);
+    }
+}
+
+/// `shutdown.drain-timeout_secs` and `SHUTDOWN_DRAIN_TIMEOUT_SECS` name the same setting.
+fn setting_name(key: &str) -> String {
+    key.replace(['.', '-'], "_").to_ascii_uppercase()
+}
+
+/// Parses a `--set NAME=VALUE` flag, rejecting settings that do not exist.
+pub fn parse_flag(flag: &str) -> Result<(String, String), String> {
+    let (name, value) = flag
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got {flag:?}"))?;
+    let name = setting_name(name.trim());
+    if !KNOWN_SETTINGS.contains(&name.as_str()) {
+        return Err(format!("unknown setting {name}"));
+    }
+    Ok((name, value.to_string()))
+}
+
+/// Renders settings as a TOML file `read_file` accepts: flat keys, then a table per custom domain.
+pub fn to_toml(
+    vars: &BTreeMap<&'static str, String>,
+    domains: &[DomainSpec],
+) -> anyhow::Result<String> {
+    let mut table = toml::Table::new();
+    for (name, value) in vars {
+        table.insert(name.to_ascii_lowercase(), value.clone().into());
+    }
+    if !domains.is_empty() {
+        let mut definitions = toml::Table::new();
+        for spec in domains {
+            let definition = toml::Table::try_from(DomainSettings::from(spec))?;
+            definitions.insert(spec.name.clone(), definition.into());
+        }
+        table.insert(DOMAINS_TABLE.to_string(), definitions.into());
+    }
+    Ok(toml::to_string(&table)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_tables_and_flat_keys() {
+        let FileLayer { vars, problems, .. } = parse_file(
+            r#"
+            bind_address = "127.0.0.1:8080"
+            reject_unregistered_devices = true
+
+            [shutdown]
+            drain_timeout_secs = 30
+
+            [tls]
+            cert-path = "/etc/tls/tls.crt"
+            "#,
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        assert_eq!(vars["BIND_ADDRESS"], "127.0.0.1:8080");
+        assert_eq!(vars["REJECT_UNREGISTERED_DEVICES"], "true");
+        assert_eq!(vars["SHUTDOWN_DRAIN_TIMEOUT_SECS"], "30");
+        assert_eq!(vars["TLS_CERT_PATH"], "/etc/tls/tls.crt");
+    }
+
+    #[test]
+    fn yaml_reports_unknown_settings_and_lists() {
+        let FileLayer {
+            vars, mut problems, ..
+        } = parse_file(
+            "redis_url: redis://redis:6379\nnoise:\n  day_start_hour: 6\nbind_adress: x\nrust_log: [info]\n",
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(vars["REDIS_URL"], "redis://redis:6379");
+        assert_eq!(vars["NOISE_DAY_START_HOUR"], "6");
+        problems.sort();
+        assert_eq!(
+            problems,
+            vec![
+                "RUST_LOG must be a single value, not a list",
+                "unknown setting BIND_ADRESS",
+            ]
+        );
+        assert!(parse_file("", ConfigFormat::Yaml).unwrap().vars.is_empty());
+    }
+
+    #[test]
+    fn domains_are_read_and_checked() {
+        let file = parse_file(
+            r#"
+            [domains.DOMINANT_FREQUENCY]
+            unit = "Hz"
+            min = 0.0
+            max = 20000.0
+            aggregation = "max"
+
+            [domains.INVERTED]
+            min = 10.0
+            max = 1.0
+
+            [domains.lowercase]
+            unit = "Hz"
+
+            [domains.TEMPERATURE]
+            unit = "K"
+
+            [domains.TYPO]
+            units = "Hz"
+            "#,
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        assert!(file.vars.is_empty());
+        assert_eq!(file.domains.len(), 1);
+        let spec = &file.domains[0];
+        assert_eq!(spec.name, "DOMINANT_FREQUENCY");
+        assert_eq!(spec.max, Some(20000.0));
+        assert_eq!(spec.aggregation, Aggregation::Max);
+        assert!(!spec.builtin);
+
+        let mut problems = file.problems;
+        problems.sort();
+        assert_eq!(problems.len(), 4, "{problems:?}");
+        assert_eq!(
+            problems[0],
+            "domains.INVERTED: min must not be greater than max"
+        );
+        assert_eq!(
+            problems[1],
+            "domains.TEMPERATURE: built-in domains cannot be redefined"
+        );
+        assert!(problems[2].starts_with("domains.TYPO: unknown field `units`"));
+        assert!(problems[3].starts_with("domains.lowercase: domain name must start with"));
+    }
+
+    #[test]
+    fn unreadable_file_is_reported_with_other_problems() {
+        let source = ConfigSource {
+            file: Some(PathBuf::from("/nonexistent/signalstash.toml")),
+            flags: HashMap::from([("EXPECTED_BATCH_INTERVAL_SECS".to_string(), "0".to_string())]),
+        };
+        let err = source.layers().settings().err().unwrap();
+        let invalid = err.downcast_ref::<InvalidSettings>().unwrap();
+        assert_eq!(invalid.0.len(), 2, "{invalid}");
+        assert!(invalid.0[0].starts_with("failed to read /nonexistent/signalstash.toml"));
+    }
+
+    #[test]
+    fn flags_override_env_override_file() {
+        let mut layers = Layers::default();
+        layers
+            .file
+            .insert("BIND_ADDRESS".to_string(), "127.0.0.1:1".to_string());
+        layers
+            .file
+            .insert("LOG_LEVEL".to_string(), "debug".to_string());
+        layers
+            .file
+            .insert("REDIS_URL".to_string(), "redis://file".to_string());
+        layers
+            .env
+            .insert("BIND_ADDRESS".to_string(), "127.0.0.1:2".to_string());
+        layers
+            .env
+            .insert("LOG_LEVEL".to_string(), "warn".to_string());
+        let (name, value) = parse_flag("log-level=error").unwrap();
+        layers.flags.insert(name, value);
+
+        let settings = layers.settings().unwrap();
+        assert_eq!(settings.redis_url, "redis://file");
+        assert_eq!(settings.bind_address, "127.0.0.1:2");
+        assert_eq!(settings.log_level, tracing::Level::ERROR);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let mut layers = Layers::default();
+        layers
+            .problems
+            .push("unknown setting BIND_ADRESS".to_string());
+        layers
+            .env
+            .insert("BIND_ADDRESS".to_string(), "localhost".to_string());
+        layers
+            .env
+            .insert("EXPECTED_BATCH_INTERVAL_SECS".to_string(), "0".to_string());
+        layers
+            .env
+            .insert("NOISE_TIMEZONE".to_string(), "Mars/Olympus".to_string());
+
+        let err = layers.settings().err().unwrap();
+        let invalid = err.downcast_ref::<InvalidSettings>().unwrap();
+        assert_eq!(invalid.0.len(), 4, "{invalid}");
+        assert_eq!(invalid.0[0], "unknown setting BIND_ADRESS");
+    }
+
+    #[test]
+    fn flag_names_must_exist() {
+        assert!(parse_flag("no_such_setting=1").is_err());
+        assert!(parse_flag("redis_url").is_err());
+        assert_eq!(
+            parse_flag("shutdown.drain_timeout_secs=9").unwrap(),
+            ("SHUTDOWN_DRAIN_TIMEOUT_SECS".to_string(), "9".to_string())
+        );
+    }
+
+    #[test]
+    fn printed_config_reads_back() {
+        let mut vars = HashMap::new();
+        vars.insert("SAMPLE_STREAM_KEY".to_string(), "samples".to_string());
+        vars.insert("TLS_CERT_PATH".to_string(), "/tls.crt".to_string());
+        vars.insert("TLS_KEY_PATH".to_string(), "/tls.key".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        let domains = parse_file(
+            "[domains.DOMINANT_FREQUENCY]\nunit = \"Hz\"\nmin = 0.0\n",
+            ConfigFormat::Toml,
+        )
+        .unwrap()
+        .domains;
+
+        let printed = to_toml(&settings.to_env_vars(), &domains).unwrap();
+        let read = parse_file(&printed, ConfigFormat::Toml).unwrap();
+        assert!(read.problems.is_empty(), "{:?}", read.problems);
+        assert_eq!(read.domains, domains);
+        let reread = Settings::from_env_vars(&read.vars).unwrap();
+        assert_eq!(reread.to_env_vars(), settings.to_env_vars());
+    }
+
+    #[test]
+    fn printed_config_masks_passwords() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "REDIS_URL".to_string(),
+            "redis://:hunter2@redis:6379".to_string(),
+        );
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.to_env_vars()["REDIS_URL"],
+            "redis://:****@redis:6379"
+        );
+    }
+}
@@ -0,0 +1,1037 @@
+pub mod layers;
+pub mod reload;
+
+use crate::acoustics::NoisePeriods;
+use crate::consts::env::*;
+use crate::domains::DomainSpec;
+use crate::events::SampleStream;
+use crate::ingest::WriteQueueSettings;
+use crate::redis::{RedisMode, RedisTopology};
+use crate::series::SeriesKeys;
+use crate::spool::SpoolSettings;
+use crate::telemetry::LogFormat;
+use crate::tls::TlsSettings;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+
+// Re-export commonly used items
+pub use layers::{ConfigFormat, ConfigSource, Layers};
+pub use reload::ActiveConfig;
+
+pub struct Settings {
+    pub alert_evaluation_interval: Duration,
+    pub alert_webhook_max_attempts: u32,
+    pub bind_address: String,
+    /// Custom domains defined in the configuration file.
+    pub domains: Vec<DomainSpec>,
+    pub expected_batch_interval: Duration,
+    /// IP and port the gRPC service listens on; `None` serves HTTP only.
+    pub grpc_bind_address: Option<String>,
+    /// Redis pub/sub channel that relays live samples between replicas; `None` keeps them local.
+    pub live_pubsub_channel: Option<String>,
+    /// `RUST_LOG`-style directives, e.g. `info,signalstashrs::routes=debug`; when set they take
+    /// precedence over `log_level`.
+    pub log_filter: Option<String>,
+    pub log_format: LogFormat,
+    pub log_level: Level,
+    pub noise_periods: NoisePeriods,
+    pub offline_after_intervals: u32,
+    /// Base URL of an OTLP/HTTP collector traces are exported to; `None` disables export.
+    pub otlp_endpoint: Option<String>,
+    pub otel_service_name: String,
+    pub redis_mode: RedisMode,
+    /// Sentinel addresses in `sentinel` mode, seed nodes in `cluster` mode.
+    pub redis_nodes: Vec<String>,
+    /// Name of the master the sentinels monitor, in `sentinel` mode.
+    pub redis_sentinel_master: Option<String>,
+    /// Server in `standalone` mode; in `sentinel` mode, the credentials and database for the
+    /// master.
+    pub redis_url: String,
+    pub reject_unregistered_devices: bool,
+    /// Stream accepted samples are published to; `None` when publishing is disabled.
+    pub sample_stream: Option<SampleStream>,
+    pub sensor_datum_prefix: String,
+    /// Whether series keys put the device ID in a hash tag; required in `cluster` mode.
+    pub series_hash_tags: bool,
+    /// How long in-flight requests and background jobs get to finish once draining.
+    pub shutdown_drain_timeout: Duration,
+    /// How long the server keeps accepting requests after `/readyz` starts failing, so load
+    /// balancers stop routing to it first.
+    pub shutdown_readiness_delay: Duration,
+    /// Where samples are kept while Redis is unreachable; `None` answers those requests with an
+    /// error instead.
+    pub spool: Option<SpoolSettings>,
+    /// Certificate and key to terminate TLS with; `None` serves plain HTTP.
+    pub tls: Option<TlsSettings>,
+    pub write_queue: WriteQueueSettings,
+}
+
+impl Settings {
+    /// Reads the settings from variables named like their environment variables, e.g. the merged
+    /// [`Layers`]. Every invalid or inconsistent value is reported in one [`InvalidSettings`].
+    pub fn from_env_vars(vars: &HashMap<String, String>) -> Result<Self, anyhow::Error> {
+        let mut problems = InvalidSettings::default();
+
+        let log_level = problems.parse_or(
+            vars,
+            LOG_LEVEL_ENV_VAR,
+            DEFAULT_LOG_LEVEL.parse().unwrap_or(Level::INFO),
+        );
+        let log_filter = vars
+            .get(RUST_LOG_ENV_VAR)
+            .filter(|directives| !directives.is_empty())
+            .cloned();
+        if let Some(directives) = &log_filter
+            && let Err(e) = EnvFilter::try_new(directives)
+        {
+            problems.invalid(RUST_LOG_ENV_VAR, directives, e);
+        }
+        let log_format = problems.parse_or(vars, LOG_FORMAT_ENV_VAR, LogFormat::default());
+        let bind_address = vars
+            .get(BIND_ADDRESS_ENV_VAR)
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string());
+        if let Err(e) = bind_address.parse::<SocketAddr>() {
+            problems.invalid(BIND_ADDRESS_ENV_VAR, &bind_address, e);
+        }
+        let grpc_bind_address = vars
+            .get(GRPC_BIND_ADDRESS_ENV_VAR)
+            .filter(|address| !address.is_empty())
+            .cloned();
+        if let Some(address) = &grpc_bind_address {
+            if let Err(e) = address.parse::<SocketAddr>() {
+                problems.invalid(GRPC_BIND_ADDRESS_ENV_VAR, address, e);
+            } else if *address == bind_address {
+                problems.push(format!(
+                    "{GRPC_BIND_ADDRESS_ENV_VAR} must differ from {BIND_ADDRESS_ENV_VAR}"
+                ));
+            }
+        }
+        let redis_url = vars
+            .get(REDIS_URL_ENV_VAR)
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_REDIS_URL.to_string());
+        if let Err(e) = redis::IntoConnectionInfo::into_connection_info(redis_url.as_str()) {
+            problems.invalid(REDIS_URL_ENV_VAR, &redact_url(&redis_url), e);
+        }
+        let redis_mode = problems.parse_or(vars, REDIS_MODE_ENV_VAR, RedisMode::default());
+        let redis_nodes: Vec<String> = vars
+            .get(REDIS_NODES_ENV_VAR)
+            .map(|nodes| {
+                nodes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|node| !node.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for node in &redis_nodes {
+            if let Err(e) = redis::IntoConnectionInfo::into_connection_info(node.as_str()) {
+                problems.invalid(REDIS_NODES_ENV_VAR, &redact_url(node), e);
+            }
+        }
+        let redis_sentinel_master = vars
+            .get(REDIS_SENTINEL_MASTER_ENV_VAR)
+            .filter(|name| !name.is_empty())
+            .cloned();
+        let series_hash_tags = problems.parse_or(
+            vars,
+            SERIES_HASH_TAGS_ENV_VAR,
+            redis_mode == RedisMode::Cluster,
+        );
+        check_redis_mode(
+            redis_mode,
+            &redis_nodes,
+            redis_sentinel_master.as_deref(),
+            series_hash_tags,
+            &mut problems,
+        );
+        let sensor_datum_prefix = vars
+            .get(ENV_SENSOR_DATUM_PREFIX)
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_SENSOR_DATUM_PREFIX.to_string());
+        let reject_unregistered_devices = problems.parse_or(
+            vars,
+            REJECT_UNREGISTERED_DEVICES_ENV_VAR,
+            DEFAULT_REJECT_UNREGISTERED_DEVICES,
+        );
+        let expected_batch_interval_secs: u64 = problems.positive(
+            vars,
+            EXPECTED_BATCH_INTERVAL_SECS_ENV_VAR,
+            DEFAULT_EXPECTED_BATCH_INTERVAL_SECS,
+        );
+        // One interval would call a device offline as soon as a single batch is late.
+        let offline_after_intervals = problems.at_least(
+            vars,
+            OFFLINE_AFTER_INTERVALS_ENV_VAR,
+            DEFAULT_OFFLINE_AFTER_INTERVALS,
+            MIN_OFFLINE_AFTER_INTERVALS,
+        );
+        let alert_evaluation_interval_secs: u64 = problems.positive(
+            vars,
+            ALERT_EVALUATION_INTERVAL_SECS_ENV_VAR,
+            DEFAULT_ALERT_EVALUATION_INTERVAL_SECS,
+        );
+        let alert_webhook_max_attempts: u32 = problems.positive(
+            vars,
+            ALERT_WEBHOOK_MAX_ATTEMPTS_ENV_VAR,
+            DEFAULT_ALERT_WEBHOOK_MAX_ATTEMPTS,
+        );
+        let sample_stream_max_len: usize = problems.positive(
+            vars,
+            SAMPLE_STREAM_MAXLEN_ENV_VAR,
+            DEFAULT_SAMPLE_STREAM_MAXLEN,
+        );
+        let sample_stream = vars
+            .get(SAMPLE_STREAM_KEY_ENV_VAR)
+            .filter(|key| !key.is_empty())
+            .map(|key| SampleStream {
+                key: key.clone(),
+                max_len: sample_stream_max_len,
+            });
+        let live_pubsub_channel = vars
+            .get(LIVE_PUBSUB_CHANNEL_ENV_VAR)
+            .filter(|channel| !channel.is_empty())
+            .cloned();
+        let shutdown_drain_timeout_secs: u64 = problems.positive(
+            vars,
+            SHUTDOWN_DRAIN_TIMEOUT_SECS_ENV_VAR,
+            DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_SECS,
+        );
+        let shutdown_readiness_delay_secs: u64 = problems.parse_or(
+            vars,
+            SHUTDOWN_READINESS_DELAY_SECS_ENV_VAR,
+            DEFAULT_SHUTDOWN_READINESS_DELAY_SECS,
+        );
+        let write_queue = WriteQueueSettings {
+            depth: problems.positive(vars, INGEST_QUEUE_DEPTH_ENV_VAR, DEFAULT_INGEST_QUEUE_DEPTH),
+            writers: problems.positive(vars, INGEST_WRITERS_ENV_VAR, DEFAULT_INGEST_WRITERS),
+            batch_size: problems.positive(
+                vars,
+                INGEST_BATCH_SIZE_ENV_VAR,
+                DEFAULT_INGEST_BATCH_SIZE,
+            ),
+            flush_interval: Duration::from_millis(problems.positive(
+                vars,
+                INGEST_FLUSH_INTERVAL_MS_ENV_VAR,
+                DEFAULT_INGEST_FLUSH_INTERVAL_MS,
+            )),
+        };
+        let spool = spool_settings(vars, &mut problems);
+        let tls = tls_settings(vars, &mut problems);
+        let otlp_endpoint = vars
+            .get(OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR)
+            .filter(|endpoint| !endpoint.is_empty())
+            .cloned();
+        if let Some(endpoint) = &otlp_endpoint
+            && let Err(e) = reqwest::Url::parse(endpoint)
+        {
+            problems.invalid(OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR, endpoint, e);
+        }
+        let otel_service_name = vars
+            .get(OTEL_SERVICE_NAME_ENV_VAR)
+            .filter(|name| !name.is_empty())
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_OTEL_SERVICE_NAME.to_string());
+        let defaults = NoisePeriods::default();
+        let noise_periods = NoisePeriods {
+            timezone: problems.parse_or(vars, NOISE_TIMEZONE_ENV_VAR, defaults.timezone),
+            day_start: problems.parse_or(vars, NOISE_DAY_START_HOUR_ENV_VAR, defaults.day_start),
+            evening_start: problems.parse_or(
+                vars,
+                NOISE_EVENING_START_HOUR_ENV_VAR,
+                defaults.evening_start,
+            ),
+            night_start: problems.parse_or(
+                vars,
+                NOISE_NIGHT_START_HOUR_ENV_VAR,
+                defaults.night_start,
+            ),
+            ldn_night_start: problems.parse_or(
+                vars,
+                NOISE_LDN_NIGHT_START_HOUR_ENV_VAR,
+                defaults.ldn_night_start,
+            ),
+        };
+        if let Err(msg) = noise_periods.check() {
+            problems.push(format!("invalid noise periods: {msg}"));
+        }
+
+        if !problems.0.is_empty() {
+            return Err(problems.into());
+        }
+        Ok(Self {
+            alert_evaluation_interval: Duration::from_secs(alert_evaluation_interval_secs),
+            alert_webhook_max_attempts,
+            bind_address,
+            domains: Vec::new(),
+            expected_batch_interval: Duration::from_secs(expected_batch_interval_secs),
+            grpc_bind_address,
+            live_pubsub_channel,
+            log_filter,
+            log_format,
+            log_level,
+            noise_periods,
+            offline_after_intervals,
+            otlp_endpoint,
+            otel_service_name,
+            redis_mode,
+            redis_nodes,
+            redis_sentinel_master,
+            redis_url,
+            reject_unregistered_devices,
+            sample_stream,
+            sensor_datum_prefix,
+            series_hash_tags,
+            shutdown_drain_timeout: Duration::from_secs(shutdown_drain_timeout_secs),
+            shutdown_readiness_delay: Duration::from_secs(shutdown_readiness_delay_secs),
+            spool,
+            tls,
+            write_queue,
+        })
+    }
+
+    /// The effective settings, defaults included, keyed by environment variable name; reading them
+    /// back gives the same settings. Passwords in URLs are masked.
+    pub fn to_env_vars(&self) -> BTreeMap<&'static str, String> {
+        let secs = |d: Duration| d.as_secs().to_string();
+        let mut vars = BTreeMap::from([
+            (
+                ALERT_EVALUATION_INTERVAL_SECS_ENV_VAR,
+                secs(self.alert_evaluation_interval),
+            ),
+            (
+                ALERT_WEBHOOK_MAX_ATTEMPTS_ENV_VAR,
+                self.alert_webhook_max_attempts.to_string(),
+            ),
+            (BIND_ADDRESS_ENV_VAR, self.bind_address.clone()),
+            (
+                EXPECTED_BATCH_INTERVAL_SECS_ENV_VAR,
+                secs(self.expected_batch_interval),
+            ),
+            (
+                INGEST_BATCH_SIZE_ENV_VAR,
+                self.write_queue.batch_size.to_string(),
+            ),
+            (
+                INGEST_FLUSH_INTERVAL_MS_ENV_VAR,
+                self.write_queue.flush_interval.as_millis().to_string(),
+            ),
+            (
+                INGEST_QUEUE_DEPTH_ENV_VAR,
+                self.write_queue.depth.to_string(),
+            ),
+            (INGEST_WRITERS_ENV_VAR, self.write_queue.writers.to_string()),
+            (LOG_FORMAT_ENV_VAR, self.log_format.as_str().to_string()),
+            (LOG_LEVEL_ENV_VAR, self.log_level.to_string()),
+            (
+                NOISE_DAY_START_HOUR_ENV_VAR,
+                self.noise_periods.day_start.to_string(),
+            ),
+            (
+                NOISE_EVENING_START_HOUR_ENV_VAR,
+                self.noise_periods.evening_start.to_string(),
+            ),
+            (
+                NOISE_LDN_NIGHT_START_HOUR_ENV_VAR,
+                self.noise_periods.ldn_night_start.to_string(),
+            ),
+            (
+                NOISE_NIGHT_START_HOUR_ENV_VAR,
+                self.noise_periods.night_start.to_string(),
+            ),
+            (
+                NOISE_TIMEZONE_ENV_VAR,
+                self.noise_periods.timezone.name().to_string(),
+            ),
+            (
+                OFFLINE_AFTER_INTERVALS_ENV_VAR,
+                self.offline_after_intervals.to_string(),
+            ),
+            (OTEL_SERVICE_NAME_ENV_VAR, self.otel_service_name.clone()),
+            (REDIS_MODE_ENV_VAR, self.redis_mode.to_string()),
+            (REDIS_URL_ENV_VAR, redact_url(&self.redis_url)),
+            (
+                REJECT_UNREGISTERED_DEVICES_ENV_VAR,
+                self.reject_unregistered_devices.to_string(),
+            ),
+            (ENV_SENSOR_DATUM_PREFIX, self.sensor_datum_prefix.clone()),
+            (SERIES_HASH_TAGS_ENV_VAR, self.series_hash_tags.to_string()),
+            (
+                SHUTDOWN_DRAIN_TIMEOUT_SECS_ENV_VAR,
+                secs(self.shutdown_drain_timeout),
+            ),
+            (
+                SHUTDOWN_READINESS_DELAY_SECS_ENV_VAR,
+                secs(self.shutdown_readiness_delay),
+            ),
+        ]);
+        if let Some(address) = &self.grpc_bind_address {
+            vars.insert(GRPC_BIND_ADDRESS_ENV_VAR, address.clone());
+        }
+        if let Some(channel) = &self.live_pubsub_channel {
+            vars.insert(LIVE_PUBSUB_CHANNEL_ENV_VAR, channel.clone());
+        }
+        if !self.redis_nodes.is_empty() {
+            let nodes: Vec<String> = self.redis_nodes.iter().map(|n| redact_url(n)).collect();
+            vars.insert(REDIS_NODES_ENV_VAR, nodes.join(","));
+        }
+        if let Some(master) = &self.redis_sentinel_master {
+            vars.insert(REDIS_SENTINEL_MASTER_ENV_VAR, master.clone());
+        }
+        if let Some(directives) = &self.log_filter {
+            vars.insert(RUST_LOG_ENV_VAR, directives.clone());
+        }
+        if let Some(endpoint) = &self.otlp_endpoint {
+            vars.insert(OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR, redact_url(endpoint));
+        }
+        if let Some(stream) = &self.sample_stream {
+            vars.insert(SAMPLE_STREAM_KEY_ENV_VAR, stream.key.clone());
+            vars.insert(SAMPLE_STREAM_MAXLEN_ENV_VAR, stream.max_len.to_string());
+        }
+        if let Some(spool) = &self.spool {
+            vars.insert(SPOOL_DIR_ENV_VAR, spool.dir.display().to_string());
+            vars.insert(SPOOL_MAX_BYTES_ENV_VAR, spool.max_bytes.to_string());
+            vars.insert(SPOOL_SEGMENT_BYTES_ENV_VAR, spool.segment_bytes.to_string());
+        }
+        if let Some(tls) = &self.tls {
+            let path = |p: &PathBuf| p.display().to_string();
+            vars.insert(TLS_CERT_PATH_ENV_VAR, path(&tls.cert_path));
+            vars.insert(TLS_KEY_PATH_ENV_VAR, path(&tls.key_path));
+            if let Some(ca) = &tls.client_ca_path {
+                vars.insert(TLS_CLIENT_CA_PATH_ENV_VAR, path(ca));
+            }
+            vars.insert(TLS_RELOAD_INTERVAL_SECS_ENV_VAR, secs(tls.reload_interval));
+        }
+        vars
+    }
+
+    pub fn redis_topology(&self) -> RedisTopology {
+        match self.redis_mode {
+            RedisMode::Standalone => RedisTopology::Standalone {
+                url: self.redis_url.clone(),
+            },
+            RedisMode::Sentinel => RedisTopology::Sentinel {
+                master_name: self.redis_sentinel_master.clone().unwrap_or_default(),
+                sentinels: self.redis_nodes.clone(),
+                url: self.redis_url.clone(),
+            },
+            RedisMode::Cluster => RedisTopology::Cluster {
+                nodes: self.redis_nodes.clone(),
+            },
+        }
+    }
+
+    pub fn series_keys(&self) -> SeriesKeys {
+        SeriesKeys {
+            prefix: self.sensor_datum_prefix.clone(),
+            hash_tags: self.series_hash_tags,
+        }
+    }
+}
+
+/// Every problem found while reading the settings, so a bad deployment is fixed in one round trip
+/// rather than one error per restart.
+#[derive(Debug, Default, PartialEq)]
+pub struct InvalidSettings(pub Vec<String>);
+
+impl fmt::Display for InvalidSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidSettings {}
+
+impl InvalidSettings {
+    pub fn push(&mut self, problem: String) {
+        self.0.push(problem);
+    }
+
+    fn invalid(&mut self, name: &str, value: &str, error: impl fmt::Display) {
+        self.push(format!("invalid value for {name}: {value:?}: {error}"));
+    }
+
+    /// Parses an optional variable, falling back to `default` when it is not set or invalid.
+    fn parse_or<T>(&mut self, vars: &HashMap<String, String>, name: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match vars.get(name).map(|value| (value, value.parse())) {
+            Some((_, Ok(value))) => value,
+            Some((value, Err(e))) => {
+                self.invalid(name, value, e);
+                default
+            }
+            None => default,
+        }
+    }
+
+    /// Like `parse_or`, for counts and intervals that must be greater than zero.
+    fn positive<T>(&mut self, vars: &HashMap<String, String>, name: &str, default: T) -> T
+    where
+        T: FromStr + Default + PartialEq,
+        T::Err: fmt::Display,
+    {
+        let value = self.parse_or(vars, name, default);
+        if value == T::default() {
+            self.push(format!("{name} must be greater than zero"));
+        }
+        value
+    }
+
+    /// Like `parse_or`, for values that must be at least `min`.
+    fn at_least<T>(&mut self, vars: &HashMap<String, String>, name: &str, default: T, min: T) -> T
+    where
+        T: FromStr + PartialOrd + fmt::Display,
+        T::Err: fmt::Display,
+    {
+        let value = self.parse_or(vars, name, default);
+        if value < min {
+            self.push(format!("{name} must be at least {min}"));
+        }
+        value
+    }
+}
+
+/// Spooling is on when a directory is set. A segment larger than the whole spool could never be
+/// written, so that is refused.
+fn spool_settings(
+    vars: &HashMap<String, String>,
+    problems: &mut InvalidSettings,
+) -> Option<SpoolSettings> {
+    let max_bytes: u64 = problems.positive(vars, SPOOL_MAX_BYTES_ENV_VAR, DEFAULT_SPOOL_MAX_BYTES);
+    let segment_bytes: u64 = problems.positive(
+        vars,
+        SPOOL_SEGMENT_BYTES_ENV_VAR,
+        DEFAULT_SPOOL_SEGMENT_BYTES,
+    );
+    if segment_bytes > max_bytes {
+        problems.push(format!(
+            "{SPOOL_SEGMENT_BYTES_ENV_VAR} must not exceed {SPOOL_MAX_BYTES_ENV_VAR}"
+        ));
+    }
+    let dir = vars.get(SPOOL_DIR_ENV_VAR).filter(|dir| !dir.is_empty())?;
+    Some(SpoolSettings {
+        dir: PathBuf::from(dir),
+        max_bytes,
+        segment_bytes,
+    })
+}
+
+/// TLS is on when both the certificate and key paths are set; setting only one of them, or a
+/// client CA without them, is an error rather than a silent fallback to plain HTTP.
+fn tls_settings(
+    vars: &HashMap<String, String>,
+    problems: &mut InvalidSettings,
+) -> Option<TlsSettings> {
+    let path = |name: &str| {
+        vars.get(name)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    };
+    let cert_path = path(TLS_CERT_PATH_ENV_VAR);
+    let key_path = path(TLS_KEY_PATH_ENV_VAR);
+    let client_ca_path = path(TLS_CLIENT_CA_PATH_ENV_VAR);
+    let reload_interval_secs: u64 = problems.positive(
+        vars,
+        TLS_RELOAD_INTERVAL_SECS_ENV_VAR,
+        DEFAULT_TLS_RELOAD_INTERVAL_SECS,
+    );
+
+    match (cert_path, key_path) {
+        (Some(cert_path), Some(key_path)) => Some(TlsSettings {
+            cert_path,
+            key_path,
+            client_ca_path,
+            reload_interval: Duration::from_secs(reload_interval_secs),
+        }),
+        (None, None) => {
+            if client_ca_path.is_some() {
+                problems.push(format!(
+                    "{TLS_CLIENT_CA_PATH_ENV_VAR} requires {TLS_CERT_PATH_ENV_VAR} and {TLS_KEY_PATH_ENV_VAR}"
+                ));
+            }
+            None
+        }
+        _ => {
+            problems.push(format!(
+                "{TLS_CERT_PATH_ENV_VAR} and {TLS_KEY_PATH_ENV_VAR} must be set together"
+            ));
+            None
+        }
+    }
+}
+
+/// Sentinel needs the master's name and its sentinels, Cluster its seed nodes and hash-tagged
+/// series keys, without which a device's series would be spread over slots.
+fn check_redis_mode(
+    mode: RedisMode,
+    nodes: &[String],
+    sentinel_master: Option<&str>,
+    hash_tags: bool,
+    problems: &mut InvalidSettings,
+) {
+    match mode {
+        RedisMode::Standalone => {}
+        RedisMode::Sentinel => {
+            if sentinel_master.is_none() {
+                problems.push(format!(
+                    "{REDIS_MODE_ENV_VAR}=sentinel requires {REDIS_SENTINEL_MASTER_ENV_VAR}"
+                ));
+            }
+            if nodes.is_empty() {
+                problems.push(format!(
+                    "{REDIS_MODE_ENV_VAR}=sentinel requires the sentinels in {REDIS_NODES_ENV_VAR}"
+                ));
+            }
+        }
+        RedisMode::Cluster => {
+            if nodes.is_empty() {
+                problems.push(format!(
+                    "{REDIS_MODE_ENV_VAR}=cluster requires at least one node in {REDIS_NODES_ENV_VAR}"
+                ));
+            }
+            if !hash_tags {
+                problems.push(format!(
+                    "{REDIS_MODE_ENV_VAR}=cluster requires {SERIES_HASH_TAGS_ENV_VAR}=true"
+                ));
+            }
+        }
+    }
+}
+
+/// Masks the password in a URL such as `redis://:secret@host`, leaving anything unparsable as is.
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some("****"));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tracing::Level;
+
+    #[test]
+    fn settings_from_env_vars_defaults() {
+        let vars = HashMap::new();
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.bind_address, "0.0.0.0:20120");
+        assert_eq!(settings.log_level, Level::INFO);
+        assert_eq!(settings.redis_url, "redis://localhost:6379");
+        assert_eq!(settings.redis_mode, RedisMode::Standalone);
+        assert!(!settings.series_hash_tags);
+        assert_eq!(settings.sensor_datum_prefix, DEFAULT_SENSOR_DATUM_PREFIX);
+        assert!(!settings.reject_unregistered_devices);
+        assert_eq!(settings.expected_batch_interval, Duration::from_secs(60));
+        assert_eq!(settings.offline_after_intervals, 5);
+        assert_eq!(settings.noise_periods, NoisePeriods::default());
+        assert_eq!(settings.alert_evaluation_interval, Duration::from_secs(30));
+        assert_eq!(settings.alert_webhook_max_attempts, 5);
+        assert!(settings.sample_stream.is_none());
+        assert!(settings.grpc_bind_address.is_none());
+        assert!(settings.live_pubsub_channel.is_none());
+        assert!(settings.otlp_endpoint.is_none());
+        assert_eq!(settings.log_format, LogFormat::Compact);
+        assert!(settings.log_filter.is_none());
+        assert_eq!(settings.shutdown_drain_timeout, Duration::from_secs(20));
+        assert_eq!(settings.shutdown_readiness_delay, Duration::from_secs(5));
+        assert!(settings.spool.is_none());
+        assert_eq!(
+            settings.write_queue,
+            WriteQueueSettings {
+                depth: 10_000,
+                writers: 4,
+                batch_size: 500,
+                flush_interval: Duration::from_millis(5),
+            }
+        );
+        assert!(settings.tls.is_none());
+        assert_eq!(settings.otel_service_name, "signalstashrs");
+    }
+
+    #[test]
+    fn settings_from_env_vars_custom() {
+        let mut vars = HashMap::new();
+        vars.insert("LOG_LEVEL".to_string(), "DEBUG".to_string());
+        vars.insert("BIND_ADDRESS".to_string(), "127.0.0.1:12345".to_string());
+        vars.insert("REDIS_URL".to_string(), "redis://custom:1234".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.bind_address, "127.0.0.1:12345");
+        assert_eq!(settings.log_level, Level::DEBUG);
+        assert_eq!(settings.redis_url, "redis://custom:1234");
+        assert_eq!(settings.sensor_datum_prefix, DEFAULT_SENSOR_DATUM_PREFIX);
+    }
+
+    #[test]
+    fn settings_from_env_vars_invalid_log_level() {
+        let mut vars = HashMap::new();
+        vars.insert("LOG_LEVEL".to_string(), "INVALID".to_string());
+        let result = Settings::from_env_vars(&vars);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sensor_datum_prefix_custom() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            ENV_SENSOR_DATUM_PREFIX.to_string(),
+            "customprefix".to_string(),
+        );
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.sensor_datum_prefix, "customprefix");
+    }
+
+    #[test]
+    fn reject_unregistered_devices_custom() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "REJECT_UNREGISTERED_DEVICES".to_string(),
+            "true".to_string(),
+        );
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert!(settings.reject_unregistered_devices);
+    }
+
+    #[test]
+    fn reject_unregistered_devices_invalid() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "REJECT_UNREGISTERED_DEVICES".to_string(),
+            "maybe".to_string(),
+        );
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn device_liveness_custom() {
+        let mut vars = HashMap::new();
+        vars.insert("EXPECTED_BATCH_INTERVAL_SECS".to_string(), "10".to_string());
+        vars.insert("OFFLINE_AFTER_INTERVALS".to_string(), "3".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.expected_batch_interval, Duration::from_secs(10));
+        assert_eq!(settings.offline_after_intervals, 3);
+    }
+
+    #[test]
+    fn offline_after_intervals_below_two_is_rejected() {
+        for intervals in ["0", "1"] {
+            let mut vars = HashMap::new();
+            vars.insert("OFFLINE_AFTER_INTERVALS".to_string(), intervals.to_string());
+            let problems = Settings::from_env_vars(&vars)
+                .err()
+                .unwrap()
+                .downcast::<InvalidSettings>()
+                .unwrap();
+            assert_eq!(
+                problems.0,
+                vec!["OFFLINE_AFTER_INTERVALS must be at least 2".to_string()]
+            );
+        }
+    }
+
+    #[test]
+    fn expected_batch_interval_zero_is_rejected() {
+        let mut vars = HashMap::new();
+        vars.insert("EXPECTED_BATCH_INTERVAL_SECS".to_string(), "0".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn logging_custom_and_invalid() {
+        let mut vars = HashMap::new();
+        vars.insert("LOG_FORMAT".to_string(), "json".to_string());
+        vars.insert(
+            "RUST_LOG".to_string(),
+            "info,signalstashrs::routes=debug".to_string(),
+        );
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.log_format, LogFormat::Json);
+        assert_eq!(
+            settings.log_filter.as_deref(),
+            Some("info,signalstashrs::routes=debug")
+        );
+
+        let mut vars = HashMap::new();
+        vars.insert("LOG_FORMAT".to_string(), "xml".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+
+        let mut vars = HashMap::new();
+        vars.insert("RUST_LOG".to_string(), "signalstashrs=loud".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn shutdown_custom_and_invalid() {
+        let mut vars = HashMap::new();
+        vars.insert("SHUTDOWN_DRAIN_TIMEOUT_SECS".to_string(), "45".to_string());
+        vars.insert("SHUTDOWN_READINESS_DELAY_SECS".to_string(), "0".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.shutdown_drain_timeout, Duration::from_secs(45));
+        assert_eq!(settings.shutdown_readiness_delay, Duration::ZERO);
+
+        let mut vars = HashMap::new();
+        vars.insert("SHUTDOWN_DRAIN_TIMEOUT_SECS".to_string(), "0".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn otlp_custom() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "OTEL_EXPORTER_OTLP_ENDPOINT".to_string(),
+            "http://collector:4318".to_string(),
+        );
+        vars.insert("OTEL_SERVICE_NAME".to_string(), "ingest-eu".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.otlp_endpoint.as_deref(),
+            Some("http://collector:4318")
+        );
+        assert_eq!(settings.otel_service_name, "ingest-eu");
+    }
+
+    #[test]
+    fn noise_periods_custom() {
+        let mut vars = HashMap::new();
+        vars.insert("NOISE_TIMEZONE".to_string(), "Europe/Amsterdam".to_string());
+        vars.insert("NOISE_DAY_START_HOUR".to_string(), "6".to_string());
+        vars.insert("NOISE_EVENING_START_HOUR".to_string(), "18".to_string());
+        vars.insert("NOISE_NIGHT_START_HOUR".to_string(), "22".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.noise_periods.timezone,
+            chrono_tz::Europe::Amsterdam
+        );
+        assert_eq!(settings.noise_periods.day_start, 6);
+        assert_eq!(settings.noise_periods.evening_start, 18);
+        assert_eq!(settings.noise_periods.night_start, 22);
+        assert_eq!(settings.noise_periods.ldn_night_start, 22);
+    }
+
+    #[test]
+    fn noise_periods_invalid() {
+        let mut vars = HashMap::new();
+        vars.insert("NOISE_TIMEZONE".to_string(), "Mars/Olympus".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+
+        let mut vars = HashMap::new();
+        vars.insert("NOISE_EVENING_START_HOUR".to_string(), "5".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn alerting_custom_and_invalid() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "ALERT_EVALUATION_INTERVAL_SECS".to_string(),
+            "10".to_string(),
+        );
+        vars.insert("ALERT_WEBHOOK_MAX_ATTEMPTS".to_string(), "3".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.alert_evaluation_interval, Duration::from_secs(10));
+        assert_eq!(settings.alert_webhook_max_attempts, 3);
+
+        let mut vars = HashMap::new();
+        vars.insert("ALERT_WEBHOOK_MAX_ATTEMPTS".to_string(), "0".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn tls_custom() {
+        let mut vars = HashMap::new();
+        vars.insert("TLS_CERT_PATH".to_string(), "/etc/tls/tls.crt".to_string());
+        vars.insert("TLS_KEY_PATH".to_string(), "/etc/tls/tls.key".to_string());
+        vars.insert(
+            "TLS_CLIENT_CA_PATH".to_string(),
+            "/etc/tls/ca.crt".to_string(),
+        );
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.tls,
+            Some(TlsSettings {
+                cert_path: PathBuf::from("/etc/tls/tls.crt"),
+                key_path: PathBuf::from("/etc/tls/tls.key"),
+                client_ca_path: Some(PathBuf::from("/etc/tls/ca.crt")),
+                reload_interval: Duration::from_secs(30),
+            })
+        );
+    }
+
+    #[test]
+    fn tls_incomplete() {
+        let mut vars = HashMap::new();
+        vars.insert("TLS_CERT_PATH".to_string(), "/etc/tls/tls.crt".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+
+        let mut vars = HashMap::new();
+        vars.insert(
+            "TLS_CLIENT_CA_PATH".to_string(),
+            "/etc/tls/ca.crt".to_string(),
+        );
+        assert!(Settings::from_env_vars(&vars).is_err());
+
+        let mut vars = HashMap::new();
+        vars.insert("TLS_RELOAD_INTERVAL_SECS".to_string(), "0".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn sample_stream_custom() {
+        let mut vars = HashMap::new();
+        vars.insert("SAMPLE_STREAM_KEY".to_string(), "samples".to_string());
+        vars.insert("SAMPLE_STREAM_MAXLEN".to_string(), "5000".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.sample_stream,
+            Some(SampleStream {
+                key: "samples".to_string(),
+                max_len: 5000,
+            })
+        );
+
+        let mut vars = HashMap::new();
+        vars.insert("SAMPLE_STREAM_MAXLEN".to_string(), "0".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn grpc_custom_and_invalid() {
+        let mut vars = HashMap::new();
+        vars.insert("GRPC_BIND_ADDRESS".to_string(), "0.0.0.0:20121".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(settings.grpc_bind_address.as_deref(), Some("0.0.0.0:20121"));
+        assert_eq!(settings.to_env_vars()["GRPC_BIND_ADDRESS"], "0.0.0.0:20121");
+
+        vars.insert("GRPC_BIND_ADDRESS".to_string(), "0.0.0.0:20120".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+
+        vars.insert("GRPC_BIND_ADDRESS".to_string(), "20121".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn ingest_queue_custom() {
+        let mut vars = HashMap::new();
+        vars.insert("INGEST_QUEUE_DEPTH".to_string(), "100".to_string());
+        vars.insert("INGEST_WRITERS".to_string(), "2".to_string());
+        vars.insert("INGEST_BATCH_SIZE".to_string(), "50".to_string());
+        vars.insert("INGEST_FLUSH_INTERVAL_MS".to_string(), "20".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.write_queue,
+            WriteQueueSettings {
+                depth: 100,
+                writers: 2,
+                batch_size: 50,
+                flush_interval: Duration::from_millis(20),
+            }
+        );
+
+        vars.insert("INGEST_WRITERS".to_string(), "0".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn spool_custom() {
+        let mut vars = HashMap::new();
+        vars.insert(
+            "SPOOL_DIR".to_string(),
+            "/var/spool/signalstash".to_string(),
+        );
+        vars.insert("SPOOL_MAX_BYTES".to_string(), "1048576".to_string());
+        vars.insert("SPOOL_SEGMENT_BYTES".to_string(), "65536".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.spool,
+            Some(SpoolSettings {
+                dir: PathBuf::from("/var/spool/signalstash"),
+                max_bytes: 1_048_576,
+                segment_bytes: 65_536,
+            })
+        );
+
+        vars.insert("SPOOL_SEGMENT_BYTES".to_string(), "2097152".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+
+    #[test]
+    fn redis_sentinel_and_cluster() {
+        let mut vars = HashMap::new();
+        vars.insert("REDIS_MODE".to_string(), "sentinel".to_string());
+        vars.insert("REDIS_SENTINEL_MASTER".to_string(), "mymaster".to_string());
+        vars.insert(
+            "REDIS_NODES".to_string(),
+            "redis://sentinel-0:26379, redis://sentinel-1:26379".to_string(),
+        );
+        vars.insert(
+            "REDIS_URL".to_string(),
+            "redis://:secret@ignored/1".to_string(),
+        );
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert_eq!(
+            settings.redis_topology(),
+            RedisTopology::Sentinel {
+                master_name: "mymaster".to_string(),
+                sentinels: vec![
+                    "redis://sentinel-0:26379".to_string(),
+                    "redis://sentinel-1:26379".to_string(),
+                ],
+                url: "redis://:secret@ignored/1".to_string(),
+            }
+        );
+        assert!(!settings.series_keys().hash_tags);
+
+        // Cluster turns hash tags on by default.
+        let mut vars = HashMap::new();
+        vars.insert("REDIS_MODE".to_string(), "cluster".to_string());
+        vars.insert("REDIS_NODES".to_string(), "redis://node-0:6379".to_string());
+        let settings = Settings::from_env_vars(&vars).unwrap();
+        assert!(settings.series_keys().hash_tags);
+        assert_eq!(
+            settings.redis_topology(),
+            RedisTopology::Cluster {
+                nodes: vec!["redis://node-0:6379".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn redis_mode_incomplete() {
+        let mut vars = HashMap::new();
+        vars.insert("REDIS_MODE".to_string(), "sentinel".to_string());
+        let Err(err) = Settings::from_env_vars(&vars) else {
+            panic!("sentinel mode without a master or sentinels was accepted");
+        };
+        let problems = err.downcast::<InvalidSettings>().unwrap();
+        assert_eq!(problems.0.len(), 2);
+
+        let mut vars = HashMap::new();
+        vars.insert("REDIS_MODE".to_string(), "cluster".to_string());
+        vars.insert("REDIS_NODES".to_string(), "redis://node-0:6379".to_string());
+        vars.insert("SERIES_HASH_TAGS".to_string(), "false".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+
+        let mut vars = HashMap::new();
+        vars.insert("REDIS_MODE".to_string(), "replicated".to_string());
+        assert!(Settings::from_env_vars(&vars).is_err());
+    }
+}
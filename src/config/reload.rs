@@ -0,0 +1,248 @@
+use super::layers::DOMAINS_TABLE;
+use super::{ConfigSource, Settings};
+use crate::app_state::AppState;
+use crate::consts::env::{LOG_LEVEL_ENV_VAR, RUST_LOG_ENV_VAR};
+use crate::consts::errors::ERR_CONFIG_RELOAD;
+use crate::domains::{DomainSpec, store};
+use crate::telemetry::{self, LogFilter};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Settings a reload applies, and the configuration file's table of custom domains; any other
+/// change is reported as pending until the next restart. Built-in domains are fixed.
+///
+/// Rate limits were meant to be reloadable too, but the server does no rate limiting of its own,
+/// so there are none to reload.
+pub const RELOADABLE_SETTINGS: &[&str] = &[LOG_LEVEL_ENV_VAR, RUST_LOG_ENV_VAR, DOMAINS_TABLE];
+
+/// How often the configuration file is checked for changes.
+const FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The settings in effect, shown read-only at `GET /api/config`.
+#[derive(Clone, Default)]
+pub struct ActiveConfig(Arc<RwLock<ConfigSnapshot>>);
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ConfigSnapshot {
+    /// When the settings were last read, at startup or by a reload.
+    pub loaded_at: Option<DateTime<Utc>>,
+    pub reloads: u64,
+    /// Running values keyed by environment variable name, passwords masked.
+    pub settings: BTreeMap<&'static str, String>,
+    /// Custom domains defined in the configuration file.
+    pub domains: Vec<DomainSpec>,
+    pub reloadable: &'static [&'static str],
+    /// Settings configured with a different value than the one running, until the next restart.
+    pub pending_restart: Vec<&'static str>,
+}
+
+impl ActiveConfig {
+    pub fn new(settings: &Settings) -> Self {
+        Self(Arc::new(RwLock::new(ConfigSnapshot {
+            loaded_at: Some(Utc::now()),
+            reloads: 0,
+            settings: settings.to_env_vars(),
+            domains: settings.domains.clone(),
+            reloadable: RELOADABLE_SETTINGS,
+            pending_restart: Vec::new(),
+        })))
+    }
+
+    pub fn snapshot(&self) -> ConfigSnapshot {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Records freshly read settings: reloadable values replace the running ones, and changes to
+    /// any other are listed as pending a restart.
+    pub fn update(&self, settings: &Settings) {
+        let configured = settings.to_env_vars();
+        let mut current = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let names: BTreeSet<&'static str> = configured
+            .keys()
+            .chain(current.settings.keys())
+            .copied()
+            .collect();
+
+        let mut pending_restart = Vec::new();
+        for name in names {
+            let value = configured.get(name);
+            if RELOADABLE_SETTINGS.contains(&name) {
+                match value {
+                    Some(value) => current.settings.insert(name, value.clone()),
+                    None => current.settings.remove(name),
+                };
+            } else if value != current.settings.get(name) {
+                pending_restart.push(name);
+            }
+        }
+        current.pending_restart = pending_restart;
+        current.domains = settings.domains.clone();
+        current.reloadable = RELOADABLE_SETTINGS;
+        current.loaded_at = Some(Utc::now());
+        current.reloads += 1;
+    }
+}
+
+/// Spawns a background task that reloads the configuration on SIGHUP and whenever the
+/// configuration file's modification time changes.
+pub fn spawn_reloader(
+    state: Arc<AppState>,
+    source: ConfigSource,
+    log_filter: LogFilter,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut hangup = Hangup::new();
+        let mut modified = file_modified(&source);
+        let mut ticker = tokio::time::interval(FILE_POLL_INTERVAL);
+        ticker.tick().await;
+        loop {
+            let trigger = tokio::select! {
+                () = hangup.recv() => "SIGHUP",
+                _ = ticker.tick() => {
+                    let current = file_modified(&source);
+                    if current == modified {
+                        continue;
+                    }
+                    modified = current;
+                    "file_change"
+                }
+            };
+            if let Err(e) = reload(&state, &source, &log_filter, trigger).await {
+                warn!(trigger, error = %e, "{ERR_CONFIG_RELOAD}");
+            }
+        }
+    })
+}
+
+/// Re-reads the settings and applies what can change at runtime: the log filter, and the custom
+/// domains' definitions and validation ranges from the file and Redis. Everything is read and
+/// checked before anything is applied, so invalid settings or an unreachable Redis leave
+/// everything as it was.
+pub async fn reload(
+    state: &AppState,
+    source: &ConfigSource,
+    log_filter: &LogFilter,
+    trigger: &str,
+) -> anyhow::Result<()> {
+    let settings = source.layers().settings()?;
+    let filter = telemetry::env_filter(&settings)?;
+    let specs = store::read_custom_domains(&state.redis, &settings.domains).await?;
+
+    log_filter.apply(filter)?;
+    let domains = specs.len();
+    state.domains.replace_custom(specs);
+    state.config.update(&settings);
+
+    let pending_restart = state.config.snapshot().pending_restart;
+    info!(trigger, domains, "Configuration reloaded");
+    if !pending_restart.is_empty() {
+        warn!(
+            settings = ?pending_restart,
+            "Changed settings take effect after a restart"
+        );
+    }
+    Ok(())
+}
+
+fn file_modified(source: &ConfigSource) -> Option<SystemTime> {
+    let path = source.file.as_ref()?;
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// SIGHUP, the conventional "reload your configuration" signal.
+struct Hangup {
+    #[cfg(unix)]
+    signal: Option<tokio::signal::unix::Signal>,
+}
+
+impl Hangup {
+    fn new() -> Self {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+            let signal = signal(SignalKind::hangup())
+                .inspect_err(|e| warn!(error = %e, "Failed to listen for SIGHUP"))
+                .ok();
+            Self { signal }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        if let Some(signal) = &mut self.signal
+            && signal.recv().await.is_some()
+        {
+            return;
+        }
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::layers;
+    use std::collections::HashMap;
+
+    fn settings(vars: &[(&str, &str)]) -> Settings {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Settings::from_env_vars(&vars).unwrap()
+    }
+
+    #[test]
+    fn update_applies_reloadable_and_flags_the_rest() {
+        let active = ActiveConfig::new(&settings(&[]));
+        active.update(&settings(&[
+            ("LOG_LEVEL", "debug"),
+            ("RUST_LOG", "warn,signalstashrs=trace"),
+            ("BIND_ADDRESS", "127.0.0.1:9000"),
+        ]));
+
+        let snapshot = active.snapshot();
+        assert_eq!(snapshot.reloads, 1);
+        assert_eq!(snapshot.settings["LOG_LEVEL"], "DEBUG");
+        assert_eq!(snapshot.settings["RUST_LOG"], "warn,signalstashrs=trace");
+        assert_eq!(snapshot.settings["BIND_ADDRESS"], "0.0.0.0:20120");
+        assert_eq!(snapshot.pending_restart, vec!["BIND_ADDRESS"]);
+
+        // Reverting the change clears it, and removing RUST_LOG removes it from the running set.
+        active.update(&settings(&[]));
+        let snapshot = active.snapshot();
+        assert!(snapshot.pending_restart.is_empty());
+        assert!(!snapshot.settings.contains_key("RUST_LOG"));
+    }
+
+    #[test]
+    fn update_shows_the_configured_domains() {
+        let active = ActiveConfig::new(&settings(&[]));
+        assert!(active.snapshot().domains.is_empty());
+
+        let domains = layers::parse_file(
+            "[domains.DOMINANT_FREQUENCY]\nunit = \"Hz\"\n",
+            layers::ConfigFormat::Toml,
+        )
+        .unwrap()
+        .domains;
+        active.update(&Settings {
+            domains,
+            ..settings(&[])
+        });
+        let snapshot = active.snapshot();
+        assert_eq!(snapshot.domains.len(), 1);
+        assert_eq!(snapshot.domains[0].name, "DOMINANT_FREQUENCY");
+        assert!(snapshot.pending_restart.is_empty());
+        assert!(snapshot.reloadable.contains(&"domains"));
+    }
+}
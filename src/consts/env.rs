@@ -1,8 +1,61 @@
+pub const ALERT_EVALUATION_INTERVAL_SECS_ENV_VAR: &str = "ALERT_EVALUATION_INTERVAL_SECS";
+pub const ALERT_WEBHOOK_MAX_ATTEMPTS_ENV_VAR: &str = "ALERT_WEBHOOK_MAX_ATTEMPTS";
 pub const BIND_ADDRESS_ENV_VAR: &str = "BIND_ADDRESS";
+pub const CONFIG_FILE_ENV_VAR: &str = "CONFIG_FILE";
+pub const DEFAULT_ALERT_EVALUATION_INTERVAL_SECS: u64 = 30;
+pub const DEFAULT_ALERT_WEBHOOK_MAX_ATTEMPTS: u32 = 5;
 pub const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:20120";
+pub const DEFAULT_EXPECTED_BATCH_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_INGEST_BATCH_SIZE: usize = 500;
+pub const DEFAULT_INGEST_FLUSH_INTERVAL_MS: u64 = 5;
+pub const DEFAULT_INGEST_QUEUE_DEPTH: usize = 10_000;
+pub const DEFAULT_INGEST_WRITERS: usize = 4;
 pub const DEFAULT_LOG_LEVEL: &str = "INFO";
+pub const DEFAULT_OFFLINE_AFTER_INTERVALS: u32 = 5;
+pub const DEFAULT_OTEL_SERVICE_NAME: &str = "signalstashrs";
 pub const DEFAULT_REDIS_URL: &str = "redis://localhost:6379";
+pub const DEFAULT_REJECT_UNREGISTERED_DEVICES: bool = false;
+pub const DEFAULT_SAMPLE_STREAM_MAXLEN: usize = 100_000;
 pub const DEFAULT_SENSOR_DATUM_PREFIX: &str = "signalstashrs";
+pub const DEFAULT_SHUTDOWN_DRAIN_TIMEOUT_SECS: u64 = 20;
+pub const DEFAULT_SHUTDOWN_READINESS_DELAY_SECS: u64 = 5;
+pub const DEFAULT_SPOOL_MAX_BYTES: u64 = 256 * 1024 * 1024;
+pub const DEFAULT_SPOOL_SEGMENT_BYTES: u64 = 8 * 1024 * 1024;
+pub const DEFAULT_TLS_RELOAD_INTERVAL_SECS: u64 = 30;
 pub const ENV_SENSOR_DATUM_PREFIX: &str = "SENSOR_DATUM_PREFIX";
+pub const EXPECTED_BATCH_INTERVAL_SECS_ENV_VAR: &str = "EXPECTED_BATCH_INTERVAL_SECS";
+pub const GRPC_BIND_ADDRESS_ENV_VAR: &str = "GRPC_BIND_ADDRESS";
+pub const INGEST_BATCH_SIZE_ENV_VAR: &str = "INGEST_BATCH_SIZE";
+pub const INGEST_FLUSH_INTERVAL_MS_ENV_VAR: &str = "INGEST_FLUSH_INTERVAL_MS";
+pub const INGEST_QUEUE_DEPTH_ENV_VAR: &str = "INGEST_QUEUE_DEPTH";
+pub const INGEST_WRITERS_ENV_VAR: &str = "INGEST_WRITERS";
+pub const LIVE_PUBSUB_CHANNEL_ENV_VAR: &str = "LIVE_PUBSUB_CHANNEL";
+pub const LOG_FORMAT_ENV_VAR: &str = "LOG_FORMAT";
 pub const LOG_LEVEL_ENV_VAR: &str = "LOG_LEVEL";
+pub const MIN_OFFLINE_AFTER_INTERVALS: u32 = 2;
+pub const NOISE_DAY_START_HOUR_ENV_VAR: &str = "NOISE_DAY_START_HOUR";
+pub const NOISE_EVENING_START_HOUR_ENV_VAR: &str = "NOISE_EVENING_START_HOUR";
+pub const NOISE_LDN_NIGHT_START_HOUR_ENV_VAR: &str = "NOISE_LDN_NIGHT_START_HOUR";
+pub const NOISE_NIGHT_START_HOUR_ENV_VAR: &str = "NOISE_NIGHT_START_HOUR";
+pub const NOISE_TIMEZONE_ENV_VAR: &str = "NOISE_TIMEZONE";
+pub const OFFLINE_AFTER_INTERVALS_ENV_VAR: &str = "OFFLINE_AFTER_INTERVALS";
+pub const OTEL_EXPORTER_OTLP_ENDPOINT_ENV_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const OTEL_SERVICE_NAME_ENV_VAR: &str = "OTEL_SERVICE_NAME";
+pub const REDIS_MODE_ENV_VAR: &str = "REDIS_MODE";
+pub const REDIS_NODES_ENV_VAR: &str = "REDIS_NODES";
+pub const REDIS_SENTINEL_MASTER_ENV_VAR: &str = "REDIS_SENTINEL_MASTER";
 pub const REDIS_URL_ENV_VAR: &str = "REDIS_URL";
+pub const REJECT_UNREGISTERED_DEVICES_ENV_VAR: &str = "REJECT_UNREGISTERED_DEVICES";
+pub const RUST_LOG_ENV_VAR: &str = "RUST_LOG";
+pub const SAMPLE_STREAM_KEY_ENV_VAR: &str = "SAMPLE_STREAM_KEY";
+pub const SAMPLE_STREAM_MAXLEN_ENV_VAR: &str = "SAMPLE_STREAM_MAXLEN";
+pub const SERIES_HASH_TAGS_ENV_VAR: &str = "SERIES_HASH_TAGS";
+pub const SHUTDOWN_DRAIN_TIMEOUT_SECS_ENV_VAR: &str = "SHUTDOWN_DRAIN_TIMEOUT_SECS";
+pub const SHUTDOWN_READINESS_DELAY_SECS_ENV_VAR: &str = "SHUTDOWN_READINESS_DELAY_SECS";
+pub const SPOOL_DIR_ENV_VAR: &str = "SPOOL_DIR";
+pub const SPOOL_MAX_BYTES_ENV_VAR: &str = "SPOOL_MAX_BYTES";
+pub const SPOOL_SEGMENT_BYTES_ENV_VAR: &str = "SPOOL_SEGMENT_BYTES";
+pub const TLS_CERT_PATH_ENV_VAR: &str = "TLS_CERT_PATH";
+pub const TLS_CLIENT_CA_PATH_ENV_VAR: &str = "TLS_CLIENT_CA_PATH";
+pub const TLS_KEY_PATH_ENV_VAR: &str = "TLS_KEY_PATH";
+pub const TLS_RELOAD_INTERVAL_SECS_ENV_VAR: &str = "TLS_RELOAD_INTERVAL_SECS";
@@ -1,5 +1,53 @@
+pub const ERR_ALERT_EVALUATION: &str = "Failed to evaluate alert rules";
+pub const ERR_ALERT_RULE_DELETE: &str = "Failed to delete alert rule";
+pub const ERR_ALERT_RULE_READ: &str = "Failed to read alert rules";
+pub const ERR_ALERT_RULE_WRITE: &str = "Failed to write alert rule";
+pub const ERR_ALERT_STATE_READ: &str = "Failed to read alerts";
+pub const ERR_ALERT_WEBHOOK: &str = "Failed to deliver alert webhook";
+pub const ERR_API_KEY_COUNT: &str = "Failed to count API keys for metrics";
+pub const ERR_ACOUSTICS_READ: &str = "Failed to read SPL series for acoustic levels";
+pub const ERR_DAILY_INDICATORS: &str = "Failed to compute daily noise indicators";
 pub const ERR_DECODE_PROTOBUF: &str = "Failed to decode protobuf in ingest";
 pub const ERR_INVALID_CONTENT_TYPE: &str = "Invalid content-type";
+pub const ERR_EXPORT: &str = "Export failed";
+pub const ERR_IMPORT: &str = "Import failed";
+pub const ERR_IMPORT_JOB_READ: &str = "Failed to read import job";
+pub const ERR_IMPORT_JOB_WRITE: &str = "Failed to write import job";
+pub const ERR_INTERVENTION_DELETE: &str = "Failed to delete intervention";
+pub const ERR_INTERVENTION_READ: &str = "Failed to read interventions";
+pub const ERR_INTERVENTION_WRITE: &str = "Failed to write intervention";
 pub const ERR_INVALID_UTF8_DEVICE_ID: &str = "Invalid UTF-8 in device_id in ingest";
+pub const ERR_LIVE_PUBLISH: &str = "Failed to publish live sample";
+pub const ERR_LIVE_RELAY: &str = "Live sample relay failed";
+pub const ERR_METRICS_RENDER: &str = "Failed to render metrics";
+pub const ERR_INGEST_QUEUE_CLOSED: &str = "Ingest write queue writers have stopped";
 pub const ERR_REDIS_CONN: &str = "Failed to get Redis connection in ingest";
+pub const ERR_SAMPLE_STREAM: &str = "Failed to publish sample to stream";
+pub const ERR_SPOOL_REPLAY: &str = "Failed to replay spooled samples";
+pub const ERR_SPOOL_WRITE: &str = "Failed to spool sample in ingest";
 pub const ERR_REDIS_WRITE: &str = "Failed to write to RedisTimeSeries in ingest";
+pub const ERR_DEVICE_DELETE: &str = "Failed to delete device from registry";
+pub const ERR_DEVICE_LOOKUP: &str = "Failed to look up device in registry in ingest";
+pub const ERR_DEVICE_READ: &str = "Failed to read device registry";
+pub const ERR_DEVICE_WRITE: &str = "Failed to write device to registry";
+pub const MSG_UNREGISTERED_DEVICE: &str = "device is not registered";
+pub const MSG_INGEST_QUEUE_FULL: &str = "too many samples waiting to be stored; retry later";
+pub const MSG_INVALID_UTF8_DEVICE_ID: &str = "device_id is not valid UTF-8";
+pub const MSG_SAMPLE_NOT_STORED: &str = "sample could not be stored; retry later";
+pub const MSG_DEVICE_ID_NOT_FILTERABLE: &str = "device_id must not contain ',', '=', '(' or ')'";
+pub const MSG_CERTIFICATE_DEVICE_MISMATCH: &str = "device_id does not match the client certificate";
+pub const ERR_DEVICE_MONITOR: &str = "Failed to check device liveness";
+pub const ERR_DEVICE_STATUS: &str = "Failed to read device status";
+pub const ERR_DEVICE_STATUS_UPDATE: &str = "Failed to update device last-seen status in ingest";
+pub const ERR_DEVICE_HEALTH: &str = "Failed to read device health telemetry";
+pub const ERR_DOMAIN_DELETE: &str = "Failed to delete custom domain";
+pub const ERR_DOMAIN_LOOKUP: &str = "Failed to look up domain in catalog in ingest";
+pub const ERR_DOMAIN_READ: &str = "Failed to read domain catalog";
+pub const ERR_DOMAIN_WRITE: &str = "Failed to write custom domain";
+pub const ERR_GRPC_ACCEPT: &str = "Failed to accept gRPC connection";
+pub const ERR_GRPC_PUSH: &str = "Failed to store a sample of a gRPC push";
+pub const ERR_GRPC_QUERY: &str = "Failed to read series in gRPC query";
+pub const ERR_TLS_ACCEPT: &str = "Failed to accept TLS connection";
+pub const ERR_TLS_HANDSHAKE: &str = "TLS handshake failed";
+pub const ERR_TLS_RELOAD: &str = "Failed to reload TLS certificate, keeping the current one";
+pub const ERR_CONFIG_RELOAD: &str = "Failed to reload configuration, keeping the current one";
@@ -1,4 +1,16 @@
-pub const MSG_REDIS_CONNECTIVITY_ERROR: &str =
-    "could not connect to redis (correlation id: {correlation_id})";
-pub const MSG_REDIS_CONNECTIVITY_FAIL: &str = "Redis connectivity check failed in readyz";
 pub const MSG_READY: &str = "ready";
+pub const MSG_SHUTTING_DOWN: &str = "shutting down";
+pub const MSG_NOT_READY: &str = "Readiness check failed";
+pub const CHECK_BACKGROUND_TASKS: &str = "background_tasks";
+pub const CHECK_INGEST_QUEUE: &str = "ingest_queue";
+pub const CHECK_REDIS: &str = "redis";
+pub const CHECK_REDIS_MEMORY: &str = "redis_memory";
+pub const CHECK_REDIS_PERSISTENCE: &str = "redis_persistence";
+pub const CHECK_SHUTDOWN: &str = "shutdown";
+pub const CHECK_SPOOL: &str = "spool";
+pub const CHECK_TIMESERIES_MODULE: &str = "timeseries_module";
+pub const JOB_ALERT_EVALUATOR: &str = "alert_evaluator";
+pub const JOB_DAILY_INDICATORS: &str = "daily_indicators";
+pub const JOB_LIVE_RELAY: &str = "live_pubsub_relay";
+pub const JOB_OFFLINE_MONITOR: &str = "device_offline_monitor";
+pub const JOB_SPOOL_REPLAY: &str = "spool_replay";
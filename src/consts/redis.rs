@@ -1,6 +1,62 @@
+pub const REDIS_CMD_PUBLISH: &str = "PUBLISH";
 pub const REDIS_CMD_TS_ADD: &str = "TS.ADD";
+pub const REDIS_CMD_TS_CREATE: &str = "TS.CREATE";
+pub const REDIS_CMD_TS_GET: &str = "TS.GET";
+pub const REDIS_CMD_TS_MADD: &str = "TS.MADD";
+pub const REDIS_CMD_TS_MGET: &str = "TS.MGET";
+pub const REDIS_CMD_TS_RANGE: &str = "TS.RANGE";
+pub const REDIS_CMD_XADD: &str = "XADD";
+pub const REDIS_APPROXIMATE_TRIM: &str = "~";
+pub const REDIS_AUTO_ID: &str = "*";
+pub const REDIS_COUNT_LABEL: &str = "COUNT";
+pub const REDIS_FILTER_LABEL: &str = "FILTER";
 pub const PING_CMD: &str = "PING";
 pub const PONG_CMD: &str = "PONG";
+pub const REDIS_CMD_INFO: &str = "INFO";
+pub const REDIS_CMD_MODULE: &str = "MODULE";
+pub const REDIS_INFO_MEMORY: &str = "memory";
+pub const REDIS_INFO_PERSISTENCE: &str = "persistence";
+pub const REDIS_MODULE_LIST: &str = "LIST";
+pub const REDIS_TIMESERIES_MODULE: &str = "timeseries";
+pub const REDIS_LABEL_AGGREGATION: &str = "aggregation";
 pub const REDIS_LABEL_DEVICE_ID: &str = "device_id";
 pub const REDIS_LABEL_DOMAIN: &str = "domain";
+pub const REDIS_LABEL_KIND: &str = "kind";
+pub const REDIS_LABEL_METRIC: &str = "metric";
+pub const REDIS_LABEL_UNIT: &str = "unit";
+pub const REDIS_LABEL_VARIANT: &str = "variant";
 pub const REDIS_LABELS_LABEL: &str = "labels";
+pub const REDIS_MAXLEN_LABEL: &str = "MAXLEN";
+pub const REDIS_ON_DUPLICATE_LABEL: &str = "ON_DUPLICATE";
+pub const REDIS_ON_DUPLICATE_LAST: &str = "LAST";
+pub const REDIS_RETENTION_LABEL: &str = "RETENTION";
+pub const REDIS_WITHLABELS_LABEL: &str = "WITHLABELS";
+pub const ALL_DEVICES: &str = "all_devices";
+pub const DEVICE_KEY_PREFIX: &str = "device:";
+pub const DEVICE_STATUS_KEY_PREFIX: &str = "device_status:";
+pub const DEVICE_STATUS_LAST_SEEN: &str = "last_seen";
+pub const DEVICE_STATUS_SAMPLE_COUNT: &str = "sample_count";
+pub const SEEN_DEVICES: &str = "seen_devices";
+pub const ALL_DOMAINS: &str = "all_domains";
+pub const DOMAIN_KEY_PREFIX: &str = "domain:";
+pub const ALL_INTERVENTIONS: &str = "all_interventions";
+pub const INTERVENTION_KEY_PREFIX: &str = "intervention:";
+pub const ALERT_EVALUATION_LOCK: &str = "alert_evaluation_lock";
+pub const DAILY_INDICATOR_LOCK: &str = "daily_indicator_lock";
+pub const OFFLINE_MONITOR_LOCK: &str = "offline_monitor_lock";
+/// Takes the lock `KEYS[1]` for holder `ARGV[1]` with a TTL of `ARGV[2]` ms, or renews it if that
+/// holder already has it. Returns 1 when the holder has the lock, 0 otherwise.
+pub const ACQUIRE_LOCK_SCRIPT: &str = r#"
+if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[2]) then
+    return 1
+end
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+pub const ALERT_RULE_KEY_PREFIX: &str = "alert_rule:";
+pub const ALERT_STATE_KEY_PREFIX: &str = "alert_state:";
+pub const ALL_ALERT_RULES: &str = "all_alert_rules";
+pub const ALL_ALERT_STATES: &str = "all_alert_states";
+pub const IMPORT_JOB_KEY_PREFIX: &str = "import_job:";
@@ -2,3 +2,26 @@ pub const HEALTHZ_PATH: &str = "/healthz";
 pub const READYZ_PATH: &str = "/readyz";
 pub const STARTZ_PATH: &str = "/startz";
 pub const INGEST_PATH: &str = "/ingest";
+pub const DEVICES_PATH: &str = "/api/devices";
+pub const DEVICE_PATH: &str = "/api/devices/:device_id";
+pub const DEVICE_STATUS_PATH: &str = "/api/devices/:device_id/status";
+pub const DEVICE_HEALTH_PATH: &str = "/api/devices/:device_id/health";
+pub const DOMAINS_PATH: &str = "/api/domains";
+pub const DOMAIN_PATH: &str = "/api/domains/:name";
+pub const DEVICE_CALIBRATIONS_PATH: &str = "/api/devices/:device_id/calibrations";
+pub const ACOUSTICS_LEVELS_PATH: &str = "/api/acoustics/:device_id/levels";
+pub const ACOUSTICS_DAILY_PATH: &str = "/api/acoustics/:device_id/daily";
+pub const INTERVENTIONS_PATH: &str = "/api/interventions";
+pub const INTERVENTION_PATH: &str = "/api/interventions/:id";
+pub const INTERVENTION_COMPARISON_PATH: &str = "/api/interventions/:id/comparison";
+pub const ALERTS_PATH: &str = "/api/alerts";
+pub const ALERT_RULES_PATH: &str = "/api/alerts/rules";
+pub const ALERT_RULE_PATH: &str = "/api/alerts/rules/:id";
+pub const STREAM_PATH: &str = "/api/stream";
+pub const STREAM_WS_PATH: &str = "/api/stream/ws";
+pub const EXPORT_PATH: &str = "/api/export";
+pub const IMPORT_PATH: &str = "/api/import";
+pub const IMPORT_JOB_PATH: &str = "/api/import/:id";
+pub const METRICS_PATH: &str = "/metrics";
+pub const CONFIG_PATH: &str = "/api/config";
+pub const HEALTH_DETAILS_PATH: &str = "/healthz/details";
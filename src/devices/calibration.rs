@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One version of a device's calibration for a single domain.
+///
+/// The calibrated value is computed by first mapping the raw value through `lookup_table` (if
+/// it has any points) and then applying `gain` and `offset`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub domain: String,
+    /// Increments per device and domain, starting at 1.
+    pub version: u32,
+    pub gain: f64,
+    pub offset: f64,
+    /// `(raw, calibrated)` points for piecewise-linear correction, sorted by `raw`.
+    #[serde(default)]
+    pub lookup_table: Vec<(f64, f64)>,
+    /// When this version takes effect; it applies until a later version's `effective_from`.
+    pub effective_from: DateTime<Utc>,
+    /// Also store the uncalibrated value in a separate `:raw` series.
+    #[serde(default)]
+    pub keep_raw: bool,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payload for adding a new calibration version.
+#[derive(Debug, Deserialize)]
+pub struct CreateCalibration {
+    pub domain: String,
+    #[serde(default = "default_gain")]
+    pub gain: f64,
+    #[serde(default)]
+    pub offset: f64,
+    #[serde(default)]
+    pub lookup_table: Vec<(f64, f64)>,
+    /// Defaults to the time the calibration is created.
+    pub effective_from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub keep_raw: bool,
+    pub note: Option<String>,
+}
+
+fn default_gain() -> f64 {
+    1.0
+}
+
+impl CreateCalibration {
+    /// Checks that the calibration describes a usable mapping.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.gain.is_finite() || !self.offset.is_finite() {
+            return Err("gain and offset must be finite numbers".to_string());
+        }
+        if self.gain == 0.0 {
+            return Err("gain must not be zero".to_string());
+        }
+        if self
+            .lookup_table
+            .iter()
+            .any(|(raw, cal)| !raw.is_finite() || !cal.is_finite())
+        {
+            return Err("lookup_table points must be finite numbers".to_string());
+        }
+        if self.lookup_table.windows(2).any(|w| w[0].0 >= w[1].0) {
+            return Err("lookup_table must be sorted by strictly increasing raw value".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Calibration {
+    /// Maps a raw reading to its calibrated value.
+    pub fn apply(&self, raw: f64) -> f64 {
+        interpolate(&self.lookup_table, raw) * self.gain + self.offset
+    }
+}
+
+/// Piecewise-linear interpolation through `points`, extrapolating from the outermost segments.
+///
+/// An empty table is the identity; a single point acts as a constant shift.
+fn interpolate(points: &[(f64, f64)], x: f64) -> f64 {
+    match points {
+        [] => x,
+        [(raw, cal)] => x + (cal - raw),
+        _ => {
+            let i = points
+                .windows(2)
+                .position(|w| x <= w[1].0)
+                .unwrap_or(points.len() - 2);
+            let (x0, y0) = points[i];
+            let (x1, y1) = points[i + 1];
+            y0 + (x - x0) * (y1 - y0) / (x1 - x0)
+        }
+    }
+}
+
+/// Returns the calibration in effect for `domain` at `at`: the latest version whose
+/// `effective_from` is not after `at`.
+pub fn effective<'a>(
+    calibrations: &'a [Calibration],
+    domain: &str,
+    at: DateTime<Utc>,
+) -> Option<&'a Calibration> {
+    calibrations
+        .iter()
+        .filter(|c| c.domain == domain && c.effective_from <= at)
+        .max_by(|a, b| {
+            a.effective_from
+                .cmp(&b.effective_from)
+                .then(a.version.cmp(&b.version))
+        })
+}
+
+/// Builds the next calibration version for a device from a request.
+pub fn next_version(
+    existing: &[Calibration],
+    req: CreateCalibration,
+    now: DateTime<Utc>,
+) -> Calibration {
+    let version = existing
+        .iter()
+        .filter(|c| c.domain == req.domain)
+        .map(|c| c.version)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    Calibration {
+        domain: req.domain,
+        version,
+        gain: req.gain,
+        offset: req.offset,
+        lookup_table: req.lookup_table,
+        effective_from: req.effective_from.unwrap_or(now),
+        keep_raw: req.keep_raw,
+        note: req.note,
+        created_at: now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, day, 0, 0, 0).unwrap()
+    }
+
+    fn request(domain: &str, offset: f64, effective_from: DateTime<Utc>) -> CreateCalibration {
+        CreateCalibration {
+            domain: domain.to_string(),
+            gain: 1.0,
+            offset,
+            lookup_table: Vec::new(),
+            effective_from: Some(effective_from),
+            keep_raw: false,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn apply_linear() {
+        let mut cal = next_version(&[], request("SOUND_PRESSURE_LEVEL", -2.5, at(1)), at(1));
+        cal.gain = 1.1;
+        assert!((cal.apply(50.0) - 52.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn apply_lookup_table_interpolates_and_extrapolates() {
+        let mut cal = next_version(&[], request("SOUND_PRESSURE_LEVEL", 0.0, at(1)), at(1));
+        cal.lookup_table = vec![(40.0, 42.0), (60.0, 61.0), (80.0, 79.0)];
+        assert!((cal.apply(50.0) - 51.5).abs() < 1e-9);
+        assert!((cal.apply(70.0) - 70.0).abs() < 1e-9);
+        assert!((cal.apply(30.0) - 32.5).abs() < 1e-9);
+        assert!((cal.apply(90.0) - 88.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn effective_picks_latest_version_in_effect() {
+        let mut history = Vec::new();
+        let v1 = next_version(&history, request("SOUND_PRESSURE_LEVEL", 1.0, at(1)), at(1));
+        history.push(v1);
+        let v2 = next_version(
+            &history,
+            request("SOUND_PRESSURE_LEVEL", 2.0, at(10)),
+            at(2),
+        );
+        history.push(v2);
+        let other = next_version(&history, request("TEMPERATURE", 5.0, at(1)), at(2));
+        history.push(other);
+
+        assert_eq!(history[1].version, 2);
+        assert_eq!(history[2].version, 1);
+        assert!(
+            effective(
+                &history,
+                "SOUND_PRESSURE_LEVEL",
+                at(1) - chrono::Duration::seconds(1)
+            )
+            .is_none()
+        );
+        assert_eq!(
+            effective(&history, "SOUND_PRESSURE_LEVEL", at(5))
+                .unwrap()
+                .version,
+            1
+        );
+        assert_eq!(
+            effective(&history, "SOUND_PRESSURE_LEVEL", at(10))
+                .unwrap()
+                .version,
+            2
+        );
+        assert_eq!(
+            effective(&history, "TEMPERATURE", at(5)).unwrap().offset,
+            5.0
+        );
+    }
+
+    #[test]
+    fn check_rejects_unsorted_table_and_zero_gain() {
+        let mut req = request("SOUND_PRESSURE_LEVEL", 0.0, at(1));
+        assert!(req.check().is_ok());
+        req.lookup_table = vec![(60.0, 61.0), (40.0, 42.0)];
+        assert!(req.check().is_err());
+        req.lookup_table.clear();
+        req.gain = 0.0;
+        assert!(req.check().is_err());
+    }
+}
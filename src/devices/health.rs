@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use redis::Value;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::consts::redis::{
+    REDIS_CMD_TS_MGET, REDIS_FILTER_LABEL, REDIS_LABEL_DEVICE_ID, REDIS_LABEL_DOMAIN,
+    REDIS_LABEL_KIND, REDIS_LABEL_VARIANT, REDIS_WITHLABELS_LABEL,
+};
+use crate::domains::DomainKind;
+use crate::redis::RedisStore;
+use crate::series;
+
+/// Characters with a meaning in a `TS.MGET` filter; a device ID holding one cannot be matched.
+const FILTER_SPECIAL_CHARS: [char; 4] = [',', '=', '(', ')'];
+
+/// The most recent value of one device health series.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Reading {
+    pub value: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Response body for `GET /api/devices/:device_id/health`.
+#[derive(Debug, Serialize)]
+pub struct DeviceHealth {
+    pub device_id: String,
+    pub firmware_version: Option<String>,
+    /// Latest reading per device health domain, keyed by domain name (e.g. `BATTERY_VOLTAGE`).
+    pub readings: BTreeMap<String, Reading>,
+}
+
+/// Whether `device_id` can be put in a `TS.MGET` label filter as is.
+pub fn is_filterable(device_id: &str) -> bool {
+    !device_id.contains(FILTER_SPECIAL_CHARS)
+}
+
+/// Fetches the latest reading of every device health series for a device in one `TS.MGET`.
+///
+/// Fails for a device ID that is not [`is_filterable`].
+pub async fn latest_readings(
+    redis: &RedisStore,
+    device_id: &str,
+) -> anyhow::Result<BTreeMap<String, Reading>> {
+    anyhow::ensure!(
+        is_filterable(device_id),
+        "device ID {device_id:?} cannot be used in a label filter"
+    );
+    let mut conn = redis.get_connection_manager().await?;
+    // On Redis Cluster a filter only searches one node: the one holding the device's series.
+    let value: Value = conn
+        .query_routed(&mget_command(device_id), &series::hash_tag(device_id))
+        .await?;
+    parse_mget(&value)
+}
+
+/// `TS.MGET` over a device's health series. `variant=` matches only series without a `variant`
+/// label, leaving out the uncalibrated copies of calibrated series.
+fn mget_command(device_id: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd(REDIS_CMD_TS_MGET);
+    cmd.arg(REDIS_WITHLABELS_LABEL)
+        .arg(REDIS_FILTER_LABEL)
+        .arg(format!("{REDIS_LABEL_DEVICE_ID}={device_id}"))
+        .arg(format!(
+            "{REDIS_LABEL_KIND}={}",
+            DomainKind::DeviceHealth.as_str()
+        ))
+        .arg(format!("{REDIS_LABEL_VARIANT}="));
+    cmd
+}
+
+/// Parses a `TS.MGET ... WITHLABELS` reply into readings keyed by the `domain` label.
+///
+/// Series that exist but hold no samples yet are skipped, as are variants such as raw copies.
+fn parse_mget(value: &Value) -> anyhow::Result<BTreeMap<String, Reading>> {
+    let mut readings = BTreeMap::new();
+    let Value::Bulk(series) = value else {
+        anyhow::bail!("unexpected TS.MGET reply: {value:?}");
+    };
+
+    for entry in series {
+        let Value::Bulk(parts) = entry else {
+            anyhow::bail!("unexpected TS.MGET entry: {entry:?}");
+        };
+        let [_key, labels, sample] = parts.as_slice() else {
+            anyhow::bail!("unexpected TS.MGET entry: {entry:?}");
+        };
+
+        let labels: Vec<Vec<String>> = redis::from_redis_value(labels)?;
+        let label = |wanted: &str| {
+            labels.iter().find_map(|pair| match pair.as_slice() {
+                [name, value] if name == wanted => Some(value.clone()),
+                _ => None,
+            })
+        };
+        if label(REDIS_LABEL_VARIANT).is_some() {
+            continue;
+        }
+        let Some(domain) = label(REDIS_LABEL_DOMAIN) else {
+            continue;
+        };
+
+        let sample: Vec<Value> = redis::from_redis_value(sample)?;
+        let [timestamp, value] = sample.as_slice() else {
+            continue;
+        };
+        let timestamp: i64 = redis::from_redis_value(timestamp)?;
+        let value: String = redis::from_redis_value(value)?;
+        let Some(timestamp) = series::from_series_timestamp(timestamp) else {
+            continue;
+        };
+
+        readings.insert(
+            domain,
+            Reading {
+                value: value.parse()?,
+                timestamp,
+            },
+        );
+    }
+    Ok(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn data(s: &str) -> Value {
+        Value::Data(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn parse_mget_reads_domain_label_and_sample() {
+        let reply = Value::Bulk(vec![
+            Value::Bulk(vec![
+                data("signalstashrs:dev1:BATTERY_VOLTAGE"),
+                Value::Bulk(vec![
+                    Value::Bulk(vec![data("device_id"), data("dev1")]),
+                    Value::Bulk(vec![data("domain"), data("BATTERY_VOLTAGE")]),
+                ]),
+                Value::Bulk(vec![Value::Int(1_735_732_800), data("3.7")]),
+            ]),
+            Value::Bulk(vec![
+                data("signalstashrs:dev1:UPTIME"),
+                Value::Bulk(vec![Value::Bulk(vec![data("domain"), data("UPTIME")])]),
+                Value::Bulk(vec![]),
+            ]),
+        ]);
+
+        let readings = parse_mget(&reply).unwrap();
+        assert_eq!(readings.len(), 1);
+        let battery = &readings["BATTERY_VOLTAGE"];
+        assert_eq!(battery.value, 3.7);
+        assert_eq!(
+            battery.timestamp,
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn mget_filter_leaves_out_variants() {
+        let packed = String::from_utf8(mget_command("dev1").get_packed_command()).unwrap();
+        assert!(packed.contains("\r\nvariant=\r\n"), "{packed}");
+    }
+
+    #[test]
+    fn parse_mget_keeps_calibrated_reading_over_raw_copy() {
+        let reply = Value::Bulk(vec![
+            Value::Bulk(vec![
+                data("signalstashrs:dev1:BATTERY_VOLTAGE"),
+                Value::Bulk(vec![Value::Bulk(vec![
+                    data("domain"),
+                    data("BATTERY_VOLTAGE"),
+                ])]),
+                Value::Bulk(vec![Value::Int(1_735_732_800), data("3.7")]),
+            ]),
+            Value::Bulk(vec![
+                data("signalstashrs:dev1:BATTERY_VOLTAGE:raw"),
+                Value::Bulk(vec![
+                    Value::Bulk(vec![data("domain"), data("BATTERY_VOLTAGE")]),
+                    Value::Bulk(vec![data("variant"), data("raw")]),
+                ]),
+                Value::Bulk(vec![Value::Int(1_735_732_800), data("3.5")]),
+            ]),
+        ]);
+
+        let readings = parse_mget(&reply).unwrap();
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings["BATTERY_VOLTAGE"].value, 3.7);
+    }
+
+    #[test]
+    fn parse_mget_rejects_non_array() {
+        assert!(parse_mget(&Value::Nil).is_err());
+    }
+
+    #[test]
+    fn filter_special_characters_are_not_filterable() {
+        assert!(is_filterable("sensor-1.site_a"));
+        for device_id in ["a,b", "a=b", "a(b", "a)b"] {
+            assert!(!is_filterable(device_id), "{device_id}");
+        }
+    }
+}
@@ -0,0 +1,12 @@
+pub mod calibration;
+pub mod health;
+pub mod monitor;
+pub mod registry;
+pub mod status;
+
+// Re-export commonly used items
+pub use monitor::spawn_offline_monitor;
+pub use registry::Device;
+pub use registry::device_exists;
+pub use registry::get_device;
+pub use status::LivenessPolicy;
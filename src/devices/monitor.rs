@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::consts::errors::ERR_DEVICE_MONITOR;
+use crate::consts::health::JOB_OFFLINE_MONITOR;
+use crate::consts::redis::OFFLINE_MONITOR_LOCK;
+use crate::devices::status::{self, Liveness};
+
+/// Spawns a background task that checks every device's last-seen time once per expected batch
+/// interval, emitting a warning event when a device goes offline and an info event when it
+/// comes back.
+///
+/// Only one replica checks at a time, under a lock in Redis like the alert evaluator's. A device is
+/// only reported once per transition; the set of devices already reported offline lives in the
+/// memory of the replica holding the lock, so after a restart or a change of holder every offline
+/// device is reported again.
+pub fn spawn_offline_monitor(state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    let interval = state.liveness.expected_batch_interval;
+    state.jobs.register(JOB_OFFLINE_MONITOR, Some(interval));
+    tokio::spawn(async move {
+        let instance = Uuid::new_v4().to_string();
+        let mut offline = HashSet::new();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            // A run left to the replica holding the lock counts as a successful one.
+            let result = match state
+                .redis
+                .acquire_lock(OFFLINE_MONITOR_LOCK, &instance, interval * 2)
+                .await
+            {
+                Ok(true) => check_devices(&state, &mut offline).await,
+                Ok(false) => {
+                    // Transitions seen meanwhile by the holder would leave this set stale.
+                    offline.clear();
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            };
+            state.jobs.record(JOB_OFFLINE_MONITOR, &result);
+            if let Err(e) = result {
+                error!(error = %e, "{ERR_DEVICE_MONITOR}");
+            }
+        }
+    })
+}
+
+async fn check_devices(state: &AppState, offline: &mut HashSet<String>) -> anyhow::Result<()> {
+    let now = chrono::Utc::now();
+    for device_id in status::list_seen_devices(&state.redis).await? {
+        let Some(activity) = status::get_activity(&state.redis, &device_id).await? else {
+            continue;
+        };
+
+        let liveness = state.liveness.classify(activity.last_seen, now);
+        if liveness == Liveness::Offline {
+            if offline.insert(device_id.clone()) {
+                warn!(
+                    event = "device_offline",
+                    device_id = %device_id,
+                    last_seen = %activity.last_seen,
+                    silent_secs = (now - activity.last_seen).num_seconds(),
+                    "Device went offline"
+                );
+            }
+        } else if offline.remove(&device_id) {
+            info!(
+                event = "device_online",
+                device_id = %device_id,
+                last_seen = %activity.last_seen,
+                "Device is reporting again"
+            );
+        }
+    }
+    Ok(())
+}
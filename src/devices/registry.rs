@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::consts::redis::{ALL_DEVICES, DEVICE_KEY_PREFIX};
+use crate::devices::calibration::Calibration;
+use crate::redis::RedisStore;
+
+/// Where a device is physically installed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Free-text description of the site, e.g. "back fence, north corner".
+    pub site: Option<String>,
+}
+
+/// A registered sensor device and its metadata.
+///
+/// `device_id` is the UTF-8 form of the `device_id` bytes the device sends with every sample.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    pub device_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub location: Location,
+    pub installed_at: Option<DateTime<Utc>>,
+    pub firmware_version: Option<String>,
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Every calibration version ever recorded for this device, across all domains.
+    #[serde(default)]
+    pub calibrations: Vec<Calibration>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Payload for registering a new device.
+#[derive(Debug, Deserialize)]
+pub struct CreateDevice {
+    pub device_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub location: Location,
+    pub installed_at: Option<DateTime<Utc>>,
+    pub firmware_version: Option<String>,
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Partial update of a device; fields left out are unchanged.
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateDevice {
+    pub name: Option<String>,
+    pub location: Option<Location>,
+    pub installed_at: Option<DateTime<Utc>>,
+    pub firmware_version: Option<String>,
+    pub owner: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl Device {
+    /// Builds a new device record from a registration request, stamping creation time.
+    pub fn from_create(req: CreateDevice, now: DateTime<Utc>) -> Self {
+        Self {
+            device_id: req.device_id,
+            name: req.name,
+            location: req.location,
+            installed_at: req.installed_at,
+            firmware_version: req.firmware_version,
+            owner: req.owner,
+            tags: req.tags,
+            calibrations: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Applies a partial update, bumping `updated_at`.
+    pub fn apply(&mut self, update: UpdateDevice, now: DateTime<Utc>) {
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(location) = update.location {
+            self.location = location;
+        }
+        if let Some(installed_at) = update.installed_at {
+            self.installed_at = Some(installed_at);
+        }
+        if let Some(firmware_version) = update.firmware_version {
+            self.firmware_version = Some(firmware_version);
+        }
+        if let Some(owner) = update.owner {
+            self.owner = Some(owner);
+        }
+        if let Some(tags) = update.tags {
+            self.tags = tags;
+        }
+        self.updated_at = now;
+    }
+}
+
+fn device_key(device_id: &str) -> String {
+    format!("{DEVICE_KEY_PREFIX}{device_id}")
+}
+
+/// Stores a device record, overwriting any existing record with the same id.
+pub async fn save_device(redis: &RedisStore, device: &Device) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json = serde_json::to_string(device)?;
+    conn.set::<_, _, ()>(device_key(&device.device_id), json)
+        .await?;
+    conn.sadd::<_, _, ()>(ALL_DEVICES, &device.device_id)
+        .await?;
+    Ok(())
+}
+
+/// Stores a new device record with `SET NX`, so of two concurrent registrations of one id only one
+/// succeeds. Returns `false`, storing nothing, if the id is already registered.
+pub async fn create_device(redis: &RedisStore, device: &Device) -> anyhow::Result<bool> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json = serde_json::to_string(device)?;
+    let created: bool = conn.set_nx(device_key(&device.device_id), json).await?;
+    if created {
+        conn.sadd::<_, _, ()>(ALL_DEVICES, &device.device_id)
+            .await?;
+    }
+    Ok(created)
+}
+
+/// Looks up a device by id, returning `None` if it is not registered.
+pub async fn get_device(redis: &RedisStore, device_id: &str) -> anyhow::Result<Option<Device>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json: Option<String> = conn.get(device_key(device_id)).await?;
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns every registered device, ordered by id.
+pub async fn list_devices(redis: &RedisStore) -> anyhow::Result<Vec<Device>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let mut ids: Vec<String> = conn.smembers(ALL_DEVICES).await?;
+    ids.sort();
+
+    let mut devices = Vec::with_capacity(ids.len());
+    for id in ids {
+        let json: Option<String> = conn.get(device_key(&id)).await?;
+        if let Some(json) = json {
+            devices.push(serde_json::from_str(&json)?);
+        }
+    }
+    Ok(devices)
+}
+
+/// Checks whether a device is registered.
+pub async fn device_exists(redis: &RedisStore, device_id: &str) -> anyhow::Result<bool> {
+    let mut conn = redis.get_connection_manager().await?;
+    let exists: bool = conn.exists(device_key(device_id)).await?;
+    Ok(exists)
+}
+
+/// Removes a device from the registry. Returns `false` if it was not registered.
+///
+/// Stored time series for the device are left untouched.
+pub async fn delete_device(redis: &RedisStore, device_id: &str) -> anyhow::Result<bool> {
+    let mut conn = redis.get_connection_manager().await?;
+    let removed: usize = conn.del(device_key(device_id)).await?;
+    conn.srem::<_, _, ()>(ALL_DEVICES, device_id).await?;
+    Ok(removed > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sample_device() -> Device {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        Device::from_create(
+            CreateDevice {
+                device_id: "testdevice".to_string(),
+                name: "Back fence".to_string(),
+                location: Location::default(),
+                installed_at: None,
+                firmware_version: Some("1.0.0".to_string()),
+                owner: None,
+                tags: vec!["outdoor".to_string()],
+            },
+            now,
+        )
+    }
+
+    #[test]
+    fn apply_updates_only_given_fields() {
+        let mut device = sample_device();
+        let later = Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+        device.apply(
+            UpdateDevice {
+                firmware_version: Some("1.1.0".to_string()),
+                ..Default::default()
+            },
+            later,
+        );
+        assert_eq!(device.name, "Back fence");
+        assert_eq!(device.firmware_version.as_deref(), Some("1.1.0"));
+        assert_eq!(device.tags, vec!["outdoor".to_string()]);
+        assert_eq!(device.updated_at, later);
+        assert_ne!(device.created_at, later);
+    }
+
+    #[test]
+    fn device_round_trips_through_json() {
+        let device = sample_device();
+        let json = serde_json::to_string(&device).unwrap();
+        let decoded: Device = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, device);
+    }
+}
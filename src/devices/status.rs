@@ -0,0 +1,188 @@
+use chrono::{DateTime, TimeZone, Utc};
+use redis::AsyncCommands;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::consts::redis::{
+    DEVICE_STATUS_KEY_PREFIX, DEVICE_STATUS_LAST_SEEN, DEVICE_STATUS_SAMPLE_COUNT, SEEN_DEVICES,
+};
+use crate::redis::RedisStore;
+
+/// How recently a device has reported, relative to its expected batch interval.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Liveness {
+    /// Reported within two batch intervals (one late batch is tolerated).
+    Online,
+    /// Missed more than one batch but not yet considered offline.
+    Stale,
+    /// Silent for longer than `offline_after_intervals` batch intervals.
+    Offline,
+}
+
+/// Thresholds used to classify a device's [`Liveness`].
+#[derive(Clone, Copy, Debug)]
+pub struct LivenessPolicy {
+    pub expected_batch_interval: Duration,
+    pub offline_after_intervals: u32,
+}
+
+impl LivenessPolicy {
+    /// Classifies a device that was last seen at `last_seen`, as of `now`.
+    pub fn classify(&self, last_seen: DateTime<Utc>, now: DateTime<Utc>) -> Liveness {
+        let silent_for = (now - last_seen).to_std().unwrap_or(Duration::ZERO);
+        if silent_for <= self.expected_batch_interval * 2 {
+            Liveness::Online
+        } else if silent_for <= self.offline_threshold() {
+            Liveness::Stale
+        } else {
+            Liveness::Offline
+        }
+    }
+
+    /// How long a device may stay silent before it is considered offline.
+    pub fn offline_threshold(&self) -> Duration {
+        self.expected_batch_interval * self.offline_after_intervals
+    }
+}
+
+/// Last-seen bookkeeping for a single device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceActivity {
+    pub last_seen: DateTime<Utc>,
+    pub sample_count: u64,
+}
+
+/// Response body for `GET /api/devices/:device_id/status`.
+#[derive(Debug, Serialize)]
+pub struct DeviceStatus {
+    pub device_id: String,
+    pub status: Liveness,
+    pub last_seen: DateTime<Utc>,
+    pub seconds_since_last_seen: i64,
+    pub sample_count: u64,
+}
+
+impl DeviceStatus {
+    pub fn new(
+        device_id: String,
+        activity: DeviceActivity,
+        policy: &LivenessPolicy,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            status: policy.classify(activity.last_seen, now),
+            seconds_since_last_seen: (now - activity.last_seen).num_seconds().max(0),
+            last_seen: activity.last_seen,
+            sample_count: activity.sample_count,
+            device_id,
+        }
+    }
+}
+
+fn status_key(device_id: &str) -> String {
+    format!("{DEVICE_STATUS_KEY_PREFIX}{device_id}")
+}
+
+/// Records that `samples` samples were accepted from a device at `seen_at`.
+///
+/// The device joins the seen set in a separate command, after its status is written, since the
+/// two keys may live in different cluster slots; a reader listing seen devices always finds a
+/// status.
+pub async fn record_samples(
+    redis: &RedisStore,
+    device_id: &str,
+    samples: u64,
+    seen_at: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let key = status_key(device_id);
+    redis::pipe()
+        .atomic()
+        .hset(&key, DEVICE_STATUS_LAST_SEEN, seen_at.timestamp_millis())
+        .ignore()
+        .hincr(&key, DEVICE_STATUS_SAMPLE_COUNT, samples)
+        .ignore()
+        .query_async::<_, ()>(&mut conn)
+        .await?;
+    conn.sadd::<_, _, ()>(SEEN_DEVICES, device_id).await?;
+    Ok(())
+}
+
+/// Returns last-seen bookkeeping for a device, or `None` if it has never reported.
+pub async fn get_activity(
+    redis: &RedisStore,
+    device_id: &str,
+) -> anyhow::Result<Option<DeviceActivity>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let fields: HashMap<String, i64> = conn.hgetall(status_key(device_id)).await?;
+    Ok(activity_from_fields(&fields))
+}
+
+/// Returns every device that has ever reported, ordered by id.
+pub async fn list_seen_devices(redis: &RedisStore) -> anyhow::Result<Vec<String>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let mut ids: Vec<String> = conn.smembers(SEEN_DEVICES).await?;
+    ids.sort();
+    Ok(ids)
+}
+
+fn activity_from_fields(fields: &HashMap<String, i64>) -> Option<DeviceActivity> {
+    let last_seen_ms = *fields.get(DEVICE_STATUS_LAST_SEEN)?;
+    let last_seen = Utc.timestamp_millis_opt(last_seen_ms).single()?;
+    let sample_count = fields
+        .get(DEVICE_STATUS_SAMPLE_COUNT)
+        .copied()
+        .unwrap_or(0)
+        .max(0) as u64;
+    Some(DeviceActivity {
+        last_seen,
+        sample_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> LivenessPolicy {
+        LivenessPolicy {
+            expected_batch_interval: Duration::from_secs(60),
+            offline_after_intervals: 5,
+        }
+    }
+
+    #[test]
+    fn classify_by_silence() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let ago = |secs| now - chrono::Duration::seconds(secs);
+        assert_eq!(policy().classify(ago(30), now), Liveness::Online);
+        assert_eq!(policy().classify(ago(120), now), Liveness::Online);
+        assert_eq!(policy().classify(ago(121), now), Liveness::Stale);
+        assert_eq!(policy().classify(ago(300), now), Liveness::Stale);
+        assert_eq!(policy().classify(ago(301), now), Liveness::Offline);
+    }
+
+    #[test]
+    fn classify_future_last_seen_is_online() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let later = now + chrono::Duration::seconds(10);
+        assert_eq!(policy().classify(later, now), Liveness::Online);
+    }
+
+    #[test]
+    fn activity_requires_last_seen() {
+        let mut fields = HashMap::new();
+        fields.insert(DEVICE_STATUS_SAMPLE_COUNT.to_string(), 3);
+        assert_eq!(activity_from_fields(&fields), None);
+
+        fields.insert(DEVICE_STATUS_LAST_SEEN.to_string(), 1_735_732_800_000);
+        let activity = activity_from_fields(&fields).unwrap();
+        assert_eq!(activity.sample_count, 3);
+        assert_eq!(
+            activity.last_seen,
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+        );
+    }
+}
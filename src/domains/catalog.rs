@@ -0,0 +1,545 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::sensor::{Domain, SensorData};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DEVICE_HEALTH_RETENTION_MS: u64 = 30 * 24 * HOUR_MS;
+const MAX_DOMAIN_NAME_LEN: usize = 64;
+/// How long a domain found missing in Redis is answered as unknown without asking Redis again.
+const MISSING_DOMAIN_TTL: Duration = Duration::from_secs(30);
+/// Most missing domain names remembered at once, so clients cannot grow the cache unbounded.
+const MAX_MISSING_DOMAINS: usize = 1024;
+
+/// Whether a domain describes the environment or the device itself.
+///
+/// Stored on every series as the `kind` label.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainKind {
+    #[default]
+    Measurement,
+    DeviceHealth,
+}
+
+impl DomainKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DomainKind::Measurement => "measurement",
+            DomainKind::DeviceHealth => "device_health",
+        }
+    }
+}
+
+/// RedisTimeSeries aggregation that makes sense for a domain when downsampling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    #[default]
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Range,
+    Count,
+    First,
+    Last,
+}
+
+impl Aggregation {
+    /// The aggregator name as RedisTimeSeries spells it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Aggregation::Avg => "avg",
+            Aggregation::Sum => "sum",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::Range => "range",
+            Aggregation::Count => "count",
+            Aggregation::First => "first",
+            Aggregation::Last => "last",
+        }
+    }
+}
+
+/// Catalog entry describing one measurement domain.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DomainSpec {
+    /// Upper-case identifier used in series keys and the `domain` label, e.g. `TEMPERATURE`.
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Unit stored as the `unit` label, e.g. `dB`. Empty for unitless domains.
+    #[serde(default)]
+    pub unit: String,
+    /// Smallest accepted value, inclusive.
+    pub min: Option<f64>,
+    /// Largest accepted value, inclusive.
+    pub max: Option<f64>,
+    /// Retention applied when a series for this domain is first created. `None` keeps samples
+    /// forever.
+    pub retention_ms: Option<u64>,
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    #[serde(default)]
+    pub kind: DomainKind,
+    /// Built-in domains ship with the server and cannot be changed or removed.
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// Why a sample was refused by the catalog.
+#[derive(Debug, PartialEq)]
+pub enum SampleRejection {
+    UnknownDomain(String),
+    NotFinite { domain: String },
+    OutOfRange { domain: String, value: f64 },
+}
+
+impl SampleRejection {
+    /// Short, fixed name for the kind of rejection, e.g. for metric labels.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SampleRejection::UnknownDomain(_) => "unknown_domain",
+            SampleRejection::NotFinite { .. } => "not_finite",
+            SampleRejection::OutOfRange { .. } => "out_of_range",
+        }
+    }
+}
+
+impl fmt::Display for SampleRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SampleRejection::UnknownDomain(name) => write!(f, "unknown domain: {name}"),
+            SampleRejection::NotFinite { domain } => {
+                write!(f, "value for domain {domain} is not a finite number")
+            }
+            SampleRejection::OutOfRange { domain, value } => {
+                write!(f, "value {value} is out of range for domain {domain}")
+            }
+        }
+    }
+}
+
+impl DomainSpec {
+    fn builtin(
+        domain: Domain,
+        description: &str,
+        unit: &str,
+        range: (Option<f64>, Option<f64>),
+        aggregation: Aggregation,
+        kind: DomainKind,
+    ) -> Self {
+        Self {
+            name: domain.as_str_name().to_string(),
+            description: description.to_string(),
+            unit: unit.to_string(),
+            min: range.0,
+            max: range.1,
+            retention_ms: match kind {
+                DomainKind::Measurement => None,
+                DomainKind::DeviceHealth => Some(DEVICE_HEALTH_RETENTION_MS),
+            },
+            aggregation,
+            kind,
+            builtin: true,
+        }
+    }
+
+    /// Checks a sample value against this domain's valid range.
+    pub fn validate(&self, value: f64) -> Result<(), SampleRejection> {
+        if !value.is_finite() {
+            return Err(SampleRejection::NotFinite {
+                domain: self.name.clone(),
+            });
+        }
+        let below = self.min.is_some_and(|min| value < min);
+        let above = self.max.is_some_and(|max| value > max);
+        if below || above {
+            return Err(SampleRejection::OutOfRange {
+                domain: self.name.clone(),
+                value,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that a runtime-registered domain definition is well formed.
+    pub fn check_definition(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.len() > MAX_DOMAIN_NAME_LEN {
+            return Err(format!(
+                "domain name must be 1 to {MAX_DOMAIN_NAME_LEN} characters"
+            ));
+        }
+        let mut chars = self.name.chars();
+        let starts_with_letter = chars.next().is_some_and(|c| c.is_ascii_uppercase());
+        let rest_valid = chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if !starts_with_letter || !rest_valid {
+            return Err(
+                "domain name must start with A-Z and contain only A-Z, 0-9 and _".to_string(),
+            );
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max)
+            && min > max
+        {
+            return Err("min must not be greater than max".to_string());
+        }
+        if self.retention_ms == Some(0) {
+            return Err("retention_ms must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The built-in domains, one per value of the `sensor::Domain` proto enum.
+pub fn builtin_domains() -> Vec<DomainSpec> {
+    use Aggregation::*;
+    use DomainKind::*;
+    vec![
+        DomainSpec::builtin(
+            Domain::Unspecified,
+            "Unspecified measurement",
+            "",
+            (None, None),
+            Avg,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::SoundPressureLevel,
+            "Sound pressure level",
+            "dB",
+            (Some(0.0), Some(194.0)),
+            Max,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::BatteryVoltage,
+            "Battery voltage",
+            "V",
+            (Some(0.0), Some(30.0)),
+            Avg,
+            DeviceHealth,
+        ),
+        DomainSpec::builtin(
+            Domain::WifiRssi,
+            "Wi-Fi received signal strength",
+            "dBm",
+            (Some(-120.0), Some(0.0)),
+            Avg,
+            DeviceHealth,
+        ),
+        DomainSpec::builtin(
+            Domain::Uptime,
+            "Time since boot",
+            "s",
+            (Some(0.0), None),
+            Last,
+            DeviceHealth,
+        ),
+        DomainSpec::builtin(
+            Domain::FreeMemory,
+            "Free memory",
+            "bytes",
+            (Some(0.0), None),
+            Min,
+            DeviceHealth,
+        ),
+        DomainSpec::builtin(
+            Domain::RingBufferFill,
+            "Fraction of the unsent-sample ring buffer in use",
+            "ratio",
+            (Some(0.0), Some(1.0)),
+            Max,
+            DeviceHealth,
+        ),
+        DomainSpec::builtin(
+            Domain::Temperature,
+            "Air temperature",
+            "degC",
+            (Some(-60.0), Some(100.0)),
+            Avg,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::Humidity,
+            "Relative humidity",
+            "%",
+            (Some(0.0), Some(100.0)),
+            Avg,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::Pressure,
+            "Barometric pressure",
+            "hPa",
+            (Some(300.0), Some(1100.0)),
+            Avg,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::Pm25,
+            "Fine particulate matter (PM2.5)",
+            "ug/m3",
+            (Some(0.0), Some(1000.0)),
+            Avg,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::Co2,
+            "Carbon dioxide concentration",
+            "ppm",
+            (Some(0.0), Some(40000.0)),
+            Avg,
+            Measurement,
+        ),
+        DomainSpec::builtin(
+            Domain::Illuminance,
+            "Illuminance",
+            "lx",
+            (Some(0.0), Some(200000.0)),
+            Avg,
+            Measurement,
+        ),
+    ]
+}
+
+/// Returns the catalog name a sample's domain resolves to.
+///
+/// `domain_name` wins when set; otherwise the proto enum value is used. Enum values this build
+/// does not know about resolve to `UNKNOWN(<n>)`, which never matches a catalog entry.
+pub fn sample_domain_name(sample: &SensorData) -> String {
+    if !sample.domain_name.is_empty() {
+        return sample.domain_name.clone();
+    }
+    match Domain::try_from(sample.domain) {
+        Ok(domain) => domain.as_str_name().to_string(),
+        Err(_) => format!("UNKNOWN({})", sample.domain),
+    }
+}
+
+/// In-memory domain catalog shared by all handlers.
+///
+/// Built-in domains are always present; custom domains are loaded from Redis at startup and
+/// added or removed through the admin API. Names found missing in Redis are remembered for a
+/// short while so unknown domains do not cost a Redis read per sample.
+pub struct DomainCatalog {
+    entries: RwLock<HashMap<String, DomainSpec>>,
+    missing: Mutex<HashMap<String, Instant>>,
+}
+
+impl Default for DomainCatalog {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+impl DomainCatalog {
+    pub fn with_builtins() -> Self {
+        let entries = builtin_domains()
+            .into_iter()
+            .map(|spec| (spec.name.clone(), spec))
+            .collect();
+        Self {
+            entries: RwLock::new(entries),
+            missing: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<DomainSpec> {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .cloned()
+    }
+
+    /// Returns every domain, ordered by name.
+    pub fn list(&self) -> Vec<DomainSpec> {
+        let mut specs: Vec<_> = self
+            .entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect();
+        specs.sort_by(|a, b| a.name.cmp(&b.name));
+        specs
+    }
+
+    /// Adds or replaces a custom domain. Built-in domains are never replaced.
+    pub fn insert(&self, spec: DomainSpec) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        if entries.get(&spec.name).is_some_and(|e| e.builtin) {
+            return;
+        }
+        self.missing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&spec.name);
+        entries.insert(spec.name.clone(), spec);
+    }
+
+    /// Replaces every custom domain with `specs`, e.g. after reloading them from Redis, so edits
+    /// and deletions made elsewhere take effect here. Built-in domains are kept.
+    pub fn replace_custom(&self, specs: Vec<DomainSpec>) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|_, spec| spec.builtin);
+        self.missing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        for spec in specs {
+            if !entries.contains_key(&spec.name) {
+                entries.insert(spec.name.clone(), spec);
+            }
+        }
+    }
+
+    /// Removes a custom domain, returning it. Built-in domains are never removed.
+    pub fn remove(&self, name: &str) -> Option<DomainSpec> {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        if entries.get(name).is_some_and(|e| e.builtin) {
+            return None;
+        }
+        entries.remove(name)
+    }
+
+    /// Whether `name` was found missing in Redis within the last [`MISSING_DOMAIN_TTL`].
+    pub fn recently_missing(&self, name: &str) -> bool {
+        self.missing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .is_some_and(|since| since.elapsed() < MISSING_DOMAIN_TTL)
+    }
+
+    /// Remembers that `name` is not in Redis. Adding the domain forgets it again.
+    pub fn record_missing(&self, name: &str) {
+        let mut missing = self.missing.lock().unwrap_or_else(PoisonError::into_inner);
+        if missing.len() >= MAX_MISSING_DOMAINS {
+            missing.retain(|_, since| since.elapsed() < MISSING_DOMAIN_TTL);
+        }
+        if missing.len() < MAX_MISSING_DOMAINS {
+            missing.insert(name.to_string(), Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(name: &str) -> DomainSpec {
+        DomainSpec {
+            name: name.to_string(),
+            description: String::new(),
+            unit: "Hz".to_string(),
+            min: Some(0.0),
+            max: Some(20000.0),
+            retention_ms: None,
+            aggregation: Aggregation::Avg,
+            kind: DomainKind::Measurement,
+            builtin: false,
+        }
+    }
+
+    #[test]
+    fn every_proto_domain_has_a_builtin_entry() {
+        let catalog = DomainCatalog::with_builtins();
+        for value in 0..=12 {
+            let domain = Domain::try_from(value).unwrap();
+            let spec = catalog.get(domain.as_str_name()).unwrap();
+            assert!(spec.builtin);
+        }
+    }
+
+    #[test]
+    fn validate_checks_range_and_finiteness() {
+        let spl = DomainCatalog::with_builtins()
+            .get("SOUND_PRESSURE_LEVEL")
+            .unwrap();
+        assert!(spl.validate(55.0).is_ok());
+        assert!(matches!(
+            spl.validate(-1.0),
+            Err(SampleRejection::OutOfRange { .. })
+        ));
+        assert!(matches!(
+            spl.validate(f64::NAN),
+            Err(SampleRejection::NotFinite { .. })
+        ));
+    }
+
+    #[test]
+    fn replace_custom_keeps_builtins() {
+        let catalog = DomainCatalog::with_builtins();
+        catalog.insert(custom("DOMINANT_FREQUENCY"));
+        let mut spl = catalog.get("SOUND_PRESSURE_LEVEL").unwrap();
+        spl.max = Some(1.0);
+        catalog.replace_custom(vec![custom("PEAK_FREQUENCY"), spl]);
+
+        assert!(catalog.get("DOMINANT_FREQUENCY").is_none());
+        assert!(catalog.get("PEAK_FREQUENCY").is_some());
+        assert_ne!(catalog.get("SOUND_PRESSURE_LEVEL").unwrap().max, Some(1.0));
+    }
+
+    #[test]
+    fn check_definition_rejects_bad_names_and_ranges() {
+        assert!(custom("DOMINANT_FREQUENCY").check_definition().is_ok());
+        assert!(custom("lowercase").check_definition().is_err());
+        assert!(custom("HAS:COLON").check_definition().is_err());
+        assert!(custom("").check_definition().is_err());
+
+        let mut inverted = custom("INVERTED");
+        inverted.min = Some(10.0);
+        inverted.max = Some(1.0);
+        assert!(inverted.check_definition().is_err());
+    }
+
+    #[test]
+    fn builtins_cannot_be_replaced_or_removed() {
+        let catalog = DomainCatalog::with_builtins();
+        let mut fake = custom("TEMPERATURE");
+        fake.unit = "K".to_string();
+        catalog.insert(fake);
+        assert_eq!(catalog.get("TEMPERATURE").unwrap().unit, "degC");
+        assert!(catalog.remove("TEMPERATURE").is_none());
+
+        catalog.insert(custom("DOMINANT_FREQUENCY"));
+        assert!(catalog.remove("DOMINANT_FREQUENCY").is_some());
+        assert!(catalog.get("DOMINANT_FREQUENCY").is_none());
+    }
+
+    #[test]
+    fn missing_domains_are_forgotten_once_added() {
+        let catalog = DomainCatalog::with_builtins();
+        assert!(!catalog.recently_missing("DOMINANT_FREQUENCY"));
+        catalog.record_missing("DOMINANT_FREQUENCY");
+        assert!(catalog.recently_missing("DOMINANT_FREQUENCY"));
+
+        catalog.insert(custom("DOMINANT_FREQUENCY"));
+        assert!(!catalog.recently_missing("DOMINANT_FREQUENCY"));
+
+        catalog.record_missing("PEAK_FREQUENCY");
+        catalog.replace_custom(Vec::new());
+        assert!(!catalog.recently_missing("PEAK_FREQUENCY"));
+    }
+
+    #[test]
+    fn sample_domain_name_prefers_explicit_name() {
+        let mut sample = SensorData {
+            domain: Domain::Temperature as i32,
+            ..Default::default()
+        };
+        assert_eq!(sample_domain_name(&sample), "TEMPERATURE");
+
+        sample.domain_name = "DOMINANT_FREQUENCY".to_string();
+        assert_eq!(sample_domain_name(&sample), "DOMINANT_FREQUENCY");
+
+        sample.domain_name.clear();
+        sample.domain = 999;
+        assert_eq!(sample_domain_name(&sample), "UNKNOWN(999)");
+    }
+}
@@ -0,0 +1,8 @@
+pub mod catalog;
+pub mod store;
+
+// Re-export commonly used items
+pub use catalog::DomainCatalog;
+pub use catalog::DomainKind;
+pub use catalog::DomainSpec;
+pub use catalog::SampleRejection;
@@ -0,0 +1,91 @@
+use redis::AsyncCommands;
+
+use crate::consts::redis::{ALL_DOMAINS, DOMAIN_KEY_PREFIX};
+use crate::domains::catalog::{DomainCatalog, DomainSpec};
+use crate::redis::RedisStore;
+
+fn domain_key(name: &str) -> String {
+    format!("{DOMAIN_KEY_PREFIX}{name}")
+}
+
+/// Persists a custom domain so every replica (and the next restart) sees it.
+pub async fn save_domain(redis: &RedisStore, spec: &DomainSpec) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json = serde_json::to_string(spec)?;
+    conn.set::<_, _, ()>(domain_key(&spec.name), json).await?;
+    conn.sadd::<_, _, ()>(ALL_DOMAINS, &spec.name).await?;
+    Ok(())
+}
+
+/// Removes a custom domain. Existing series for the domain are left untouched.
+pub async fn delete_domain(redis: &RedisStore, name: &str) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    conn.del::<_, ()>(domain_key(name)).await?;
+    conn.srem::<_, _, ()>(ALL_DOMAINS, name).await?;
+    Ok(())
+}
+
+/// Loads every persisted custom domain into the catalog along with the `configured` ones, which
+/// win over a persisted domain of the same name, replacing the custom domains it held. Returns
+/// how many were loaded.
+pub async fn load_custom_domains(
+    redis: &RedisStore,
+    catalog: &DomainCatalog,
+    configured: &[DomainSpec],
+) -> anyhow::Result<usize> {
+    let specs = read_custom_domains(redis, configured).await?;
+    let loaded = specs.len();
+    catalog.replace_custom(specs);
+    Ok(loaded)
+}
+
+/// Reads every persisted custom domain along with the `configured` ones, as
+/// [`load_custom_domains`] loads them, without touching the catalog.
+pub async fn read_custom_domains(
+    redis: &RedisStore,
+    configured: &[DomainSpec],
+) -> anyhow::Result<Vec<DomainSpec>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let names: Vec<String> = conn.smembers(ALL_DOMAINS).await?;
+    let mut specs = configured.to_vec();
+    for name in names {
+        if configured.iter().any(|spec| spec.name == name) {
+            continue;
+        }
+        let json: Option<String> = conn.get(domain_key(&name)).await?;
+        if let Some(json) = json {
+            specs.push(serde_json::from_str(&json)?);
+        }
+    }
+    Ok(specs)
+}
+
+/// Resolves a domain by name, falling back to Redis for custom domains registered by another
+/// replica since this one started. Hits from Redis are cached in the catalog, and misses for a
+/// short while so an unknown domain does not cost a Redis read per sample.
+pub async fn lookup(
+    redis: &RedisStore,
+    catalog: &DomainCatalog,
+    name: &str,
+) -> anyhow::Result<Option<DomainSpec>> {
+    if let Some(spec) = catalog.get(name) {
+        return Ok(Some(spec));
+    }
+    if catalog.recently_missing(name) {
+        return Ok(None);
+    }
+
+    let mut conn = redis.get_connection_manager().await?;
+    let json: Option<String> = conn.get(domain_key(name)).await?;
+    match json {
+        Some(json) => {
+            let spec: DomainSpec = serde_json::from_str(&json)?;
+            catalog.insert(spec.clone());
+            Ok(Some(spec))
+        }
+        None => {
+            catalog.record_missing(name);
+            Ok(None)
+        }
+    }
+}
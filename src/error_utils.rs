@@ -2,9 +2,14 @@ use axum::{http::StatusCode, response::IntoResponse};
 use tracing::error;
 use uuid::Uuid;
 
+use crate::request_id;
+
 /// Logs the error with a correlation ID and returns a generic error response with the correlation ID.
+///
+/// Within a request the correlation ID is the request's `X-Request-Id`, so the message a client
+/// sees can be matched to the server logs; elsewhere a fresh one is generated.
 pub fn log_and_response<E: std::fmt::Display>(context: &str, err: E) -> axum::response::Response {
-    let correlation_id = Uuid::new_v4();
+    let correlation_id = request_id::current().unwrap_or_else(|| Uuid::new_v4().to_string());
     error!(correlation_id = %correlation_id, error = %err, "{context}");
     (
         StatusCode::INTERNAL_SERVER_ERROR,
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::consts::errors::ERR_LIVE_RELAY;
+use crate::consts::health::JOB_LIVE_RELAY;
+use crate::consts::redis::REDIS_CMD_PUBLISH;
+use crate::events::sample::SampleEvent;
+use crate::redis::RedisStore;
+
+/// Samples buffered per subscriber before a slow one starts missing samples.
+const CHANNEL_CAPACITY: usize = 1024;
+/// Delay before the pub/sub relay reconnects after losing its subscription.
+const RELAY_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Envelope for samples relayed between replicas over Redis pub/sub.
+#[derive(Serialize, Deserialize)]
+struct RelayedSample {
+    /// The replica that ingested the sample; it has already delivered it locally.
+    origin: String,
+    event: SampleEvent,
+}
+
+/// Fan-out of newly ingested samples to live subscribers.
+///
+/// Samples ingested by this replica go straight to an in-process broadcast channel. When a
+/// pub/sub channel is configured they are also published to Redis, and a relay task feeds
+/// samples published by other replicas into the local channel, so every client sees every
+/// sample whichever replica it is connected to.
+#[derive(Clone)]
+pub struct LiveFeed {
+    sender: broadcast::Sender<SampleEvent>,
+    origin: String,
+    pubsub_channel: Option<String>,
+}
+
+impl LiveFeed {
+    pub fn new(pubsub_channel: Option<String>) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            origin: Uuid::new_v4().to_string(),
+            pubsub_channel,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SampleEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Delivers a sample to local subscribers and, when configured, to other replicas.
+    pub async fn publish(&self, redis: &RedisStore, event: SampleEvent) -> anyhow::Result<()> {
+        // Sending only fails when nobody is subscribed, which is not an error here.
+        let _ = self.sender.send(event.clone());
+
+        let Some(channel) = &self.pubsub_channel else {
+            return Ok(());
+        };
+        let payload = serde_json::to_string(&RelayedSample {
+            origin: self.origin.clone(),
+            event,
+        })?;
+        let mut conn = redis.get_connection_manager().await?;
+        redis::cmd(REDIS_CMD_PUBLISH)
+            .arg(channel)
+            .arg(payload)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Spawns the task relaying samples published by other replicas into the local live feed.
+/// Does nothing when no pub/sub channel is configured.
+pub fn spawn_pubsub_relay(state: Arc<AppState>) -> Option<tokio::task::JoinHandle<()>> {
+    let channel = state.live.pubsub_channel.clone()?;
+    state.jobs.register(JOB_LIVE_RELAY, None);
+    Some(tokio::spawn(async move {
+        loop {
+            let result = relay(&state, &channel).await;
+            state.jobs.record(JOB_LIVE_RELAY, &result);
+            if let Err(e) = result {
+                error!(error = %e, "{ERR_LIVE_RELAY}");
+            }
+            tokio::time::sleep(RELAY_RECONNECT_DELAY).await;
+        }
+    }))
+}
+
+async fn relay(state: &AppState, channel: &str) -> anyhow::Result<()> {
+    let mut pubsub = state.redis.get_pubsub().await?;
+    pubsub.subscribe(channel).await?;
+    state.jobs.record(JOB_LIVE_RELAY, &Ok(()));
+    info!(channel = %channel, "Relaying live samples from other replicas");
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        let relayed: RelayedSample = match serde_json::from_str(&payload) {
+            Ok(relayed) => relayed,
+            Err(e) => {
+                debug!(error = %e, "Ignoring malformed live sample");
+                continue;
+            }
+        };
+        if relayed.origin != state.live.origin {
+            let _ = state.live.sender.send(relayed.event);
+        }
+    }
+    anyhow::bail!("pub/sub subscription to {channel} ended")
+}
@@ -0,0 +1,9 @@
+pub mod live;
+pub mod sample;
+pub mod stream;
+
+// Re-export commonly used items
+pub use live::LiveFeed;
+pub use live::spawn_pubsub_relay;
+pub use sample::SampleEvent;
+pub use stream::SampleStream;
@@ -0,0 +1,80 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Version of the outbound sample message schema. Bump it when fields change meaning or are
+/// removed; adding a field does not require a bump.
+pub const SAMPLE_EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// A sample that `/ingest` accepted and stored, as published to downstream consumers.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SampleEvent {
+    pub device_id: String,
+    pub domain: String,
+    /// The stored (calibrated) value.
+    pub value: f64,
+    /// The value as reported, present only when a calibration changed it.
+    pub raw_value: Option<f64>,
+    pub unit: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl SampleEvent {
+    /// Flattens the event into Redis Stream field/value pairs. Every value is a string, as
+    /// stream entries carry no types.
+    pub fn stream_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("schema_version", SAMPLE_EVENT_SCHEMA_VERSION.to_string()),
+            ("device_id", self.device_id.clone()),
+            ("domain", self.domain.clone()),
+            ("value", self.value.to_string()),
+            ("unit", self.unit.clone()),
+            (
+                "timestamp",
+                self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            ),
+            (
+                "timestamp_ms",
+                self.timestamp.timestamp_millis().to_string(),
+            ),
+        ];
+        if let Some(raw_value) = self.raw_value {
+            fields.push(("raw_value", raw_value.to_string()));
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn stream_fields_are_flat_strings() {
+        let event = SampleEvent {
+            device_id: "dev-1".to_string(),
+            domain: "SOUND_PRESSURE_LEVEL".to_string(),
+            value: 61.5,
+            raw_value: Some(63.0),
+            unit: "dB".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap(),
+        };
+        let fields = event.stream_fields();
+        assert_eq!(fields[0], ("schema_version", "1".to_string()));
+        assert!(fields.contains(&("value", "61.5".to_string())));
+        assert!(fields.contains(&("raw_value", "63".to_string())));
+        assert!(fields.contains(&("timestamp", "2025-06-01T12:00:00.000Z".to_string())));
+        assert!(fields.contains(&("timestamp_ms", "1748779200000".to_string())));
+
+        let uncalibrated = SampleEvent {
+            raw_value: None,
+            ..event
+        };
+        assert!(
+            !uncalibrated
+                .stream_fields()
+                .iter()
+                .any(|(name, _)| *name == "raw_value")
+        );
+    }
+}
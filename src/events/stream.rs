@@ -0,0 +1,35 @@
+use crate::consts::redis::{
+    REDIS_APPROXIMATE_TRIM, REDIS_AUTO_ID, REDIS_CMD_XADD, REDIS_MAXLEN_LABEL,
+};
+use crate::events::sample::SampleEvent;
+use crate::redis::RedisStore;
+
+/// The Redis Stream accepted samples are appended to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleStream {
+    pub key: String,
+    /// Approximate cap on the stream's length; older entries are trimmed as new ones arrive.
+    pub max_len: usize,
+}
+
+/// Appends a sample to the stream with `XADD <key> MAXLEN ~ <max_len> *`, returning the entry id.
+///
+/// Approximate trimming lets Redis drop whole macro nodes, which is far cheaper than an exact
+/// `MAXLEN`; the stream may briefly hold slightly more than `max_len` entries.
+pub async fn publish(
+    redis: &RedisStore,
+    stream: &SampleStream,
+    event: &SampleEvent,
+) -> anyhow::Result<String> {
+    let mut conn = redis.get_connection_manager().await?;
+    let mut cmd = redis::cmd(REDIS_CMD_XADD);
+    cmd.arg(&stream.key)
+        .arg(REDIS_MAXLEN_LABEL)
+        .arg(REDIS_APPROXIMATE_TRIM)
+        .arg(stream.max_len)
+        .arg(REDIS_AUTO_ID);
+    for (field, value) in event.stream_fields() {
+        cmd.arg(field).arg(value);
+    }
+    Ok(cmd.query_async(&mut conn).await?)
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One exported sample.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ExportRow {
+    pub device_id: String,
+    pub domain: String,
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+    pub unit: String,
+}
+
+/// File formats an export can be written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Header line of a CSV export.
+pub const CSV_HEADER: &str = "device_id,domain,timestamp,value,unit\n";
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+/// Appends rows to `out` as CSV lines, without the header.
+pub fn write_csv(rows: &[ExportRow], out: &mut Vec<u8>) {
+    for row in rows {
+        let line = format!(
+            "{},{},{},{},{}\n",
+            csv_field(&row.device_id),
+            csv_field(&row.domain),
+            row.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            row.value,
+            csv_field(&row.unit),
+        );
+        out.extend_from_slice(line.as_bytes());
+    }
+}
+
+/// Appends rows to `out` as newline-delimited JSON objects.
+pub fn write_ndjson(rows: &[ExportRow], out: &mut Vec<u8>) -> serde_json::Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut *out, row)?;
+        out.push(b'\n');
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn row(device_id: &str) -> ExportRow {
+        ExportRow {
+            device_id: device_id.to_string(),
+            domain: "SOUND_PRESSURE_LEVEL".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap(),
+            value: 55.5,
+            unit: "dB".to_string(),
+        }
+    }
+
+    #[test]
+    fn csv_lines_quote_when_needed() {
+        let mut out = Vec::new();
+        write_csv(&[row("dev-1"), row("lobby, \"east\"")], &mut out);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "dev-1,SOUND_PRESSURE_LEVEL,2025-06-01T12:00:00.000Z,55.5,dB\n\
+             \"lobby, \"\"east\"\"\",SOUND_PRESSURE_LEVEL,2025-06-01T12:00:00.000Z,55.5,dB\n"
+        );
+    }
+
+    #[test]
+    fn ndjson_lines() {
+        let mut out = Vec::new();
+        write_ndjson(&[row("dev-1")], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"device_id\":\"dev-1\",\"domain\":\"SOUND_PRESSURE_LEVEL\",\
+             \"timestamp\":\"2025-06-01T12:00:00Z\",\"value\":55.5,\"unit\":\"dB\"}\n"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod format;
+pub mod parquet;
+pub mod stream;
+
+// Re-export commonly used items
+pub use format::{ExportFormat, ExportRow};
+pub use stream::{ExportPlan, export_body};
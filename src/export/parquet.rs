@@ -0,0 +1,121 @@
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::export::format::ExportRow;
+
+/// Parquet schema of an export. Timestamps are UTC milliseconds, which pandas reads as
+/// `datetime64[ms, UTC]`.
+const SCHEMA: &str = "
+    message export {
+        REQUIRED BYTE_ARRAY device_id (UTF8);
+        REQUIRED BYTE_ARRAY domain (UTF8);
+        REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+        REQUIRED DOUBLE value;
+        REQUIRED BYTE_ARRAY unit (UTF8);
+    }
+";
+
+/// Writes export rows as a Parquet file, one row group per call to [`write_rows`], so only
+/// the current batch is held in memory.
+///
+/// [`write_rows`]: ParquetEncoder::write_rows
+pub struct ParquetEncoder<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+}
+
+impl<W: Write + Send> ParquetEncoder<W> {
+    pub fn new(sink: W) -> Result<Self, ParquetError> {
+        let schema = Arc::new(parse_message_type(SCHEMA)?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(Self {
+            writer: SerializedFileWriter::new(sink, schema, Arc::new(properties))?,
+        })
+    }
+
+    pub fn write_rows(&mut self, rows: &[ExportRow]) -> Result<(), ParquetError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let text = |f: fn(&ExportRow) -> &str| -> Vec<ByteArray> {
+            rows.iter().map(|r| ByteArray::from(f(r))).collect()
+        };
+        let device_ids = text(|r| &r.device_id);
+        let domains = text(|r| &r.domain);
+        let units = text(|r| &r.unit);
+        let timestamps: Vec<i64> = rows
+            .iter()
+            .map(|r| r.timestamp.timestamp_millis())
+            .collect();
+        let values: Vec<f64> = rows.iter().map(|r| r.value).collect();
+
+        let mut row_group = self.writer.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            match index {
+                0 => column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&device_ids, None, None)?,
+                1 => column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&domains, None, None)?,
+                2 => column
+                    .typed::<Int64Type>()
+                    .write_batch(&timestamps, None, None)?,
+                3 => column
+                    .typed::<DoubleType>()
+                    .write_batch(&values, None, None)?,
+                _ => column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&units, None, None)?,
+            };
+            column.close()?;
+            index += 1;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    /// Writes the footer. A file is unreadable until this succeeds.
+    pub fn finish(self) -> Result<(), ParquetError> {
+        self.writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn writes_readable_row_groups() {
+        let row = |value: f64| ExportRow {
+            device_id: "dev-1".to_string(),
+            domain: "SOUND_PRESSURE_LEVEL".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap(),
+            value,
+            unit: "dB".to_string(),
+        };
+
+        let mut out = Vec::new();
+        let mut encoder = ParquetEncoder::new(&mut out).unwrap();
+        encoder.write_rows(&[row(50.0), row(51.0)]).unwrap();
+        encoder.write_rows(&[row(52.0)]).unwrap();
+        encoder.finish().unwrap();
+
+        let reader = SerializedFileReader::new(axum::body::Bytes::from(out)).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.num_row_groups(), 2);
+        assert_eq!(metadata.file_metadata().num_rows(), 3);
+        assert_eq!(metadata.file_metadata().schema_descr().num_columns(), 5);
+    }
+}
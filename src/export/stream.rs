@@ -0,0 +1,229 @@
+use axum::body::{Body, Bytes};
+use chrono::{DateTime, Utc};
+use std::io::{self, Write};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+
+use crate::app_state::AppState;
+use crate::consts::errors::ERR_EXPORT;
+use crate::export::format::{self, CSV_HEADER, ExportFormat, ExportRow};
+use crate::export::parquet::ParquetEncoder;
+use crate::series;
+
+/// Samples read from RedisTimeSeries per `TS.RANGE` call, and rows per Parquet row group.
+const PAGE_SIZE: usize = 10_000;
+/// Encoded chunks buffered ahead of the client; a slow client pauses the reads.
+const BODY_CHANNEL_DEPTH: usize = 4;
+/// Parquet output is handed to the body in chunks of about this size.
+const PARQUET_CHUNK_BYTES: usize = 256 * 1024;
+
+/// One series to export, with the unit to report for it.
+#[derive(Clone, Debug)]
+pub struct ExportSeries {
+    pub device_id: String,
+    pub domain: String,
+    pub unit: String,
+}
+
+/// What to export: every sample of each series between `from` and `to`, inclusive.
+#[derive(Clone, Debug)]
+pub struct ExportPlan {
+    pub series: Vec<ExportSeries>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub format: ExportFormat,
+}
+
+type Chunk = Result<Bytes, io::Error>;
+
+/// Starts the export and returns its response body.
+///
+/// Series are read a page at a time and encoded as they arrive, so memory stays bounded
+/// however long the range is. If a read fails part-way, the body ends with an error and the
+/// client sees a truncated transfer rather than a silently short file.
+pub fn export_body(state: Arc<AppState>, plan: ExportPlan) -> Body {
+    let (tx, rx) = mpsc::channel::<Chunk>(BODY_CHANNEL_DEPTH);
+    tokio::spawn(async move {
+        let result = match plan.format {
+            ExportFormat::Csv | ExportFormat::Ndjson => write_text(&state, &plan, &tx).await,
+            ExportFormat::Parquet => write_parquet(&state, &plan, tx.clone()).await,
+        };
+        if let Err(e) = result {
+            error!(error = %e, "{ERR_EXPORT}");
+            let _ = tx.send(Err(io::Error::other(e.to_string()))).await;
+        }
+    });
+    Body::from_stream(ReceiverStream::new(rx))
+}
+
+/// Reads the plan's series one page at a time, in plan order.
+struct PageReader<'a> {
+    state: &'a AppState,
+    plan: &'a ExportPlan,
+    /// Index of the series being read.
+    series: usize,
+    /// Stored timestamp the next page of the current series starts at.
+    from: i64,
+}
+
+impl<'a> PageReader<'a> {
+    fn new(state: &'a AppState, plan: &'a ExportPlan) -> Self {
+        Self {
+            state,
+            plan,
+            series: 0,
+            from: series::to_series_timestamp(plan.from),
+        }
+    }
+
+    fn next_series(&mut self) {
+        self.series += 1;
+        self.from = series::to_series_timestamp(self.plan.from);
+    }
+
+    /// Returns the next non-empty page, or `None` once every series is exhausted.
+    async fn next(&mut self) -> anyhow::Result<Option<Vec<ExportRow>>> {
+        let to = series::to_series_timestamp(self.plan.to);
+        while let Some(selected) = self.plan.series.get(self.series) {
+            if self.from > to {
+                self.next_series();
+                continue;
+            }
+            let key = self
+                .state
+                .series_keys
+                .series(&selected.device_id, &selected.domain);
+            let samples = series::range_page(&self.state.redis, &key, self.from, to, PAGE_SIZE)
+                .await?
+                .unwrap_or_default();
+            match samples.last() {
+                Some(last) if samples.len() == PAGE_SIZE => {
+                    self.from = series::to_series_timestamp(last.timestamp) + 1;
+                }
+                _ => self.next_series(),
+            }
+            if samples.is_empty() {
+                continue;
+            }
+
+            let rows = samples
+                .into_iter()
+                .map(|s| ExportRow {
+                    device_id: selected.device_id.clone(),
+                    domain: selected.domain.clone(),
+                    timestamp: s.timestamp,
+                    value: s.value,
+                    unit: selected.unit.clone(),
+                })
+                .collect();
+            return Ok(Some(rows));
+        }
+        Ok(None)
+    }
+}
+
+async fn write_text(
+    state: &AppState,
+    plan: &ExportPlan,
+    tx: &mpsc::Sender<Chunk>,
+) -> anyhow::Result<()> {
+    if plan.format == ExportFormat::Csv && tx.send(Ok(Bytes::from(CSV_HEADER))).await.is_err() {
+        return Ok(());
+    }
+    let mut pages = PageReader::new(state, plan);
+    while let Some(rows) = pages.next().await? {
+        let mut out = Vec::new();
+        match plan.format {
+            ExportFormat::Ndjson => format::write_ndjson(&rows, &mut out)?,
+            _ => format::write_csv(&rows, &mut out),
+        }
+        if tx.send(Ok(Bytes::from(out))).await.is_err() {
+            // The client went away.
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Encodes on a blocking thread, since the Parquet writer is synchronous, fed one page at a
+/// time over a channel of depth one. `None` on the channel aborts the file without a footer.
+async fn write_parquet(
+    state: &AppState,
+    plan: &ExportPlan,
+    tx: mpsc::Sender<Chunk>,
+) -> anyhow::Result<()> {
+    let (rows_tx, mut rows_rx) = mpsc::channel::<Option<Vec<ExportRow>>>(1);
+    let encoder = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        let mut encoder = ParquetEncoder::new(ChannelWriter::new(tx))?;
+        while let Some(page) = rows_rx.blocking_recv() {
+            let Some(rows) = page else {
+                anyhow::bail!("export aborted");
+            };
+            encoder.write_rows(&rows)?;
+        }
+        encoder.finish()?;
+        Ok(())
+    });
+
+    let mut pages = PageReader::new(state, plan);
+    let read = loop {
+        match pages.next().await {
+            Ok(Some(rows)) => {
+                if rows_tx.send(Some(rows)).await.is_err() {
+                    // The encoder stopped; its error is reported below.
+                    break Ok(());
+                }
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => {
+                let _ = rows_tx.send(None).await;
+                break Err(e);
+            }
+        }
+    };
+    drop(rows_tx);
+    let encoded = encoder.await?;
+    read?;
+    encoded
+}
+
+/// A blocking `Write` that forwards buffered output to the response body.
+struct ChannelWriter {
+    tx: mpsc::Sender<Chunk>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn new(tx: mpsc::Sender<Chunk>) -> Self {
+        Self {
+            tx,
+            buffer: Vec::with_capacity(PARQUET_CHUNK_BYTES),
+        }
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(PARQUET_CHUNK_BYTES));
+        self.tx
+            .blocking_send(Ok(Bytes::from(chunk)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "export client disconnected"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= PARQUET_CHUNK_BYTES {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
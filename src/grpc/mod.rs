@@ -0,0 +1,6 @@
+pub mod server;
+pub mod service;
+
+// Re-export commonly used items
+pub use server::{PeerInfo, serve};
+pub use service::SensorIngestService;
@@ -0,0 +1,163 @@
+use rustls::ServerConfig;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Server;
+use tonic::transport::server::Connected;
+use tracing::warn;
+
+use super::SensorIngestService;
+use crate::app_state::AppState;
+use crate::auth::client_cert::ClientCertificate;
+use crate::consts::errors::ERR_GRPC_ACCEPT;
+use crate::sensor::sensor_ingest_server::SensorIngestServer;
+use crate::tls;
+
+/// Accepted connections that may wait for the server to pick them up.
+const ACCEPT_BACKLOG: usize = 64;
+
+/// Set on every request of a connection: who is on the other end and, over TLS, the client
+/// certificate they presented.
+#[derive(Clone, Debug)]
+pub struct PeerInfo {
+    pub remote_addr: SocketAddr,
+    pub certificate: Option<ClientCertificate>,
+}
+
+/// Serves the `SensorIngest` service until `signal` resolves, then stops accepting and lets open
+/// calls finish. Uses TLS when `config` is set, with the same certificate and client CA as HTTP.
+pub async fn serve<F>(
+    state: Arc<AppState>,
+    listener: TcpListener,
+    config: Option<Arc<ServerConfig>>,
+    signal: F,
+) -> anyhow::Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let incoming = accept(listener, config.map(TlsAcceptor::from));
+    Server::builder()
+        .add_service(SensorIngestServer::new(SensorIngestService::new(state)))
+        .serve_with_incoming_shutdown(incoming, signal)
+        .await?;
+    Ok(())
+}
+
+/// Accepts connections, completing TLS handshakes off the accept loop, until the server drops the
+/// stream. Unlike tonic's own listener a failed accept is logged rather than ending the server.
+fn accept(
+    listener: TcpListener,
+    acceptor: Option<TlsAcceptor>,
+) -> ReceiverStream<io::Result<Connection>> {
+    let (tx, rx) = mpsc::channel(ACCEPT_BACKLOG);
+    tokio::spawn(async move {
+        loop {
+            let (stream, remote_addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!(error = %e, "{ERR_GRPC_ACCEPT}");
+                        continue;
+                    }
+                },
+                () = tx.closed() => break,
+            };
+
+            let Some(acceptor) = acceptor.clone() else {
+                let connection = Connection {
+                    stream: Stream::Plain(stream),
+                    peer: PeerInfo {
+                        remote_addr,
+                        certificate: None,
+                    },
+                };
+                let _ = tx.send(Ok(connection)).await;
+                continue;
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Some((stream, certificate)) =
+                    tls::server::handshake(&acceptor, stream, remote_addr).await
+                {
+                    let connection = Connection {
+                        stream: Stream::Tls(Box::new(stream)),
+                        peer: PeerInfo {
+                            remote_addr,
+                            certificate,
+                        },
+                    };
+                    let _ = tx.send(Ok(connection)).await;
+                }
+            });
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+/// A client connection, plain or TLS, that hands tonic its [`PeerInfo`].
+struct Connection {
+    stream: Stream,
+    peer: PeerInfo,
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Connected for Connection {
+    type ConnectInfo = PeerInfo;
+
+    fn connect_info(&self) -> PeerInfo {
+        self.peer.clone()
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.stream {
+            Stream::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Stream::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.stream {
+            Stream::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Stream::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.stream {
+            Stream::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Stream::Tls(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.stream {
+            Stream::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Stream::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{TimeZone, Utc};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status, Streaming};
+
+use super::PeerInfo;
+use crate::app_state::AppState;
+use crate::auth::api_key::{API_KEY_PREFIX, AuthFailure, parse_authorization, verify_api_key};
+use crate::consts::errors::{
+    ERR_DOMAIN_LOOKUP, ERR_GRPC_PUSH, ERR_GRPC_QUERY, ERR_INGEST_QUEUE_CLOSED,
+    MSG_CERTIFICATE_DEVICE_MISMATCH, MSG_INGEST_QUEUE_FULL, MSG_INVALID_UTF8_DEVICE_ID,
+    MSG_SAMPLE_NOT_STORED, MSG_UNREGISTERED_DEVICE,
+};
+use crate::domains::{catalog, store};
+use crate::ingest::{self, IngestError, Sample, Source, UNKNOWN_DOMAIN_LABEL};
+use crate::metrics;
+use crate::sensor::sensor_ingest_server::SensorIngest;
+use crate::sensor::{
+    Point, PushResponse, QueryRequest, QueryResponse, RejectedSample, SensorData, SensorDataBatch,
+};
+use crate::series;
+
+/// Metadata entry carrying the API key; gRPC metadata keys are lowercase.
+const AUTH_METADATA: &str = "authorization";
+/// Most samples a single query returns.
+const MAX_QUERY_POINTS: u32 = 10_000;
+
+/// What became of one sample of a push or stream.
+enum Outcome {
+    Stored,
+    Spooled,
+    Rejected { reason: String, retry: bool },
+}
+
+/// The `SensorIngest` gRPC service: the same ingest path as `POST /ingest`, and reads of single
+/// series.
+pub struct SensorIngestService {
+    state: Arc<AppState>,
+}
+
+impl SensorIngestService {
+    pub fn new(state: Arc<AppState>) -> Self {
+        Self { state }
+    }
+
+    /// Checks the call's API key, or when `devices` is set, accepts a verified client certificate
+    /// in its place as `validate_device` does. Returns the device a certificate names.
+    async fn authenticate(
+        &self,
+        metadata: &MetadataMap,
+        peer: Option<&PeerInfo>,
+        devices: bool,
+    ) -> Result<Option<String>, Status> {
+        if devices
+            && !metadata.contains_key(AUTH_METADATA)
+            && let Some(PeerInfo {
+                certificate: Some(certificate),
+                ..
+            }) = peer
+        {
+            return Ok(Some(certificate.device_id.clone()));
+        }
+        let api_key = metadata
+            .get(AUTH_METADATA)
+            .map(|value| value.to_str().map_err(|_| AuthFailure::MalformedHeader))
+            .transpose()
+            .and_then(parse_authorization);
+        verify_api_key(&self.state, api_key, API_KEY_PREFIX, "standard")
+            .await
+            .map_err(|failure| match failure {
+                AuthFailure::StoreError => Status::internal(failure.as_str()),
+                _ => Status::unauthenticated(failure.as_str()),
+            })?;
+        Ok(None)
+    }
+
+    /// Stores one sample like `POST /ingest`, spooling it if Redis cannot be reached.
+    async fn store(state: &AppState, sample: Sample) -> Outcome {
+        let rejected = |reason: &str, retry| Outcome::Rejected {
+            reason: reason.to_string(),
+            retry,
+        };
+        match ingest::store_sample(state, &sample, Source::Ingest).await {
+            Ok(event) => {
+                ingest::record_stored(state, event).await;
+                Outcome::Stored
+            }
+            Err(IngestError::UnregisteredDevice) => rejected(MSG_UNREGISTERED_DEVICE, false),
+            Err(IngestError::Rejected(rejection)) => rejected(&rejection.to_string(), false),
+            Err(IngestError::Busy) => rejected(MSG_INGEST_QUEUE_FULL, true),
+            Err(IngestError::Closed) => {
+                tracing::error!(device_id = %sample.device_id, "{ERR_INGEST_QUEUE_CLOSED}");
+                rejected(MSG_SAMPLE_NOT_STORED, true)
+            }
+            Err(IngestError::Redis {
+                context,
+                error,
+                unreachable: true,
+            }) => match ingest::spool_sample(state, sample, context, error).await {
+                Ok(()) => Outcome::Spooled,
+                Err(error) => {
+                    tracing::error!(error = %error, "{context}");
+                    rejected(MSG_SAMPLE_NOT_STORED, true)
+                }
+            },
+            Err(IngestError::Redis { context, error, .. }) => {
+                tracing::error!(error = %error, "{context}");
+                rejected(MSG_SAMPLE_NOT_STORED, false)
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SensorIngest for SensorIngestService {
+    async fn push(
+        &self,
+        request: Request<SensorDataBatch>,
+    ) -> Result<Response<PushResponse>, Status> {
+        let certified = self
+            .authenticate(request.metadata(), request.extensions().get(), true)
+            .await?;
+        // Refuse the whole batch before storing any of it if it names another device.
+        let samples = request
+            .into_inner()
+            .samples
+            .iter()
+            .map(|data| read_sample(data, certified.as_deref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Stored concurrently, so the batch's samples share write batches instead of each waiting
+        // out a flush of its own.
+        let mut stores = JoinSet::new();
+        for (index, sample) in samples.into_iter().enumerate() {
+            let state = self.state.clone();
+            stores.spawn(async move {
+                let outcome = match sample {
+                    Some(sample) => Self::store(&state, sample).await,
+                    None => invalid_device_id(),
+                };
+                (index, outcome)
+            });
+        }
+        let mut outcomes = Vec::with_capacity(stores.len());
+        while let Some(joined) = stores.join_next().await {
+            outcomes.push(joined.map_err(|e| internal(ERR_GRPC_PUSH, e))?);
+        }
+        outcomes.sort_by_key(|(index, _)| *index);
+
+        let mut response = PushResponse::default();
+        for (index, outcome) in outcomes {
+            tally(&mut response, index, outcome);
+        }
+        Ok(Response::new(response))
+    }
+
+    async fn stream_samples(
+        &self,
+        request: Request<Streaming<SensorData>>,
+    ) -> Result<Response<PushResponse>, Status> {
+        let certified = self
+            .authenticate(request.metadata(), request.extensions().get(), true)
+            .await?;
+        let mut stream = request.into_inner();
+
+        // Samples are read one at a time, so a slow Redis slows the client through flow control.
+        let mut response = PushResponse::default();
+        let mut index = 0;
+        while let Some(data) = stream.message().await? {
+            let outcome = match read_sample(&data, certified.as_deref())? {
+                Some(sample) => Self::store(&self.state, sample).await,
+                None => invalid_device_id(),
+            };
+            tally(&mut response, index, outcome);
+            index += 1;
+        }
+        Ok(Response::new(response))
+    }
+
+    async fn query(
+        &self,
+        request: Request<QueryRequest>,
+    ) -> Result<Response<QueryResponse>, Status> {
+        self.authenticate(request.metadata(), request.extensions().get(), false)
+            .await?;
+        let query = request.into_inner();
+        if query.device_id.is_empty() || query.domain.is_empty() {
+            return Err(Status::invalid_argument(
+                "device_id and domain are required",
+            ));
+        }
+        if query.from > query.to {
+            return Err(Status::invalid_argument("from must not be after to"));
+        }
+        let (Some(from), Some(to)) = (series_timestamp(query.from), series_timestamp(query.to))
+        else {
+            return Err(Status::invalid_argument(
+                "from and to must be valid Unix seconds",
+            ));
+        };
+
+        let state = &self.state;
+        let spec = match store::lookup(&state.redis, &state.domains, &query.domain).await {
+            Ok(Some(spec)) => spec,
+            Ok(None) => {
+                let msg = format!("unknown domain {}", query.domain);
+                return Err(Status::not_found(msg));
+            }
+            Err(e) => return Err(internal(ERR_DOMAIN_LOOKUP, e)),
+        };
+
+        let limit = match query.limit {
+            0 => MAX_QUERY_POINTS,
+            limit => limit.min(MAX_QUERY_POINTS),
+        } as usize;
+        let key = state.series_keys.series(&query.device_id, &spec.name);
+        // One more than the limit tells whether the range holds more.
+        let mut samples = series::range_page(&state.redis, &key, from, to, limit + 1)
+            .await
+            .map_err(|e| internal(ERR_GRPC_QUERY, e))?
+            .unwrap_or_default();
+        let truncated = samples.len() > limit;
+        samples.truncate(limit);
+
+        Ok(Response::new(QueryResponse {
+            points: samples
+                .into_iter()
+                .map(|sample| Point {
+                    timestamp: sample.timestamp.timestamp(),
+                    value: sample.value,
+                })
+                .collect(),
+            unit: spec.unit,
+            truncated,
+        }))
+    }
+}
+
+/// A sample names another device than the caller's client certificate; fails the whole call.
+struct CertificateMismatch;
+
+impl From<CertificateMismatch> for Status {
+    fn from(_: CertificateMismatch) -> Self {
+        Status::permission_denied(MSG_CERTIFICATE_DEVICE_MISMATCH)
+    }
+}
+
+/// Reads a sample off the wire. `None` for a device ID that is not UTF-8, which rejects just that
+/// sample.
+fn read_sample(
+    data: &SensorData,
+    certified: Option<&str>,
+) -> Result<Option<Sample>, CertificateMismatch> {
+    let Ok(device_id) = std::str::from_utf8(&data.device_id) else {
+        metrics::sample_rejected(UNKNOWN_DOMAIN_LABEL, "invalid_device_id");
+        return Ok(None);
+    };
+    if let Some(certified) = certified
+        && certified != device_id
+    {
+        tracing::warn!(device_id = %device_id, certificate = %certified, "{MSG_CERTIFICATE_DEVICE_MISMATCH}");
+        metrics::sample_rejected(UNKNOWN_DOMAIN_LABEL, "certificate_mismatch");
+        return Err(CertificateMismatch);
+    }
+    Ok(Some(Sample {
+        device_id: device_id.to_owned(),
+        domain: catalog::sample_domain_name(data),
+        value: f64::from(data.datum),
+        received_at: Utc::now(),
+    }))
+}
+
+/// Reads a bound of a query, given in Unix seconds, as a series timestamp.
+fn series_timestamp(seconds: i64) -> Option<i64> {
+    let at = Utc.timestamp_opt(seconds, 0).single()?;
+    Some(series::to_series_timestamp(at))
+}
+
+fn invalid_device_id() -> Outcome {
+    Outcome::Rejected {
+        reason: MSG_INVALID_UTF8_DEVICE_ID.to_string(),
+        retry: false,
+    }
+}
+
+fn tally(response: &mut PushResponse, index: usize, outcome: Outcome) {
+    match outcome {
+        Outcome::Stored => response.accepted += 1,
+        Outcome::Spooled => response.spooled += 1,
+        Outcome::Rejected { reason, retry } => response.rejected.push(RejectedSample {
+            index: index as u32,
+            reason,
+            retry,
+        }),
+    }
+}
+
+/// Logs a failure with a correlation ID, like `log_and_response`, and answers with that ID only.
+fn internal(context: &str, error: impl std::fmt::Display) -> Status {
+    let correlation_id = uuid::Uuid::new_v4().to_string();
+    tracing::error!(correlation_id = %correlation_id, error = %error, "{context}");
+    Status::internal(format!("internal error (correlation id: {correlation_id})"))
+}
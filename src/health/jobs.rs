@@ -0,0 +1,166 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use super::CheckStatus;
+
+/// A periodic job is reported failed once it has not finished a run for this many intervals.
+const STALLED_AFTER_INTERVALS: u32 = 3;
+
+/// Outcomes of the background loops, which report every run here for `GET /healthz/details`.
+#[derive(Clone, Default)]
+pub struct JobMonitor(Arc<Mutex<BTreeMap<&'static str, JobRecord>>>);
+
+struct JobRecord {
+    interval: Option<Duration>,
+    registered_at: DateTime<Utc>,
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobReport {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+impl JobMonitor {
+    /// Starts tracking a job. One that runs every `interval` is reported stalled when it stops
+    /// finishing runs; one without (a long-lived connection) only by its failures.
+    pub fn register(&self, name: &'static str, interval: Option<Duration>) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                name,
+                JobRecord {
+                    interval,
+                    registered_at: Utc::now(),
+                    last_success: None,
+                    last_failure: None,
+                    last_error: None,
+                    consecutive_failures: 0,
+                },
+            );
+    }
+
+    /// Records the outcome of one run of a registered job.
+    pub fn record<T>(&self, name: &'static str, result: &anyhow::Result<T>) {
+        let mut jobs = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(job) = jobs.get_mut(name) else {
+            return;
+        };
+        let now = Utc::now();
+        match result {
+            Ok(_) => {
+                job.last_success = Some(now);
+                job.consecutive_failures = 0;
+            }
+            Err(e) => {
+                job.last_failure = Some(now);
+                job.last_error = Some(format!("{e:#}"));
+                job.consecutive_failures += 1;
+            }
+        }
+    }
+
+    /// Every registered job by name: failed when stalled, degraded while its last run failed.
+    pub fn report(&self, now: DateTime<Utc>) -> Vec<JobReport> {
+        let jobs = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.iter()
+            .map(|(&name, job)| {
+                let (status, message) = job.status(now);
+                JobReport {
+                    name,
+                    status,
+                    message,
+                    interval_secs: job.interval.map(|i| i.as_secs()),
+                    last_success: job.last_success,
+                    last_failure: job.last_failure,
+                    last_error: job.last_error.clone(),
+                    consecutive_failures: job.consecutive_failures,
+                }
+            })
+            .collect()
+    }
+}
+
+impl JobRecord {
+    fn status(&self, now: DateTime<Utc>) -> (CheckStatus, Option<String>) {
+        if let Some(interval) = self.interval {
+            let last_run = [self.last_success, self.last_failure]
+                .into_iter()
+                .flatten()
+                .max()
+                .unwrap_or(self.registered_at);
+            let silent = (now - last_run).to_std().unwrap_or_default();
+            if silent > interval * STALLED_AFTER_INTERVALS {
+                return (
+                    CheckStatus::Failed,
+                    Some(format!("no run finished for {}s", silent.as_secs())),
+                );
+            }
+        }
+        if self.consecutive_failures > 0 {
+            let message = format!("last {} runs failed", self.consecutive_failures);
+            return (CheckStatus::Degraded, Some(message));
+        }
+        (CheckStatus::Ok, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job_status(monitor: &JobMonitor, now: DateTime<Utc>) -> CheckStatus {
+        monitor.report(now)[0].status
+    }
+
+    #[test]
+    fn failures_degrade_until_a_run_succeeds() {
+        let monitor = JobMonitor::default();
+        monitor.register("job", Some(Duration::from_secs(60)));
+        monitor.record::<()>("job", &Err(anyhow::anyhow!("redis down")));
+        monitor.record::<()>("job", &Err(anyhow::anyhow!("redis down")));
+
+        let report = &monitor.report(Utc::now())[0];
+        assert_eq!(report.status, CheckStatus::Degraded);
+        assert_eq!(report.consecutive_failures, 2);
+        assert_eq!(report.last_error.as_deref(), Some("redis down"));
+
+        monitor.record("job", &Ok(()));
+        assert_eq!(job_status(&monitor, Utc::now()), CheckStatus::Ok);
+    }
+
+    #[test]
+    fn periodic_job_without_runs_stalls() {
+        let monitor = JobMonitor::default();
+        monitor.register("job", Some(Duration::from_secs(60)));
+        monitor.record("job", &Ok(()));
+
+        let later = Utc::now() + chrono::Duration::seconds(179);
+        assert_eq!(job_status(&monitor, later), CheckStatus::Ok);
+        let later = Utc::now() + chrono::Duration::seconds(200);
+        assert_eq!(job_status(&monitor, later), CheckStatus::Failed);
+
+        // Long-lived jobs never stall.
+        let monitor = JobMonitor::default();
+        monitor.register("relay", None);
+        let later = Utc::now() + chrono::Duration::days(1);
+        assert_eq!(job_status(&monitor, later), CheckStatus::Ok);
+    }
+}
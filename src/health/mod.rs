@@ -0,0 +1,215 @@
+pub mod jobs;
+pub mod redis;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::app_state::AppState;
+use crate::consts::health::{
+    CHECK_BACKGROUND_TASKS, CHECK_INGEST_QUEUE, CHECK_SHUTDOWN, CHECK_SPOOL, MSG_SHUTTING_DOWN,
+};
+use crate::ingest::WriteQueue;
+use crate::spool::Spool;
+
+// Re-export commonly used items
+pub use jobs::{JobMonitor, JobReport};
+
+/// Outcome of one check. `Degraded` still serves traffic but needs attention; `Skipped` means a
+/// check could not run because one it depends on failed, which is reported on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Degraded,
+    Failed,
+    Skipped,
+}
+
+impl CheckStatus {
+    fn severity(self) -> u8 {
+        match self {
+            Self::Ok | Self::Skipped => 0,
+            Self::Degraded => 1,
+            Self::Failed => 2,
+        }
+    }
+
+    /// The more severe of the two.
+    pub fn max(self, other: Self) -> Self {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub details: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Check {
+    pub fn ok(name: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Ok,
+            latency_ms: None,
+            message: None,
+            details: serde_json::Map::new(),
+        }
+    }
+
+    pub fn failed(name: &'static str, message: impl Into<String>) -> Self {
+        Self::ok(name).downgrade(CheckStatus::Failed, message)
+    }
+
+    pub fn skipped(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Skipped,
+            message: Some(message.into()),
+            ..Self::ok(name)
+        }
+    }
+
+    /// Lowers the status to `status` unless it is already worse, adding `message` to any earlier
+    /// one.
+    pub fn downgrade(mut self, status: CheckStatus, message: impl Into<String>) -> Self {
+        self.status = self.status.max(status);
+        let message = message.into();
+        self.message = Some(match self.message.take() {
+            Some(earlier) => format!("{earlier}; {message}"),
+            None => message,
+        });
+        self
+    }
+
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency_ms = Some(latency.as_secs_f64() * 1000.0);
+        self
+    }
+
+    pub fn with_detail(mut self, key: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.details.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// Everything `GET /healthz/details` reports. `status` is the worst of the checks and jobs.
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: CheckStatus,
+    pub checked_at: DateTime<Utc>,
+    pub checks: Vec<Check>,
+    pub jobs: Vec<JobReport>,
+}
+
+/// The checks `/readyz` gates on: Redis answers and has the RedisTimeSeries module loaded.
+pub async fn readiness(state: &AppState) -> Vec<Check> {
+    redis::checks(&state.redis, false).await
+}
+
+/// Share of the spool's limit, or of the write queue's depth, above which either is reported
+/// degraded; samples are refused once full.
+const FULL_DEGRADED_RATIO: f64 = 0.9;
+
+/// Runs every check, including Redis memory and persistence and the state of background jobs.
+pub async fn report(state: &AppState) -> HealthReport {
+    let mut checks = redis::checks(&state.redis, true).await;
+    checks.push(shutdown_check(state));
+    checks.push(
+        Check::ok(CHECK_BACKGROUND_TASKS)
+            .with_detail("running", state.lifecycle.running_tasks() as u64),
+    );
+    checks.push(ingest_queue_check(&state.write_queue));
+    if let Some(spool) = &state.spool {
+        checks.push(spool_check(spool));
+    }
+
+    let checked_at = Utc::now();
+    let jobs = state.jobs.report(checked_at);
+    let status = checks
+        .iter()
+        .map(|c| c.status)
+        .chain(jobs.iter().map(|j| j.status))
+        .fold(CheckStatus::Ok, CheckStatus::max);
+    HealthReport {
+        status,
+        checked_at,
+        checks,
+        jobs,
+    }
+}
+
+fn shutdown_check(state: &AppState) -> Check {
+    if state.lifecycle.is_draining() {
+        Check::failed(CHECK_SHUTDOWN, MSG_SHUTTING_DOWN)
+    } else {
+        Check::ok(CHECK_SHUTDOWN)
+    }
+}
+
+/// Reports requests waiting for a writer, degraded once the queue is close to full.
+fn ingest_queue_check(queue: &WriteQueue) -> Check {
+    let (depth, capacity) = (queue.depth(), queue.capacity());
+    let check = Check::ok(CHECK_INGEST_QUEUE)
+        .with_detail("depth", depth as u64)
+        .with_detail("capacity", capacity as u64);
+    let ratio = depth as f64 / capacity as f64;
+    if ratio >= FULL_DEGRADED_RATIO {
+        check.downgrade(
+            CheckStatus::Degraded,
+            format!("write queue is {:.0}% full", ratio * 100.0),
+        )
+    } else {
+        check
+    }
+}
+
+/// Reports samples waiting for replay, degraded once the spool is close to its limit.
+fn spool_check(spool: &Spool) -> Check {
+    let (bytes, max_bytes) = spool.usage();
+    let check = Check::ok(CHECK_SPOOL)
+        .with_detail("bytes", bytes)
+        .with_detail("max_bytes", max_bytes);
+    let ratio = bytes as f64 / max_bytes as f64;
+    if ratio >= FULL_DEGRADED_RATIO {
+        check.downgrade(
+            CheckStatus::Degraded,
+            format!("spool is {:.0}% full", ratio * 100.0),
+        )
+    } else {
+        check
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downgrade_keeps_the_worst_status() {
+        let check = Check::ok("test")
+            .downgrade(CheckStatus::Failed, "first")
+            .downgrade(CheckStatus::Degraded, "second");
+        assert_eq!(check.status, CheckStatus::Failed);
+        assert_eq!(check.message.as_deref(), Some("first; second"));
+    }
+
+    #[test]
+    fn skipped_does_not_lower_the_overall_status() {
+        assert_eq!(CheckStatus::Ok.max(CheckStatus::Skipped), CheckStatus::Ok);
+        assert_eq!(
+            CheckStatus::Degraded.max(CheckStatus::Skipped),
+            CheckStatus::Degraded
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use redis::{FromRedisValue, InfoDict, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use super::{Check, CheckStatus};
+use crate::consts::health::{
+    CHECK_REDIS, CHECK_REDIS_MEMORY, CHECK_REDIS_PERSISTENCE, CHECK_TIMESERIES_MODULE,
+};
+use crate::consts::redis::{
+    PING_CMD, PONG_CMD, REDIS_CMD_INFO, REDIS_CMD_MODULE, REDIS_INFO_MEMORY,
+    REDIS_INFO_PERSISTENCE, REDIS_MODULE_LIST, REDIS_TIMESERIES_MODULE,
+};
+use crate::redis::RedisStore;
+
+/// How long each round trip may take before its check fails, so a hung Redis cannot hang the
+/// probes.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Share of `maxmemory` above which Redis is reported degraded; writes fail once it is reached.
+const MEMORY_DEGRADED_RATIO: f64 = 0.9;
+
+/// Checks that Redis answers PING and has the RedisTimeSeries module loaded and, when `detailed`,
+/// its memory usage and persistence. Checks after a failed PING are skipped, so a network failure
+/// is told apart from a Redis without the module.
+pub async fn checks(redis: &RedisStore, detailed: bool) -> Vec<Check> {
+    let started = Instant::now();
+    let connected = with_timeout(async {
+        let mut conn = redis.get_connection_manager().await?;
+        let pong: String = redis::cmd(PING_CMD).query_async(&mut conn).await?;
+        anyhow::ensure!(pong == PONG_CMD, "unexpected PING response: {pong}");
+        Ok(conn)
+    })
+    .await;
+
+    let mut conn = match connected {
+        Ok(conn) => conn,
+        Err(e) => {
+            let mut checks =
+                vec![Check::failed(CHECK_REDIS, format!("{e:#}")).with_latency(started.elapsed())];
+            let mut dependent = vec![CHECK_TIMESERIES_MODULE];
+            if detailed {
+                dependent.extend([CHECK_REDIS_MEMORY, CHECK_REDIS_PERSISTENCE]);
+            }
+            checks.extend(
+                dependent
+                    .into_iter()
+                    .map(|name| Check::skipped(name, "Redis is unreachable")),
+            );
+            return checks;
+        }
+    };
+    let mut checks = vec![Check::ok(CHECK_REDIS).with_latency(started.elapsed())];
+
+    let modules = with_timeout(async {
+        let modules: Vec<HashMap<String, Value>> = redis::cmd(REDIS_CMD_MODULE)
+            .arg(REDIS_MODULE_LIST)
+            .query_async(&mut conn)
+            .await?;
+        Ok(modules)
+    })
+    .await;
+    checks.push(match modules {
+        Ok(modules) => timeseries_module_check(&modules),
+        Err(e) => Check::failed(
+            CHECK_TIMESERIES_MODULE,
+            format!("MODULE LIST failed: {e:#}"),
+        ),
+    });
+
+    if detailed {
+        let info = with_timeout(async {
+            let info: (InfoDict, InfoDict) = redis::pipe()
+                .cmd(REDIS_CMD_INFO)
+                .arg(REDIS_INFO_MEMORY)
+                .cmd(REDIS_CMD_INFO)
+                .arg(REDIS_INFO_PERSISTENCE)
+                .query_async(&mut conn)
+                .await?;
+            Ok(info)
+        })
+        .await;
+        match info {
+            Ok((memory, persistence)) => {
+                checks.push(memory_check(&memory));
+                checks.push(persistence_check(&persistence));
+            }
+            Err(e) => {
+                let message = format!("INFO failed: {e:#}");
+                checks
+                    .push(Check::ok(CHECK_REDIS_MEMORY).downgrade(CheckStatus::Degraded, &message));
+                checks.push(
+                    Check::ok(CHECK_REDIS_PERSISTENCE).downgrade(CheckStatus::Degraded, message),
+                );
+            }
+        }
+    }
+    checks
+}
+
+async fn with_timeout<T>(check: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out after {}s", CHECK_TIMEOUT.as_secs()))?
+}
+
+/// Finds RedisTimeSeries in a `MODULE LIST` reply, reporting its version.
+fn timeseries_module_check(modules: &[HashMap<String, Value>]) -> Check {
+    let module = modules.iter().find(|module| {
+        module
+            .get("name")
+            .and_then(|name| String::from_redis_value(name).ok())
+            .is_some_and(|name| name.eq_ignore_ascii_case(REDIS_TIMESERIES_MODULE))
+    });
+    match module {
+        Some(module) => {
+            let check = Check::ok(CHECK_TIMESERIES_MODULE);
+            match module
+                .get("ver")
+                .and_then(|v| i64::from_redis_value(v).ok())
+            {
+                Some(version) => check.with_detail("version", version),
+                None => check,
+            }
+        }
+        None => Check::failed(
+            CHECK_TIMESERIES_MODULE,
+            "RedisTimeSeries module is not loaded",
+        ),
+    }
+}
+
+/// Reports memory in use against `maxmemory`, degraded once close to the limit.
+fn memory_check(info: &InfoDict) -> Check {
+    let Some(used) = info.get::<u64>("used_memory") else {
+        return Check::ok(CHECK_REDIS_MEMORY).downgrade(
+            CheckStatus::Degraded,
+            "INFO memory did not report used_memory",
+        );
+    };
+    let max = info.get::<u64>("maxmemory").unwrap_or(0);
+    let mut check = Check::ok(CHECK_REDIS_MEMORY)
+        .with_detail("used_bytes", used)
+        .with_detail("max_bytes", max);
+    if let Some(fragmentation) = info.get::<f64>("mem_fragmentation_ratio") {
+        check = check.with_detail("fragmentation_ratio", fragmentation);
+    }
+    if max > 0 {
+        let ratio = used as f64 / max as f64;
+        check = check.with_detail("usage_ratio", ratio);
+        if ratio >= MEMORY_DEGRADED_RATIO {
+            check = check.downgrade(
+                CheckStatus::Degraded,
+                format!("using {:.0}% of maxmemory", ratio * 100.0),
+            );
+        }
+    }
+    check
+}
+
+/// Reports the last RDB save and AOF write. Either failing fails the check, since Redis refuses
+/// writes after a failed save by default.
+fn persistence_check(info: &InfoDict) -> Check {
+    let bgsave_status = info.get::<String>("rdb_last_bgsave_status");
+    let aof_enabled = info.get::<u64>("aof_enabled") == Some(1);
+    let aof_status = info.get::<String>("aof_last_write_status");
+
+    let mut check = Check::ok(CHECK_REDIS_PERSISTENCE).with_detail("aof_enabled", aof_enabled);
+    if let Some(status) = &bgsave_status {
+        check = check.with_detail("rdb_last_bgsave_status", status.as_str());
+    }
+    if let Some(changes) = info.get::<u64>("rdb_changes_since_last_save") {
+        check = check.with_detail("rdb_changes_since_last_save", changes);
+    }
+    if let Some(saved_at) = info.get::<i64>("rdb_last_save_time") {
+        check = check.with_detail("rdb_last_save_time", saved_at);
+    }
+    if let Some(status) = &aof_status {
+        check = check.with_detail("aof_last_write_status", status.as_str());
+    }
+
+    if info.get::<u64>("loading") == Some(1) {
+        check = check.downgrade(CheckStatus::Degraded, "loading the dataset from disk");
+    }
+    if bgsave_status.as_deref().is_some_and(|s| s != "ok") {
+        check = check.downgrade(CheckStatus::Failed, "last RDB save failed");
+    }
+    if aof_enabled && aof_status.as_deref().is_some_and(|s| s != "ok") {
+        check = check.downgrade(CheckStatus::Failed, "last AOF write failed");
+    }
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(name: &str, version: i64) -> HashMap<String, Value> {
+        HashMap::from([
+            ("name".to_string(), Value::Data(name.as_bytes().to_vec())),
+            ("ver".to_string(), Value::Int(version)),
+        ])
+    }
+
+    #[test]
+    fn finds_the_timeseries_module() {
+        let check =
+            timeseries_module_check(&[module("search", 20811), module("timeseries", 11011)]);
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert_eq!(check.details["version"], 11011);
+
+        let check = timeseries_module_check(&[module("search", 20811)]);
+        assert_eq!(check.status, CheckStatus::Failed);
+    }
+
+    #[test]
+    fn memory_near_the_limit_is_degraded() {
+        let info =
+            InfoDict::new("used_memory:950\r\nmaxmemory:1000\r\nmem_fragmentation_ratio:1.2\r\n");
+        let check = memory_check(&info);
+        assert_eq!(check.status, CheckStatus::Degraded);
+        assert_eq!(check.details["used_bytes"], 950);
+
+        // Without a limit only the usage is reported.
+        let check = memory_check(&InfoDict::new("used_memory:950\r\nmaxmemory:0\r\n"));
+        assert_eq!(check.status, CheckStatus::Ok);
+        assert!(!check.details.contains_key("usage_ratio"));
+    }
+
+    #[test]
+    fn failed_saves_fail_persistence() {
+        let healthy = "loading:0\r\nrdb_last_bgsave_status:ok\r\naof_enabled:1\r\naof_last_write_status:ok\r\n";
+        assert_eq!(
+            persistence_check(&InfoDict::new(healthy)).status,
+            CheckStatus::Ok
+        );
+
+        let check = persistence_check(&InfoDict::new(
+            "loading:0\r\nrdb_last_bgsave_status:err\r\naof_enabled:0\r\naof_last_write_status:err\r\n",
+        ));
+        assert_eq!(check.status, CheckStatus::Failed);
+        assert_eq!(check.message.as_deref(), Some("last RDB save failed"));
+    }
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How samples sharing a series and timestamp are resolved, both within an import and against
+/// samples already stored. Mirrors RedisTimeSeries' `ON_DUPLICATE` policies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicatePolicy {
+    /// Keep the first value and report the rest as rejected duplicates.
+    #[default]
+    Block,
+    /// Keep the first value.
+    First,
+    /// Keep the last value.
+    Last,
+    Min,
+    Max,
+    /// Add the values together.
+    Sum,
+}
+
+impl DuplicatePolicy {
+    /// The `ON_DUPLICATE` argument for this policy.
+    pub fn as_redis_arg(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::Block => "BLOCK",
+            DuplicatePolicy::First => "FIRST",
+            DuplicatePolicy::Last => "LAST",
+            DuplicatePolicy::Min => "MIN",
+            DuplicatePolicy::Max => "MAX",
+            DuplicatePolicy::Sum => "SUM",
+        }
+    }
+
+    /// Whether a stored sample can be changed by an incoming duplicate.
+    pub fn overwrites(&self) -> bool {
+        !matches!(self, DuplicatePolicy::Block | DuplicatePolicy::First)
+    }
+
+    /// Resolves an incoming value against an earlier one for the same timestamp.
+    pub fn merge(&self, earlier: f64, incoming: f64) -> f64 {
+        match self {
+            DuplicatePolicy::Block | DuplicatePolicy::First => earlier,
+            DuplicatePolicy::Last => incoming,
+            DuplicatePolicy::Min => earlier.min(incoming),
+            DuplicatePolicy::Max => earlier.max(incoming),
+            DuplicatePolicy::Sum => earlier + incoming,
+        }
+    }
+}
+
+/// A value to write to a series at a stored timestamp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub key: String,
+    pub timestamp: i64,
+    pub value: f64,
+}
+
+/// Collapses points that share a series and timestamp according to `policy`, keeping the
+/// survivors in first-seen order. Returns them with the number of points collapsed away.
+pub fn collapse(points: Vec<Point>, policy: DuplicatePolicy) -> (Vec<Point>, usize) {
+    let mut kept: Vec<Point> = Vec::with_capacity(points.len());
+    let mut index: HashMap<(String, i64), usize> = HashMap::with_capacity(points.len());
+    let mut collapsed = 0;
+    for point in points {
+        match index.get(&(point.key.clone(), point.timestamp)) {
+            Some(&i) => {
+                kept[i].value = policy.merge(kept[i].value, point.value);
+                collapsed += 1;
+            }
+            None => {
+                index.insert((point.key.clone(), point.timestamp), kept.len());
+                kept.push(point);
+            }
+        }
+    }
+    (kept, collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(key: &str, timestamp: i64, value: f64) -> Point {
+        Point {
+            key: key.to_string(),
+            timestamp,
+            value,
+        }
+    }
+
+    #[test]
+    fn collapse_applies_policy_in_order() {
+        let points = || {
+            vec![
+                point("a", 2, 50.0),
+                point("a", 1, 40.0),
+                point("b", 2, 10.0),
+                point("a", 2, 60.0),
+                point("a", 2, 55.0),
+            ]
+        };
+
+        let (kept, collapsed) = collapse(points(), DuplicatePolicy::Block);
+        assert_eq!(collapsed, 2);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[0], point("a", 2, 50.0));
+
+        let value = |policy| collapse(points(), policy).0[0].value;
+        assert_eq!(value(DuplicatePolicy::First), 50.0);
+        assert_eq!(value(DuplicatePolicy::Last), 55.0);
+        assert_eq!(value(DuplicatePolicy::Min), 50.0);
+        assert_eq!(value(DuplicatePolicy::Max), 60.0);
+        assert_eq!(value(DuplicatePolicy::Sum), 165.0);
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::consts::redis::IMPORT_JOB_KEY_PREFIX;
+use crate::import::duplicates::DuplicatePolicy;
+use crate::import::parse::{ImportFormat, RowError};
+use crate::redis::RedisStore;
+
+/// How long a job's record is kept after its last update.
+const JOB_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+/// Rejected records beyond this many are counted but not described.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// Where an import job is in its lifecycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Running,
+    Completed,
+    /// The job stopped early, e.g. because Redis became unreachable; see `error`.
+    Failed,
+}
+
+/// A bulk import and its progress, updated after every batch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportJob {
+    pub id: Uuid,
+    pub status: ImportStatus,
+    pub format: ImportFormat,
+    pub on_duplicate: DuplicatePolicy,
+    pub calibrate: bool,
+    /// Size of the uploaded body.
+    pub bytes: usize,
+    pub records_read: u64,
+    pub samples_written: u64,
+    /// Samples whose series and timestamp were already taken, within the import or in storage.
+    pub duplicates: u64,
+    /// Records that could not be parsed or were refused by the domain catalog.
+    pub rejected: u64,
+    /// The first rejected records and why.
+    pub errors: Vec<RowError>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl ImportJob {
+    pub fn new(
+        format: ImportFormat,
+        on_duplicate: DuplicatePolicy,
+        calibrate: bool,
+        bytes: usize,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            status: ImportStatus::Running,
+            format,
+            on_duplicate,
+            calibrate,
+            bytes,
+            records_read: 0,
+            samples_written: 0,
+            duplicates: 0,
+            rejected: 0,
+            errors: Vec::new(),
+            error: None,
+            created_at: now,
+            updated_at: now,
+            finished_at: None,
+        }
+    }
+
+    /// Counts a rejected record, keeping its description while there is room.
+    pub fn reject(&mut self, error: RowError) {
+        self.rejected += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn finish(&mut self, result: anyhow::Result<()>, now: DateTime<Utc>) {
+        match result {
+            Ok(()) => self.status = ImportStatus::Completed,
+            Err(e) => {
+                self.status = ImportStatus::Failed;
+                self.error = Some(e.to_string());
+            }
+        }
+        self.updated_at = now;
+        self.finished_at = Some(now);
+    }
+}
+
+fn job_key(id: &Uuid) -> String {
+    format!("{IMPORT_JOB_KEY_PREFIX}{id}")
+}
+
+pub async fn save_job(redis: &RedisStore, job: &ImportJob) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json = serde_json::to_string(job)?;
+    conn.set_ex::<_, _, ()>(job_key(&job.id), json, JOB_TTL_SECS)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_job(redis: &RedisStore, id: &Uuid) -> anyhow::Result<Option<ImportJob>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json: Option<String> = conn.get(job_key(id)).await?;
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
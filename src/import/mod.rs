@@ -0,0 +1,10 @@
+pub mod duplicates;
+pub mod job;
+pub mod parse;
+pub mod runner;
+
+// Re-export commonly used items
+pub use duplicates::DuplicatePolicy;
+pub use job::{ImportJob, ImportStatus};
+pub use parse::ImportFormat;
+pub use runner::spawn_import;
@@ -0,0 +1,287 @@
+use chrono::{DateTime, TimeZone, Utc};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::domains::catalog;
+use crate::sensor::SensorData;
+
+/// Formats accepted by the import endpoint.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// CSV with a header row naming at least `device_id`, `domain`, `timestamp` and `value`.
+    #[default]
+    Csv,
+    /// One JSON object per line with the same fields as CSV.
+    Ndjson,
+    /// Length-delimited `SensorData` messages, as written by `encode_length_delimited`.
+    Protobuf,
+}
+
+/// One sample read from an import body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportRecord {
+    /// Line number for CSV and NDJSON, message number for protobuf.
+    pub position: usize,
+    pub device_id: String,
+    pub domain: String,
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// A record that could not be imported, and why.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RowError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl RowError {
+    pub fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+pub type ParsedRecord = Result<ImportRecord, RowError>;
+
+/// Timestamps in text formats: RFC 3339, or Unix milliseconds like the protobuf `timestamp`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TimestampField {
+    Millis(i64),
+    Text(String),
+}
+
+impl TimestampField {
+    fn parse(self) -> Result<DateTime<Utc>, String> {
+        match self {
+            TimestampField::Millis(ms) => from_millis(ms),
+            TimestampField::Text(text) => match text.parse::<i64>() {
+                Ok(ms) => from_millis(ms),
+                Err(_) => DateTime::parse_from_rfc3339(&text)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|e| format!("invalid timestamp {text:?}: {e}")),
+            },
+        }
+    }
+}
+
+fn from_millis(ms: i64) -> Result<DateTime<Utc>, String> {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .ok_or_else(|| format!("timestamp {ms} is out of range"))
+}
+
+#[derive(Deserialize)]
+struct TextRow {
+    device_id: String,
+    domain: String,
+    timestamp: TimestampField,
+    value: f64,
+}
+
+impl TextRow {
+    fn into_record(self, position: usize) -> ParsedRecord {
+        if self.device_id.is_empty() {
+            return Err(RowError::new(position, "device_id must not be empty"));
+        }
+        let timestamp = self
+            .timestamp
+            .parse()
+            .map_err(|msg| RowError::new(position, msg))?;
+        Ok(ImportRecord {
+            position,
+            device_id: self.device_id,
+            domain: self.domain,
+            timestamp,
+            value: self.value,
+        })
+    }
+}
+
+/// Reads the records of an import body lazily, so a bad record only fails itself.
+pub fn records(
+    format: ImportFormat,
+    body: &[u8],
+) -> Box<dyn Iterator<Item = ParsedRecord> + Send + '_> {
+    match format {
+        ImportFormat::Csv => Box::new(csv_records(body)),
+        ImportFormat::Ndjson => Box::new(ndjson_records(body)),
+        ImportFormat::Protobuf => Box::new(ProtobufRecords {
+            buf: body,
+            count: 0,
+            failed: false,
+        }),
+    }
+}
+
+fn csv_records(body: &[u8]) -> impl Iterator<Item = ParsedRecord> + Send + '_ {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    let headers = reader.headers().cloned();
+    let mut header_error = None;
+    let headers = match headers {
+        Ok(headers) => Some(headers),
+        Err(e) => {
+            header_error = Some(Err(RowError::new(1, format!("invalid CSV header: {e}"))));
+            None
+        }
+    };
+
+    let rows = reader.into_records().map_while(move |record| {
+        let headers = headers.as_ref()?;
+        Some(match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line() as usize);
+                record
+                    .deserialize::<TextRow>(Some(headers))
+                    .map_err(|e| RowError::new(line, e.to_string()))
+                    .and_then(|row| row.into_record(line))
+            }
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line() as usize);
+                Err(RowError::new(line, e.to_string()))
+            }
+        })
+    });
+    header_error.into_iter().chain(rows)
+}
+
+fn ndjson_records(body: &[u8]) -> impl Iterator<Item = ParsedRecord> + Send + '_ {
+    body.split(|b| *b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.trim_ascii().is_empty())
+        .map(|(index, line)| {
+            let position = index + 1;
+            serde_json::from_slice::<TextRow>(line)
+                .map_err(|e| RowError::new(position, e.to_string()))
+                .and_then(|row| row.into_record(position))
+        })
+}
+
+/// Length-delimited protobuf messages. A message that cannot be decoded ends the stream, since
+/// its length prefix can no longer be trusted.
+struct ProtobufRecords<'a> {
+    buf: &'a [u8],
+    count: usize,
+    failed: bool,
+}
+
+impl Iterator for ProtobufRecords<'_> {
+    type Item = ParsedRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.buf.is_empty() {
+            return None;
+        }
+        self.count += 1;
+        let position = self.count;
+        let message = match SensorData::decode_length_delimited(&mut self.buf) {
+            Ok(message) => message,
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(RowError::new(
+                    position,
+                    format!("invalid protobuf message, stopping: {e}"),
+                )));
+            }
+        };
+
+        let device_id = match String::from_utf8(message.device_id.clone()) {
+            Ok(id) if !id.is_empty() => id,
+            Ok(_) => return Some(Err(RowError::new(position, "device_id must not be empty"))),
+            Err(_) => return Some(Err(RowError::new(position, "device_id is not UTF-8"))),
+        };
+        if message.timestamp == 0 {
+            return Some(Err(RowError::new(position, "timestamp is missing")));
+        }
+        let timestamp = match i64::try_from(message.timestamp)
+            .map_err(|_| "timestamp is out of range".to_string())
+            .and_then(from_millis)
+        {
+            Ok(timestamp) => timestamp,
+            Err(msg) => return Some(Err(RowError::new(position, msg))),
+        };
+        Some(Ok(ImportRecord {
+            position,
+            device_id,
+            domain: catalog::sample_domain_name(&message),
+            timestamp,
+            value: f64::from(message.datum),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensor::Domain;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn csv_by_header_with_extra_columns() {
+        let body = b"timestamp,device_id,domain,value,unit\n\
+            2025-06-01T12:00:00Z,dev-1,SOUND_PRESSURE_LEVEL,55.5,dB\n\
+            1748779201000,dev-1,SOUND_PRESSURE_LEVEL,56,dB\n\
+            yesterday,dev-1,SOUND_PRESSURE_LEVEL,57,dB\n";
+        let parsed: Vec<_> = records(ImportFormat::Csv, body).collect();
+        assert_eq!(parsed.len(), 3);
+        let first = parsed[0].as_ref().unwrap();
+        assert_eq!(first.position, 2);
+        assert_eq!(first.timestamp, at(1_748_779_200));
+        assert_eq!(first.value, 55.5);
+        assert_eq!(parsed[1].as_ref().unwrap().timestamp, at(1_748_779_201));
+        assert_eq!(parsed[2].as_ref().unwrap_err().position, 4);
+    }
+
+    #[test]
+    fn csv_without_required_column() {
+        let body = b"device_id,value\ndev-1,55\n";
+        let parsed: Vec<_> = records(ImportFormat::Csv, body).collect();
+        assert_eq!(parsed.len(), 1);
+        assert!(parsed[0].is_err());
+    }
+
+    #[test]
+    fn ndjson_skips_blank_lines_and_reports_bad_ones() {
+        let body = b"{\"device_id\":\"dev-1\",\"domain\":\"TEMPERATURE\",\"timestamp\":\"2025-06-01T12:00:00Z\",\"value\":21.5}\n\
+            \n\
+            not json\n";
+        let parsed: Vec<_> = records(ImportFormat::Ndjson, body).collect();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].as_ref().unwrap().domain, "TEMPERATURE");
+        assert_eq!(parsed[1].as_ref().unwrap_err().position, 3);
+    }
+
+    #[test]
+    fn length_delimited_protobuf() {
+        let mut body = Vec::new();
+        for (timestamp, datum) in [(1_748_779_200_000, 55.0), (0, 56.0)] {
+            SensorData {
+                timestamp,
+                datum,
+                domain: Domain::SoundPressureLevel as i32,
+                device_id: b"dev-1".to_vec(),
+                domain_name: String::new(),
+            }
+            .encode_length_delimited(&mut body)
+            .unwrap();
+        }
+        body.extend_from_slice(&[0xff, 0xff]);
+
+        let parsed: Vec<_> = records(ImportFormat::Protobuf, &body).collect();
+        assert_eq!(parsed.len(), 3);
+        let first = parsed[0].as_ref().unwrap();
+        assert_eq!(first.domain, "SOUND_PRESSURE_LEVEL");
+        assert_eq!(first.timestamp, at(1_748_779_200));
+        assert!(parsed[1].is_err(), "a zero timestamp is rejected");
+        assert!(parsed[2].is_err(), "trailing garbage ends the stream");
+    }
+}
@@ -0,0 +1,344 @@
+use axum::body::Bytes;
+use chrono::Utc;
+use redis::{AsyncCommands, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::app_state::AppState;
+use crate::consts::errors::{ERR_IMPORT, ERR_IMPORT_JOB_WRITE, MSG_UNREGISTERED_DEVICE};
+use crate::consts::redis::{
+    REDIS_CMD_TS_ADD, REDIS_CMD_TS_CREATE, REDIS_CMD_TS_MADD, REDIS_CMD_TS_RANGE,
+    REDIS_ON_DUPLICATE_LABEL,
+};
+use crate::devices::{self, Device, calibration};
+use crate::domains::{DomainSpec, SampleRejection, store};
+use crate::import::duplicates::{self, DuplicatePolicy, Point};
+use crate::import::job::{self, ImportJob};
+use crate::import::parse::{self, ParsedRecord, RowError};
+use crate::metrics;
+use crate::series;
+
+/// Records parsed, validated and written together; progress is saved after each batch.
+const BATCH_SIZE: usize = 5_000;
+/// Samples per `TS.MADD` command.
+const MADD_CHUNK: usize = 1_000;
+/// `source` label for import's write batches.
+const BATCH_SOURCE: &str = "import";
+
+/// Spawns a background task that imports `body` as described by `job`, saving the job's progress
+/// after every batch and its outcome when done. Shutdown waits for it, and it stops at the next
+/// batch boundary once draining so the job is recorded as failed rather than left running.
+pub fn spawn_import(
+    state: Arc<AppState>,
+    mut job: ImportJob,
+    body: Bytes,
+) -> tokio::task::JoinHandle<()> {
+    let lifecycle = state.lifecycle.clone();
+    lifecycle.spawn(async move {
+        let result = run(&state, &mut job, &body).await;
+        if let Err(e) = &result {
+            error!(job_id = %job.id, error = %e, "{ERR_IMPORT}");
+        }
+        job.finish(result, Utc::now());
+        if let Err(e) = job::save_job(&state.redis, &job).await {
+            error!(job_id = %job.id, error = %e, "{ERR_IMPORT_JOB_WRITE}");
+        }
+        info!(
+            job_id = %job.id,
+            records = job.records_read,
+            written = job.samples_written,
+            duplicates = job.duplicates,
+            rejected = job.rejected,
+            "Import finished"
+        );
+    })
+}
+
+async fn run(state: &AppState, job: &mut ImportJob, body: &[u8]) -> anyhow::Result<()> {
+    let mut importer = Importer {
+        state,
+        policy: job.on_duplicate,
+        calibrate: job.calibrate,
+        domains: HashMap::new(),
+        devices: HashMap::new(),
+        series: HashSet::new(),
+    };
+    let mut records = parse::records(job.format, body);
+    loop {
+        let batch: Vec<ParsedRecord> = records.by_ref().take(BATCH_SIZE).collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        if state.lifecycle.is_draining() {
+            anyhow::bail!("interrupted by shutdown");
+        }
+        importer.import_batch(job, batch).await?;
+        job.updated_at = Utc::now();
+        job::save_job(&state.redis, job).await?;
+    }
+}
+
+/// How to create a series the import writes to, should it not exist yet.
+struct NewSeries {
+    spec: DomainSpec,
+    device_id: String,
+    variant: Option<&'static str>,
+}
+
+/// State kept across the batches of one import.
+struct Importer<'a> {
+    state: &'a AppState,
+    policy: DuplicatePolicy,
+    calibrate: bool,
+    domains: HashMap<String, Option<DomainSpec>>,
+    devices: HashMap<String, Option<Device>>,
+    /// Series known to exist.
+    series: HashSet<String>,
+}
+
+impl Importer<'_> {
+    async fn import_batch(
+        &mut self,
+        job: &mut ImportJob,
+        batch: Vec<ParsedRecord>,
+    ) -> anyhow::Result<()> {
+        let keys = &self.state.series_keys;
+        let now = Utc::now();
+        let mut points = Vec::with_capacity(batch.len());
+        let mut new_series: HashMap<String, NewSeries> = HashMap::new();
+
+        for parsed in batch {
+            job.records_read += 1;
+            let record = match parsed {
+                Ok(record) => record,
+                Err(e) => {
+                    job.reject(e);
+                    continue;
+                }
+            };
+            let reject = |message: String| RowError::new(record.position, message);
+
+            let Some(spec) = self.domain(&record.domain).await? else {
+                job.reject(reject(
+                    SampleRejection::UnknownDomain(record.domain.clone()).to_string(),
+                ));
+                continue;
+            };
+            self.load_device(&record.device_id).await?;
+            let device = self.devices[&record.device_id].as_ref();
+            if self.state.reject_unregistered_devices && device.is_none() {
+                job.reject(reject(MSG_UNREGISTERED_DEVICE.to_string()));
+                continue;
+            }
+            if let Some(retention_ms) = spec.retention_ms
+                && record.timestamp < now - chrono::Duration::milliseconds(retention_ms as i64)
+            {
+                job.reject(reject(format!(
+                    "timestamp is older than the {} retention period",
+                    spec.name
+                )));
+                continue;
+            }
+
+            let calibration = device
+                .filter(|_| self.calibrate)
+                .and_then(|d| calibration::effective(&d.calibrations, &spec.name, record.timestamp))
+                .cloned();
+            let datum = calibration
+                .as_ref()
+                .map_or(record.value, |c| c.apply(record.value));
+            if let Err(rejection) = spec.validate(datum) {
+                job.reject(reject(rejection.to_string()));
+                continue;
+            }
+
+            let timestamp = series::to_series_timestamp(record.timestamp);
+            let mut push = |key: String, value: f64, variant: Option<&'static str>| {
+                if !self.series.contains(&key) && !new_series.contains_key(&key) {
+                    new_series.insert(
+                        key.clone(),
+                        NewSeries {
+                            spec: spec.clone(),
+                            device_id: record.device_id.clone(),
+                            variant,
+                        },
+                    );
+                }
+                points.push(Point {
+                    key,
+                    timestamp,
+                    value,
+                });
+            };
+            push(keys.series(&record.device_id, &spec.name), datum, None);
+            if calibration.is_some_and(|c| c.keep_raw) {
+                push(
+                    keys.raw_series(&record.device_id, &spec.name),
+                    record.value,
+                    Some(series::VARIANT_RAW),
+                );
+            }
+        }
+
+        self.create_series(new_series).await?;
+
+        let (points, collapsed) = duplicates::collapse(points, self.policy);
+        job.duplicates += collapsed as u64;
+
+        let stored = self.stored_timestamps(&points).await?;
+        let (conflicts, fresh): (Vec<Point>, Vec<Point>) = points.into_iter().partition(|p| {
+            stored
+                .get(&p.key)
+                .is_some_and(|timestamps| timestamps.contains(&p.timestamp))
+        });
+        job.duplicates += conflicts.len() as u64;
+
+        let overwrites = if self.policy.overwrites() {
+            conflicts
+        } else {
+            Vec::new()
+        };
+        let written = fresh.len() + overwrites.len();
+        self.write_points(fresh, overwrites).await?;
+        job.samples_written += written as u64;
+        if written > 0 {
+            metrics::observe_batch(BATCH_SOURCE, written);
+        }
+        Ok(())
+    }
+
+    /// Adds `fresh` points with `TS.MADD` and overwrites `conflicts` with `TS.ADD`, pipelined per
+    /// cluster slot.
+    async fn write_points(&self, fresh: Vec<Point>, conflicts: Vec<Point>) -> anyhow::Result<()> {
+        let store = &self.state.redis;
+        let mut conn = store.get_connection_manager().await?;
+        for group in store.group_by_slot(fresh, |p| &p.key) {
+            let mut pipe = redis::pipe();
+            for chunk in group.chunks(MADD_CHUNK) {
+                let mut cmd = redis::cmd(REDIS_CMD_TS_MADD);
+                for point in chunk {
+                    cmd.arg(&point.key).arg(point.timestamp).arg(point.value);
+                }
+                pipe.add_command(cmd).ignore();
+            }
+            pipe.query_async::<_, ()>(&mut conn).await?;
+        }
+        for group in store.group_by_slot(conflicts, |p| &p.key) {
+            let mut pipe = redis::pipe();
+            for point in &group {
+                pipe.cmd(REDIS_CMD_TS_ADD)
+                    .arg(&point.key)
+                    .arg(point.timestamp)
+                    .arg(point.value)
+                    .arg(REDIS_ON_DUPLICATE_LABEL)
+                    .arg(self.policy.as_redis_arg())
+                    .ignore();
+            }
+            pipe.query_async::<_, ()>(&mut conn).await?;
+        }
+        Ok(())
+    }
+
+    async fn domain(&mut self, name: &str) -> anyhow::Result<Option<DomainSpec>> {
+        if let Some(spec) = self.domains.get(name) {
+            return Ok(spec.clone());
+        }
+        let spec = store::lookup(&self.state.redis, &self.state.domains, name).await?;
+        self.domains.insert(name.to_string(), spec.clone());
+        Ok(spec)
+    }
+
+    async fn load_device(&mut self, device_id: &str) -> anyhow::Result<()> {
+        if !self.devices.contains_key(device_id) {
+            let device = devices::get_device(&self.state.redis, device_id).await?;
+            self.devices.insert(device_id.to_string(), device);
+        }
+        Ok(())
+    }
+
+    /// Creates the series that do not exist yet with the same retention and labels ingest would
+    /// give them, since `TS.MADD` cannot.
+    async fn create_series(
+        &mut self,
+        new_series: HashMap<String, NewSeries>,
+    ) -> anyhow::Result<()> {
+        if new_series.is_empty() {
+            return Ok(());
+        }
+        let store = &self.state.redis;
+        let mut conn = store.get_connection_manager().await?;
+        let mut keys: Vec<&String> = Vec::with_capacity(new_series.len());
+        let mut exists: Vec<bool> = Vec::with_capacity(new_series.len());
+        for group in store.group_by_slot(new_series.keys(), |key| key.as_str()) {
+            let mut pipe = redis::pipe();
+            for key in &group {
+                pipe.exists(*key);
+            }
+            exists.extend(pipe.query_async::<_, Vec<bool>>(&mut conn).await?);
+            keys.extend(group);
+        }
+
+        for (key, exists) in keys.into_iter().zip(exists) {
+            if !exists {
+                let series = &new_series[key];
+                let mut cmd = redis::cmd(REDIS_CMD_TS_CREATE);
+                cmd.arg(key);
+                series::append_series_options(
+                    &mut cmd,
+                    &series.spec,
+                    &series.device_id,
+                    series.variant,
+                );
+                // Live ingest may have created it since the check, which is fine.
+                if let Err(e) = cmd.query_async::<_, ()>(&mut conn).await
+                    && !conn.exists::<_, bool>(key).await?
+                {
+                    return Err(e.into());
+                }
+            }
+            self.series.insert(key.clone());
+        }
+        Ok(())
+    }
+
+    /// Reads which of the points' timestamps each series already holds, by ranging over the span
+    /// the points cover.
+    async fn stored_timestamps(
+        &self,
+        points: &[Point],
+    ) -> anyhow::Result<HashMap<String, HashSet<i64>>> {
+        let mut spans: HashMap<&str, (i64, i64)> = HashMap::new();
+        for point in points {
+            spans
+                .entry(&point.key)
+                .and_modify(|(from, to)| {
+                    *from = (*from).min(point.timestamp);
+                    *to = (*to).max(point.timestamp);
+                })
+                .or_insert((point.timestamp, point.timestamp));
+        }
+        if spans.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let store = &self.state.redis;
+        let mut conn = store.get_connection_manager().await?;
+        let mut stored = HashMap::with_capacity(spans.len());
+        for group in store.group_by_slot(spans, |(key, _)| key) {
+            let mut pipe = redis::pipe();
+            for (key, (from, to)) in &group {
+                pipe.cmd(REDIS_CMD_TS_RANGE).arg(*key).arg(*from).arg(*to);
+            }
+            let replies: Vec<Value> = pipe.query_async(&mut conn).await?;
+
+            for ((key, _), reply) in group.into_iter().zip(replies) {
+                let timestamps = series::parse_samples(&reply)?
+                    .into_iter()
+                    .map(|s| series::to_series_timestamp(s.timestamp))
+                    .collect();
+                stored.insert(key.to_string(), timestamps);
+            }
+        }
+        Ok(stored)
+    }
+}
@@ -0,0 +1,8 @@
+pub mod queue;
+pub mod sample;
+
+// Re-export commonly used items
+pub use queue::{WriteQueue, WriteQueueSettings};
+pub use sample::{
+    IngestError, Sample, Source, UNKNOWN_DOMAIN_LABEL, record_stored, spool_sample, store_sample,
+};
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{Mutex as AsyncMutex, mpsc, oneshot};
+use tokio::time::Instant;
+
+use crate::consts::redis::{
+    REDIS_CMD_TS_ADD, REDIS_CMD_TS_MADD, REDIS_CMD_TS_RANGE, REDIS_ON_DUPLICATE_LABEL,
+};
+use crate::domains::DomainSpec;
+use crate::metrics;
+use crate::redis::{InstrumentedConnection, RedisStore, is_unreachable, is_unreachable_error};
+use crate::series;
+
+/// Samples per `TS.MADD` command.
+const MADD_CHUNK: usize = 1_000;
+/// `source` label for the queue's write batches.
+const BATCH_SOURCE: &str = "ingest";
+
+/// How deep the ingest write queue is and how its writers batch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteQueueSettings {
+    /// Requests that may wait for a writer before further ones are refused.
+    pub depth: usize,
+    pub writers: usize,
+    /// A batch is written once it holds this many samples...
+    pub batch_size: usize,
+    /// ...or this long after its first request arrived, whichever comes first.
+    pub flush_interval: Duration,
+}
+
+/// One value to append to a series, with what it takes to create the series if it is missing.
+#[derive(Clone, Debug)]
+pub struct SeriesWrite {
+    pub key: String,
+    pub timestamp: i64,
+    pub value: f64,
+    pub spec: DomainSpec,
+    pub device_id: String,
+    /// Labels secondary copies such as the uncalibrated `raw` series.
+    pub variant: Option<&'static str>,
+}
+
+impl SeriesWrite {
+    /// The `TS.ADD` for this value, with the labels and retention applied if it creates the
+    /// series. `on_duplicate` overrides the series' duplicate policy for this write.
+    pub fn ts_add(&self, on_duplicate: Option<&str>) -> redis::Cmd {
+        let mut cmd = redis::cmd(REDIS_CMD_TS_ADD);
+        cmd.arg(&self.key).arg(self.timestamp).arg(self.value);
+        if let Some(policy) = on_duplicate {
+            cmd.arg(REDIS_ON_DUPLICATE_LABEL).arg(policy);
+        }
+        series::append_series_options(&mut cmd, &self.spec, &self.device_id, self.variant);
+        cmd
+    }
+}
+
+#[derive(Debug)]
+pub enum QueueError {
+    /// Every slot in the queue is taken; the caller should back off.
+    Full,
+    /// The writers have stopped.
+    Closed,
+    /// The batch holding the request failed, because Redis could not be reached or because it
+    /// refused the request's writes.
+    Redis {
+        error: anyhow::Error,
+        unreachable: bool,
+    },
+}
+
+/// Why a request was not written, as told to each request of a failed batch.
+#[derive(Clone, Debug)]
+struct WriteFailure {
+    message: String,
+    unreachable: bool,
+}
+
+impl WriteFailure {
+    fn new(error: impl std::fmt::Display, unreachable: bool) -> Self {
+        Self {
+            message: format!("{error:#}"),
+            unreachable,
+        }
+    }
+}
+
+/// A request waiting for a writer, answered once its batch is written.
+struct Pending {
+    writes: Vec<SeriesWrite>,
+    done: oneshot::Sender<Result<(), WriteFailure>>,
+}
+
+/// A bounded queue between the ingest handlers and a pool of writer tasks, which coalesce the
+/// samples of many requests into pipelined `TS.MADD`s.
+///
+/// Each request still waits for its own batch to be written, so a `204` means the sample is
+/// stored; the queue only bounds how many requests may wait at once. When it is full, requests are
+/// refused at once rather than queued behind a Redis that cannot keep up.
+#[derive(Clone)]
+pub struct WriteQueue {
+    sender: mpsc::Sender<Pending>,
+}
+
+struct Writer {
+    redis: Arc<RedisStore>,
+    receiver: AsyncMutex<mpsc::Receiver<Pending>>,
+    /// Series known to exist, which `TS.MADD` can append to; the others are written with a
+    /// `TS.ADD` that creates them.
+    known_series: Mutex<HashSet<String>>,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl WriteQueue {
+    /// Creates the queue and spawns its writers, which run until every clone of it is dropped.
+    pub fn start(redis: Arc<RedisStore>, settings: &WriteQueueSettings) -> Self {
+        let (sender, receiver) = mpsc::channel(settings.depth);
+        let writer = Arc::new(Writer {
+            redis,
+            receiver: AsyncMutex::new(receiver),
+            known_series: Mutex::new(HashSet::new()),
+            batch_size: settings.batch_size,
+            flush_interval: settings.flush_interval,
+        });
+        for _ in 0..settings.writers {
+            tokio::spawn(writer.clone().run());
+        }
+        Self { sender }
+    }
+
+    /// Queues `writes` and waits until the batch holding them is written.
+    pub async fn write(&self, writes: Vec<SeriesWrite>) -> Result<(), QueueError> {
+        let (done, result) = oneshot::channel();
+        self.sender
+            .try_send(Pending { writes, done })
+            .map_err(|e| match e {
+                TrySendError::Full(_) => QueueError::Full,
+                TrySendError::Closed(_) => QueueError::Closed,
+            })?;
+        metrics::set_ingest_queue_depth(self.depth());
+        match result.await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(failure)) => Err(QueueError::Redis {
+                error: anyhow::anyhow!(failure.message),
+                unreachable: failure.unreachable,
+            }),
+            Err(_) => Err(QueueError::Closed),
+        }
+    }
+
+    /// Requests waiting for a writer.
+    pub fn depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.sender.max_capacity()
+    }
+}
+
+impl Writer {
+    async fn run(self: Arc<Self>) {
+        while let Some(batch) = self.next_batch().await {
+            self.flush(batch).await;
+        }
+    }
+
+    /// Waits for a request, then collects more until the batch is full or the flush interval has
+    /// passed. Only one writer collects at a time; the others are busy writing.
+    async fn next_batch(&self) -> Option<Vec<Pending>> {
+        let mut receiver = self.receiver.lock().await;
+        let first = receiver.recv().await?;
+        let deadline = Instant::now() + self.flush_interval;
+        let mut samples = first.writes.len();
+        let mut batch = vec![first];
+        while samples < self.batch_size {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(pending)) => {
+                    samples += pending.writes.len();
+                    batch.push(pending);
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+        metrics::set_ingest_queue_depth(receiver.len());
+        Some(batch)
+    }
+
+    /// Writes a batch and answers its requests. A batch that Redis refuses, say for a write older
+    /// than its series' retention, is retried one request at a time so the others are still
+    /// stored. A batch that cannot reach Redis fails as a whole.
+    async fn flush(&self, batch: Vec<Pending>) {
+        let samples: usize = batch.iter().map(|p| p.writes.len()).sum();
+        let mut conn = match self.redis.get_connection_manager().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                let failure = WriteFailure::new(&e, is_unreachable_error(&e));
+                for pending in batch {
+                    let _ = pending.done.send(Err(failure.clone()));
+                }
+                return;
+            }
+        };
+
+        // With hash-tagged keys all of a request's writes share its device's slot.
+        for group in self
+            .redis
+            .group_by_slot(batch, |pending| pending.writes[0].key.as_str())
+        {
+            match self.write_group(&mut conn, &group).await {
+                Ok(()) => {
+                    for pending in group {
+                        let _ = pending.done.send(Ok(()));
+                    }
+                }
+                Err(e) if is_unreachable(&e) => {
+                    let failure = WriteFailure::new(&e, true);
+                    for pending in group {
+                        let _ = pending.done.send(Err(failure.clone()));
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!(error = %e, requests = group.len(), "Write batch refused, retrying requests one at a time");
+                    for pending in group {
+                        let result = write_one(&mut conn, &pending.writes).await;
+                        let result = result.map_err(|e| WriteFailure::new(&e, is_unreachable(&e)));
+                        let _ = pending.done.send(result);
+                    }
+                }
+            }
+        }
+        metrics::observe_batch(BATCH_SOURCE, samples);
+    }
+
+    /// Writes a group in one pipeline: `TS.MADD` to series known to exist, `TS.ADD` to the rest.
+    async fn write_group(
+        &self,
+        conn: &mut InstrumentedConnection,
+        group: &[Pending],
+    ) -> redis::RedisResult<()> {
+        let mut pipe = redis::pipe();
+        let mut appends = Vec::new();
+        {
+            let known = self
+                .known_series
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            for write in group.iter().flat_map(|pending| &pending.writes) {
+                if known.contains(&write.key) {
+                    appends.push(write);
+                } else {
+                    pipe.add_command(write.ts_add(None)).ignore();
+                }
+            }
+        }
+        for chunk in appends.chunks(MADD_CHUNK) {
+            let mut cmd = redis::cmd(REDIS_CMD_TS_MADD);
+            for write in chunk {
+                cmd.arg(&write.key).arg(write.timestamp).arg(write.value);
+            }
+            pipe.add_command(cmd).ignore();
+        }
+        pipe.query_async::<_, ()>(conn).await?;
+
+        let mut known = self
+            .known_series
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for write in group.iter().flat_map(|pending| &pending.writes) {
+            if !known.contains(&write.key) {
+                known.insert(write.key.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes one request's values on their own. The refused batch may already have stored some of
+/// them, so a refused value still counts as written when its series holds exactly that value at
+/// its timestamp; another request's value there is a duplicate and fails this one.
+async fn write_one(
+    conn: &mut InstrumentedConnection,
+    writes: &[SeriesWrite],
+) -> redis::RedisResult<()> {
+    for write in writes {
+        if let Err(e) = write.ts_add(None).query_async::<_, ()>(conn).await
+            && (is_unreachable(&e) || !already_stored(conn, write).await)
+        {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `write`'s series already holds its value at its timestamp.
+async fn already_stored(conn: &mut InstrumentedConnection, write: &SeriesWrite) -> bool {
+    let reply: redis::RedisResult<redis::Value> = redis::cmd(REDIS_CMD_TS_RANGE)
+        .arg(&write.key)
+        .arg(write.timestamp)
+        .arg(write.timestamp)
+        .query_async(conn)
+        .await;
+    reply
+        .ok()
+        .and_then(|reply| series::parse_samples(&reply).ok())
+        .is_some_and(|samples| samples.iter().any(|sample| sample.value == write.value))
+}
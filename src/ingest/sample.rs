@@ -0,0 +1,259 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::app_state::AppState;
+use crate::consts::errors::{
+    ERR_DEVICE_LOOKUP, ERR_DEVICE_STATUS_UPDATE, ERR_DOMAIN_LOOKUP, ERR_LIVE_PUBLISH,
+    ERR_REDIS_CONN, ERR_REDIS_WRITE, ERR_SAMPLE_STREAM, ERR_SPOOL_WRITE,
+};
+use crate::consts::redis::REDIS_ON_DUPLICATE_LAST;
+use crate::devices::{self, calibration};
+use crate::domains::{SampleRejection, store};
+use crate::events::{SampleEvent, stream};
+use crate::ingest::queue::{QueueError, SeriesWrite};
+use crate::metrics;
+use crate::redis::is_unreachable_error;
+use crate::series;
+
+/// `domain` label for rejections made before the domain is known, or for domains the catalog does
+/// not know, so clients cannot grow the label set.
+pub const UNKNOWN_DOMAIN_LABEL: &str = "unknown";
+
+/// A sample as a device reported it, before calibration and validation. This is what the spool
+/// keeps while Redis is unreachable, so a replayed sample goes through the same checks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub device_id: String,
+    pub domain: String,
+    pub value: f64,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Where a sample being stored came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Sent by a device just now.
+    Ingest,
+    /// Read back from the spool. A segment may be replayed twice if Redis fails part way, so
+    /// writes overwrite rather than collide with a copy stored by the first attempt.
+    Spool,
+}
+
+impl Source {
+    /// `source` label for the batch size metric.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Ingest => "ingest",
+            Source::Spool => "spool",
+        }
+    }
+}
+
+/// Why a sample was not stored.
+#[derive(Debug)]
+pub enum IngestError {
+    /// The device is not registered and unregistered devices are refused.
+    UnregisteredDevice,
+    /// The domain catalog refused the sample.
+    Rejected(SampleRejection),
+    /// The write queue is full; the device should retry later.
+    Busy,
+    /// The write queue's writers have stopped, so nothing more can be stored through it.
+    Closed,
+    /// Redis failed at the step `context` names; the sample may be retried. Only a sample Redis
+    /// could not be reached for is worth spooling, as one it refused would be refused on replay.
+    Redis {
+        context: &'static str,
+        error: anyhow::Error,
+        unreachable: bool,
+    },
+}
+
+impl IngestError {
+    fn redis(context: &'static str, error: impl Into<anyhow::Error>) -> Self {
+        let error = error.into();
+        Self::Redis {
+            context,
+            unreachable: is_unreachable_error(&error),
+            error,
+        }
+    }
+}
+
+/// Calibrates, validates and writes one sample, returning the event to publish. Samples from
+/// devices go through the write queue and are batched with those of other requests.
+///
+/// Refusals are logged and counted here, so samples refused on replay show up alongside those
+/// refused at ingest. Device status and publishing are left to the caller.
+pub async fn store_sample(
+    state: &AppState,
+    sample: &Sample,
+    source: Source,
+) -> Result<SampleEvent, IngestError> {
+    let device_id = &sample.device_id;
+    let device = devices::get_device(&state.redis, device_id)
+        .instrument(tracing::info_span!("device_lookup"))
+        .await
+        .map_err(|e| IngestError::redis(ERR_DEVICE_LOOKUP, e))?;
+    if state.reject_unregistered_devices && device.is_none() {
+        tracing::warn!(device_id = %device_id, "Rejected sample from unregistered device");
+        metrics::sample_rejected(UNKNOWN_DOMAIN_LABEL, "unregistered_device");
+        return Err(IngestError::UnregisteredDevice);
+    }
+
+    let spec = match store::lookup(&state.redis, &state.domains, &sample.domain)
+        .instrument(tracing::info_span!("domain_lookup"))
+        .await
+    {
+        Ok(Some(spec)) => spec,
+        Ok(None) => {
+            return Err(reject(
+                device_id,
+                SampleRejection::UnknownDomain(sample.domain.clone()),
+            ));
+        }
+        Err(e) => return Err(IngestError::redis(ERR_DOMAIN_LOOKUP, e)),
+    };
+
+    let raw = sample.value;
+    let (calibration, validated) =
+        tracing::info_span!("validate", domain = %spec.name).in_scope(|| {
+            let calibration = device.as_ref().and_then(|d| {
+                calibration::effective(&d.calibrations, &spec.name, sample.received_at)
+            });
+            let datum = calibration.map_or(raw, |c| c.apply(raw));
+            (calibration, spec.validate(datum).map(|()| datum))
+        });
+    let datum = validated.map_err(|rejection| reject(device_id, rejection))?;
+
+    // TODO(steve): PUT THIS BACK
+    // let timestamp = sensor_data.timestamp;
+    let timestamp = series::to_series_timestamp(sample.received_at);
+
+    let write = |key: String, value: f64, variant: Option<&'static str>| SeriesWrite {
+        key,
+        timestamp,
+        value,
+        spec: spec.clone(),
+        device_id: device_id.clone(),
+        variant,
+    };
+    let mut writes = vec![write(
+        state.series_keys.series(device_id, &spec.name),
+        datum,
+        None,
+    )];
+    if calibration.is_some_and(|c| c.keep_raw) {
+        writes.push(write(
+            state.series_keys.raw_series(device_id, &spec.name),
+            raw,
+            Some(series::VARIANT_RAW),
+        ));
+    }
+
+    match source {
+        Source::Ingest => state.write_queue.write(writes).await.map_err(|e| match e {
+            QueueError::Full => {
+                tracing::warn!(device_id = %device_id, "Ingest write queue is full");
+                metrics::sample_rejected(&spec.name, "queue_full");
+                IngestError::Busy
+            }
+            QueueError::Closed => IngestError::Closed,
+            QueueError::Redis { error, unreachable } => IngestError::Redis {
+                context: ERR_REDIS_WRITE,
+                error,
+                unreachable,
+            },
+        })?,
+        Source::Spool => write_replayed(state, &writes).await?,
+    }
+    metrics::sample_accepted(&spec.name);
+
+    Ok(SampleEvent {
+        device_id: device_id.clone(),
+        domain: spec.name.clone(),
+        value: datum,
+        raw_value: calibration.map(|_| raw),
+        unit: spec.unit.clone(),
+        timestamp: series::from_series_timestamp(timestamp).unwrap_or(sample.received_at),
+    })
+}
+
+/// Counts a sample a device just sent towards its status, then publishes it to the sample stream
+/// and live subscribers. The sample is already stored, so failures are logged rather than
+/// returned.
+pub async fn record_stored(state: &AppState, event: SampleEvent) {
+    let device_id = event.device_id.clone();
+    if let Err(e) = devices::status::record_samples(&state.redis, &device_id, 1, Utc::now()).await {
+        tracing::warn!(device_id = %device_id, error = %e, "{ERR_DEVICE_STATUS_UPDATE}");
+    }
+
+    if let Some(sample_stream) = &state.sample_stream
+        && let Err(e) = stream::publish(&state.redis, sample_stream, &event).await
+    {
+        tracing::warn!(device_id = %device_id, error = %e, "{ERR_SAMPLE_STREAM}");
+    }
+    if let Err(e) = state.live.publish(&state.redis, event).await {
+        tracing::warn!(device_id = %device_id, error = %e, "{ERR_LIVE_PUBLISH}");
+    }
+}
+
+/// Keeps a sample Redis could not store in the spool, for the replayer to store once Redis
+/// recovers. Gives the Redis failure back when spooling is disabled or the spool is full.
+pub async fn spool_sample(
+    state: &AppState,
+    sample: Sample,
+    context: &'static str,
+    error: anyhow::Error,
+) -> Result<(), anyhow::Error> {
+    let Some(spool) = &state.spool else {
+        return Err(error);
+    };
+    let device_id = sample.device_id.clone();
+    match spool.push(sample).await {
+        Ok(()) => {
+            tracing::warn!(device_id = %device_id, error = %error, "{context}; spooled for replay");
+            metrics::spool_samples("spooled", 1);
+            Ok(())
+        }
+        Err(spool_error) => {
+            metrics::spool_samples("refused", 1);
+            tracing::error!(device_id = %device_id, error = %spool_error, "{ERR_SPOOL_WRITE}");
+            Err(error)
+        }
+    }
+}
+
+/// Writes a replayed sample directly rather than through the queue, so replay neither waits for
+/// nor crowds out live requests. Overwrites a copy stored by an earlier, interrupted replay.
+async fn write_replayed(state: &AppState, writes: &[SeriesWrite]) -> Result<(), IngestError> {
+    let mut conn = state
+        .redis
+        .get_connection_manager()
+        .await
+        .map_err(|e| IngestError::redis(ERR_REDIS_CONN, e))?;
+    let mut pipe = redis::pipe();
+    for write in writes {
+        pipe.add_command(write.ts_add(Some(REDIS_ON_DUPLICATE_LAST)))
+            .ignore();
+    }
+    pipe.query_async::<_, ()>(&mut conn)
+        .await
+        .map_err(|e| IngestError::redis(ERR_REDIS_WRITE, e))?;
+    metrics::observe_batch(Source::Spool.as_str(), writes.len());
+    Ok(())
+}
+
+/// Logs and counts a sample the domain catalog refused.
+fn reject(device_id: &str, rejection: SampleRejection) -> IngestError {
+    tracing::warn!(device_id = %device_id, reason = %rejection, "Rejected sample");
+    let domain = match &rejection {
+        SampleRejection::UnknownDomain(_) => UNKNOWN_DOMAIN_LABEL,
+        SampleRejection::NotFinite { domain } | SampleRejection::OutOfRange { domain, .. } => {
+            domain
+        }
+    };
+    metrics::sample_rejected(domain, rejection.reason());
+    IngestError::Rejected(rejection)
+}
@@ -0,0 +1,369 @@
+use chrono::{DateTime, Datelike, DurationRound, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::acoustics::levels::{self, Levels, leq};
+use crate::series::Sample;
+
+/// Significance level used for the `significant` flag.
+const ALPHA: f64 = 0.05;
+
+/// Levels over one side of the intervention.
+#[derive(Debug, Serialize)]
+pub struct Side {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub levels: Option<Levels>,
+}
+
+/// After-minus-before differences of the summary levels, in dB. Negative means quieter after.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Difference {
+    pub leq: f64,
+    pub l10: f64,
+    pub l50: f64,
+    pub l90: f64,
+}
+
+/// Before and after Leq for one bucket of a profile, such as an hour of the day.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ProfileEntry<K> {
+    pub bucket: K,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+    pub difference: Option<f64>,
+}
+
+/// Welch's t-test on the hourly Leq values of each side.
+///
+/// Consecutive hours are correlated, so treat the p-value as a rough guide rather than proof.
+#[derive(Debug, Serialize)]
+pub struct Significance {
+    pub test: &'static str,
+    pub hours_before: usize,
+    pub hours_after: usize,
+    pub mean_difference_db: f64,
+    pub t: f64,
+    pub degrees_of_freedom: f64,
+    pub p_value: f64,
+    pub cohens_d: f64,
+    pub significant: bool,
+}
+
+/// The full before/after comparison report.
+#[derive(Debug, Serialize)]
+pub struct Comparison {
+    pub window_secs: i64,
+    pub before: Side,
+    pub after: Side,
+    pub difference: Option<Difference>,
+    /// Leq per local hour of the day, 0-23.
+    pub hourly_profile: Vec<ProfileEntry<u32>>,
+    /// Leq per local day of the week, Monday first, so weekdays are compared like for like.
+    pub day_of_week: Vec<ProfileEntry<Weekday>>,
+    pub significance: Option<Significance>,
+}
+
+/// Compares the samples in `[at - window, at)` with those in `[at, at + window)`.
+///
+/// `samples` must be sorted by time and may extend beyond both windows.
+pub fn compare(
+    samples: &[Sample],
+    at: DateTime<Utc>,
+    window: chrono::Duration,
+    timezone: Tz,
+) -> Comparison {
+    let start = at - window;
+    let end = at + window;
+    let before = slice(samples, start, at);
+    let after = slice(samples, at, end);
+
+    let before_values: Vec<f64> = before.iter().map(|s| s.value).collect();
+    let after_values: Vec<f64> = after.iter().map(|s| s.value).collect();
+    let before_levels = levels::levels(&before_values);
+    let after_levels = levels::levels(&after_values);
+
+    let difference = match (&before_levels, &after_levels) {
+        (Some(b), Some(a)) => Some(Difference {
+            leq: a.leq - b.leq,
+            l10: a.l10 - b.l10,
+            l50: a.l50 - b.l50,
+            l90: a.l90 - b.l90,
+        }),
+        _ => None,
+    };
+
+    let hourly_profile = profile(before, after, 0..24, |s| {
+        s.timestamp.with_timezone(&timezone).hour()
+    });
+    let day_of_week = profile(before, after, 0..7, |s| {
+        s.timestamp
+            .with_timezone(&timezone)
+            .weekday()
+            .num_days_from_monday()
+    })
+    .into_iter()
+    .map(|entry| ProfileEntry {
+        bucket: Weekday::try_from(entry.bucket as u8).unwrap_or(Weekday::Mon),
+        before: entry.before,
+        after: entry.after,
+        difference: entry.difference,
+    })
+    .collect();
+
+    Comparison {
+        window_secs: window.num_seconds(),
+        before: Side {
+            start,
+            end: at,
+            levels: before_levels,
+        },
+        after: Side {
+            start: at,
+            end,
+            levels: after_levels,
+        },
+        difference,
+        hourly_profile,
+        day_of_week,
+        significance: welch(&hourly_leqs(before), &hourly_leqs(after)),
+    }
+}
+
+fn slice(samples: &[Sample], start: DateTime<Utc>, end: DateTime<Utc>) -> &[Sample] {
+    let from = samples.partition_point(|s| s.timestamp < start);
+    let to = samples.partition_point(|s| s.timestamp < end).max(from);
+    &samples[from..to]
+}
+
+/// Leq per bucket on each side, for every bucket in `buckets`.
+fn profile<K, I, F>(before: &[Sample], after: &[Sample], buckets: I, key: F) -> Vec<ProfileEntry<K>>
+where
+    K: Ord + Copy,
+    I: IntoIterator<Item = K>,
+    F: Fn(&Sample) -> K,
+{
+    let group = |samples: &[Sample]| {
+        let mut groups: BTreeMap<K, Vec<f64>> = BTreeMap::new();
+        for sample in samples {
+            groups.entry(key(sample)).or_default().push(sample.value);
+        }
+        groups
+    };
+    let before = group(before);
+    let after = group(after);
+
+    buckets
+        .into_iter()
+        .map(|bucket| {
+            let b = before.get(&bucket).and_then(|v| leq(v));
+            let a = after.get(&bucket).and_then(|v| leq(v));
+            ProfileEntry {
+                bucket,
+                before: b,
+                after: a,
+                difference: b.zip(a).map(|(b, a)| a - b),
+            }
+        })
+        .collect()
+}
+
+/// Leq of every clock hour that has samples.
+fn hourly_leqs(samples: &[Sample]) -> Vec<f64> {
+    let mut hours: BTreeMap<DateTime<Utc>, Vec<f64>> = BTreeMap::new();
+    for sample in samples {
+        let hour = sample
+            .timestamp
+            .duration_trunc(chrono::Duration::hours(1))
+            .unwrap_or(sample.timestamp);
+        hours.entry(hour).or_default().push(sample.value);
+    }
+    hours.values().filter_map(|v| leq(v)).collect()
+}
+
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance)
+}
+
+/// Welch's unequal-variance t-test of `after` against `before`.
+///
+/// Returns `None` without at least two values on each side or with no variance at all.
+fn welch(before: &[f64], after: &[f64]) -> Option<Significance> {
+    if before.len() < 2 || after.len() < 2 {
+        return None;
+    }
+    let (m1, v1) = mean_and_variance(before);
+    let (m2, v2) = mean_and_variance(after);
+    let (n1, n2) = (before.len() as f64, after.len() as f64);
+    let se2 = v1 / n1 + v2 / n2;
+    if se2 <= 0.0 {
+        return None;
+    }
+
+    let t = (m2 - m1) / se2.sqrt();
+    let df = se2.powi(2) / ((v1 / n1).powi(2) / (n1 - 1.0) + (v2 / n2).powi(2) / (n2 - 1.0));
+    let p_value = student_t_two_sided_p(t, df);
+    let pooled_sd = ((v1 + v2) / 2.0).sqrt();
+
+    Some(Significance {
+        test: "welch_t",
+        hours_before: before.len(),
+        hours_after: after.len(),
+        mean_difference_db: m2 - m1,
+        t,
+        degrees_of_freedom: df,
+        p_value,
+        cohens_d: (m2 - m1) / pooled_sd,
+        significant: p_value < ALPHA,
+    })
+}
+
+/// Two-sided p-value of Student's t distribution, via the regularized incomplete beta function.
+fn student_t_two_sided_p(t: f64, df: f64) -> f64 {
+    incomplete_beta(df / 2.0, 0.5, df / (df + t * t)).clamp(0.0, 1.0)
+}
+
+/// Regularized incomplete beta function I_x(a, b).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    let front = ln_front.exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// Lentz's continued fraction for the incomplete beta function.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..200 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1.0 + aa / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1.0 / d;
+        h *= d * c;
+
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1.0 + aa / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+/// Natural log of the gamma function (Lanczos approximation).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000_000_000_190_015;
+    for c in COEFFICIENTS {
+        y += 1.0;
+        series += c / y;
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    #[test]
+    fn student_t_p_values_match_tables() {
+        // Two-sided critical values: t(10) = 2.228 at 0.05, t(30) = 2.750 at 0.01.
+        assert!(close(student_t_two_sided_p(2.228, 10.0), 0.05, 1e-3));
+        assert!(close(student_t_two_sided_p(2.750, 30.0), 0.01, 1e-3));
+        assert!(close(student_t_two_sided_p(0.0, 5.0), 1.0, 1e-9));
+    }
+
+    #[test]
+    fn compare_detects_a_quieter_after_period() {
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let window = chrono::Duration::days(2);
+        let mut samples = Vec::new();
+        let mut t = at - window;
+        let mut i = 0;
+        while t < at + window {
+            let base = if t < at { 60.0 } else { 55.0 };
+            samples.push(Sample {
+                timestamp: t,
+                value: base + (i % 3) as f64,
+            });
+            t += chrono::Duration::minutes(15);
+            i += 1;
+        }
+
+        let report = compare(&samples, at, window, Tz::UTC);
+        let difference = report.difference.unwrap();
+        assert!(close(difference.l50, -5.0, 1e-9));
+        assert!(difference.leq < -4.0);
+        assert_eq!(report.hourly_profile.len(), 24);
+        assert_eq!(report.day_of_week.len(), 7);
+        assert!(report.hourly_profile[0].difference.unwrap() < 0.0);
+        // 1 June 2025 is a Sunday: only Fri/Sat before, Sun/Mon after.
+        assert!(report.day_of_week[6].before.is_none());
+        assert!(report.day_of_week[6].after.is_some());
+
+        let significance = report.significance.unwrap();
+        assert_eq!(significance.hours_before, 48);
+        assert!(significance.significant);
+        assert!(significance.mean_difference_db < 0.0);
+    }
+
+    #[test]
+    fn compare_without_after_data() {
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let samples = vec![Sample {
+            timestamp: at - chrono::Duration::hours(1),
+            value: 50.0,
+        }];
+        let report = compare(&samples, at, chrono::Duration::days(1), Tz::UTC);
+        assert!(report.before.levels.is_some());
+        assert!(report.after.levels.is_none());
+        assert!(report.difference.is_none());
+        assert!(report.significance.is_none());
+    }
+}
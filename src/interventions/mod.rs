@@ -0,0 +1,6 @@
+pub mod comparison;
+pub mod registry;
+
+// Re-export commonly used items
+pub use comparison::Comparison;
+pub use registry::Intervention;
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::consts::redis::{ALL_INTERVENTIONS, INTERVENTION_KEY_PREFIX};
+use crate::redis::RedisStore;
+
+/// A change made near a device whose effect on noise levels should be evaluated, such as
+/// planting a hedge.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Intervention {
+    pub id: Uuid,
+    pub device_id: String,
+    /// When the change took effect; the comparison windows meet here.
+    pub at: DateTime<Utc>,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Payload for recording a new intervention.
+#[derive(Debug, Deserialize)]
+pub struct CreateIntervention {
+    pub device_id: String,
+    pub at: DateTime<Utc>,
+    pub description: String,
+}
+
+impl Intervention {
+    pub fn from_create(req: CreateIntervention, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            device_id: req.device_id,
+            at: req.at,
+            description: req.description,
+            created_at: now,
+        }
+    }
+}
+
+fn intervention_key(id: &Uuid) -> String {
+    format!("{INTERVENTION_KEY_PREFIX}{id}")
+}
+
+pub async fn save_intervention(
+    redis: &RedisStore,
+    intervention: &Intervention,
+) -> anyhow::Result<()> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json = serde_json::to_string(intervention)?;
+    conn.set::<_, _, ()>(intervention_key(&intervention.id), json)
+        .await?;
+    conn.sadd::<_, _, ()>(ALL_INTERVENTIONS, intervention.id.to_string())
+        .await?;
+    Ok(())
+}
+
+pub async fn get_intervention(
+    redis: &RedisStore,
+    id: &Uuid,
+) -> anyhow::Result<Option<Intervention>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let json: Option<String> = conn.get(intervention_key(id)).await?;
+    match json {
+        Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        None => Ok(None),
+    }
+}
+
+/// Returns every intervention, ordered by when it took effect.
+pub async fn list_interventions(redis: &RedisStore) -> anyhow::Result<Vec<Intervention>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let ids: Vec<String> = conn.smembers(ALL_INTERVENTIONS).await?;
+
+    let mut interventions: Vec<Intervention> = Vec::with_capacity(ids.len());
+    for id in ids {
+        let json: Option<String> = conn.get(format!("{INTERVENTION_KEY_PREFIX}{id}")).await?;
+        if let Some(json) = json {
+            interventions.push(serde_json::from_str(&json)?);
+        }
+    }
+    interventions.sort_by_key(|i| i.at);
+    Ok(interventions)
+}
+
+/// Removes an intervention. Returns `false` if it did not exist.
+pub async fn delete_intervention(redis: &RedisStore, id: &Uuid) -> anyhow::Result<bool> {
+    let mut conn = redis.get_connection_manager().await?;
+    let removed: usize = conn.del(intervention_key(id)).await?;
+    conn.srem::<_, _, ()>(ALL_INTERVENTIONS, id.to_string())
+        .await?;
+    Ok(removed > 0)
+}
@@ -1,9 +1,29 @@
+pub mod acoustics;
+pub mod alerts;
 pub mod app_state;
 pub mod application;
 pub mod auth;
+pub mod cli;
 pub mod config;
 pub mod consts;
+pub mod devices;
+pub mod domains;
 pub mod error_utils;
+pub mod events;
+pub mod export;
+pub mod grpc;
+pub mod health;
+pub mod import;
+pub mod ingest;
+pub mod interventions;
+pub mod lifecycle;
+pub mod metrics;
 pub mod redis;
+pub mod request_id;
 pub mod routes;
 pub mod sensor;
+pub mod series;
+pub mod spool;
+pub mod telemetry;
+pub mod time_utils;
+pub mod tls;
@@ -0,0 +1,138 @@
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_util::task::TaskTracker;
+use tracing::error;
+
+/// Shutdown state shared across the process: whether it is draining, and the background work
+/// that should get a chance to finish before it exits.
+#[derive(Clone)]
+pub struct Lifecycle {
+    draining: Arc<watch::Sender<bool>>,
+    tasks: TaskTracker,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self {
+            draining: Arc::new(watch::Sender::new(false)),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// Whether shutdown has started; readiness fails and long-running jobs stop from then on.
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    pub fn begin_drain(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Resolves once shutdown has started.
+    pub async fn draining(&self) {
+        let mut rx = self.draining.subscribe();
+        // The sender lives as long as `self`, so this only ends once draining.
+        let _ = rx.wait_for(|draining| *draining).await;
+    }
+
+    /// Spawns background work that shutdown waits for, within the drain timeout.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tasks.spawn(task)
+    }
+
+    /// How many tasks started with `spawn` are still running.
+    pub fn running_tasks(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Waits for every task started with `spawn`; no new ones are tracked afterwards.
+    pub async fn wait_for_tasks(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+}
+
+/// Resolves with the signal's name on SIGTERM (sent by Kubernetes) or SIGINT (Ctrl-C).
+pub async fn shutdown_signal() -> &'static str {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!(error = %e, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => "SIGINT",
+        () = terminate => "SIGTERM",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn drain_is_observed_by_clones() {
+        let lifecycle = Lifecycle::new();
+        let observer = lifecycle.clone();
+        assert!(!observer.is_draining());
+
+        let waiting = tokio::spawn(async move { observer.draining().await });
+        lifecycle.begin_drain();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(lifecycle.is_draining());
+    }
+
+    #[tokio::test]
+    async fn waits_for_tracked_tasks() {
+        let lifecycle = Lifecycle::new();
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        lifecycle.spawn(async move {
+            let _ = rx.await;
+        });
+
+        let waiting = lifecycle.wait_for_tasks();
+        tokio::pin!(waiting);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(20), &mut waiting)
+                .await
+                .is_err()
+        );
+        tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap();
+    }
+}
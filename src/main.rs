@@ -1,15 +1,37 @@
+use clap::Parser;
 use signalstashrs::application::Application;
+use signalstashrs::cli::{self, Cli};
+use signalstashrs::config::layers;
 
 /// The main entry point for the application.
 ///
 /// This function is marked as `#[tokio::main]`, which means it will be called by
-/// the Tokio runtime once it has been initialized. It will then create a new
-/// `Application` instance, build it, and then run it.
+/// the Tokio runtime once it has been initialized. It will then read the settings
+/// from the configuration file, environment and flags, create a new `Application`
+/// instance, build it, and then run it.
 ///
-/// The `main` function will return an error if either the `Application` instance
-/// cannot be built or if the `run` method fails.
+/// The configuration is read again on SIGHUP or when the file changes. With
+/// `--check-config` or `--print-config` it only validates (and prints) the
+/// settings. The `main` function will return an error if the settings are invalid,
+/// if the `Application` instance cannot be built or if the `run` method fails.
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    Application::build().await?.run().await?;
+    let cli = Cli::parse();
+    let source = cli.source();
+    let settings = source.layers().settings()?;
+
+    if cli.print_config {
+        let printed = layers::to_toml(&settings.to_env_vars(), &settings.domains)?;
+        print!("{printed}");
+    }
+    if cli.check_config || cli.print_config {
+        cli::check_files(&settings)?;
+        if cli.check_config {
+            eprintln!("configuration is valid");
+        }
+        return Ok(());
+    }
+
+    Application::build(settings, source).await?.run().await?;
     Ok(())
 }
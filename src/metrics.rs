@@ -0,0 +1,261 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// Prefix shared by every metric name.
+const NAMESPACE: &str = "signalstash";
+/// `route` label for requests that matched no route, so probes for random paths cannot grow the
+/// label set.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Process-wide metrics, registered on first use.
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    samples_accepted: IntCounterVec,
+    samples_rejected: IntCounterVec,
+    batch_size: HistogramVec,
+    redis_command_duration: HistogramVec,
+    redis_errors: IntCounterVec,
+    auth_failures: IntCounterVec,
+    api_keys: IntGaugeVec,
+    ingest_queue_depth: IntGauge,
+    spool_bytes: IntGauge,
+    spool_segments: IntGauge,
+    spool_samples: IntCounterVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+    Metrics::register().expect("metric definitions are valid and registered once")
+});
+
+impl Metrics {
+    fn register() -> prometheus::Result<Self> {
+        let registry = Registry::new();
+        let opts = |name: &str, help: &str| Opts::new(name, help).namespace(NAMESPACE);
+        let histogram = |name: &str, help: &str, buckets: Vec<f64>| {
+            HistogramOpts::new(name, help)
+                .namespace(NAMESPACE)
+                .buckets(buckets)
+        };
+
+        let metrics = Self {
+            http_requests: IntCounterVec::new(
+                opts("http_requests_total", "HTTP requests by route and status."),
+                &["method", "route", "status"],
+            )?,
+            http_request_duration: HistogramVec::new(
+                histogram(
+                    "http_request_duration_seconds",
+                    "HTTP request latency by route and status.",
+                    prometheus::DEFAULT_BUCKETS.to_vec(),
+                ),
+                &["method", "route", "status"],
+            )?,
+            samples_accepted: IntCounterVec::new(
+                opts(
+                    "samples_accepted_total",
+                    "Ingested samples stored, by domain.",
+                ),
+                &["domain"],
+            )?,
+            samples_rejected: IntCounterVec::new(
+                opts(
+                    "samples_rejected_total",
+                    "Ingested samples refused, by domain and reason.",
+                ),
+                &["domain", "reason"],
+            )?,
+            batch_size: HistogramVec::new(
+                histogram(
+                    "batch_size_samples",
+                    "Samples written per batch, by source.",
+                    prometheus::exponential_buckets(1.0, 4.0, 10)?,
+                ),
+                &["source"],
+            )?,
+            redis_command_duration: HistogramVec::new(
+                histogram(
+                    "redis_command_duration_seconds",
+                    "Redis command latency by command; pipelines are labelled PIPELINE.",
+                    prometheus::exponential_buckets(0.0001, 2.0, 16)?,
+                ),
+                &["command"],
+            )?,
+            redis_errors: IntCounterVec::new(
+                opts("redis_errors_total", "Failed Redis commands by command."),
+                &["command"],
+            )?,
+            auth_failures: IntCounterVec::new(
+                opts(
+                    "auth_failures_total",
+                    "Rejected API key checks by scope and reason.",
+                ),
+                &["scope", "reason"],
+            )?,
+            api_keys: IntGaugeVec::new(
+                opts("api_keys", "API keys currently issued, by kind."),
+                &["kind"],
+            )?,
+            ingest_queue_depth: IntGauge::with_opts(opts(
+                "ingest_queue_depth",
+                "Ingest requests waiting for a writer.",
+            ))?,
+            spool_bytes: IntGauge::with_opts(opts(
+                "spool_bytes",
+                "Bytes of samples spooled to disk awaiting replay.",
+            ))?,
+            spool_segments: IntGauge::with_opts(opts(
+                "spool_segments",
+                "Spool segment files awaiting replay.",
+            ))?,
+            spool_samples: IntCounterVec::new(
+                opts(
+                    "spool_samples_total",
+                    "Samples spooled, replayed, dropped on replay, or refused because the spool was full or failed.",
+                ),
+                &["outcome"],
+            )?,
+            registry,
+        };
+
+        let collectors: [Box<dyn Collector>; 13] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_request_duration.clone()),
+            Box::new(metrics.samples_accepted.clone()),
+            Box::new(metrics.samples_rejected.clone()),
+            Box::new(metrics.batch_size.clone()),
+            Box::new(metrics.redis_command_duration.clone()),
+            Box::new(metrics.redis_errors.clone()),
+            Box::new(metrics.auth_failures.clone()),
+            Box::new(metrics.api_keys.clone()),
+            Box::new(metrics.ingest_queue_depth.clone()),
+            Box::new(metrics.spool_bytes.clone()),
+            Box::new(metrics.spool_segments.clone()),
+            Box::new(metrics.spool_samples.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector)?;
+        }
+        Ok(metrics)
+    }
+}
+
+/// The route template a request matched, e.g. `/api/devices/:device_id`.
+pub fn matched_route(req: &Request) -> String {
+    req.extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE.to_string(), |p| p.as_str().to_string())
+}
+
+/// Middleware recording the count and latency of every request by method, matched route and
+/// status.
+pub async fn track_http(req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = matched_route(&req);
+    let started = Instant::now();
+
+    let response = next.run(req).await;
+
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
+
+pub fn sample_accepted(domain: &str) {
+    METRICS.samples_accepted.with_label_values(&[domain]).inc();
+}
+
+pub fn sample_rejected(domain: &str, reason: &str) {
+    METRICS
+        .samples_rejected
+        .with_label_values(&[domain, reason])
+        .inc();
+}
+
+pub fn observe_batch(source: &str, samples: usize) {
+    METRICS
+        .batch_size
+        .with_label_values(&[source])
+        .observe(samples as f64);
+}
+
+pub fn observe_redis(command: &str, elapsed: Duration, failed: bool) {
+    METRICS
+        .redis_command_duration
+        .with_label_values(&[command])
+        .observe(elapsed.as_secs_f64());
+    if failed {
+        METRICS.redis_errors.with_label_values(&[command]).inc();
+    }
+}
+
+pub fn auth_failure(scope: &str, reason: &str) {
+    METRICS
+        .auth_failures
+        .with_label_values(&[scope, reason])
+        .inc();
+}
+
+pub fn set_api_keys(kind: &str, count: usize) {
+    METRICS
+        .api_keys
+        .with_label_values(&[kind])
+        .set(count as i64);
+}
+
+pub fn set_ingest_queue_depth(depth: usize) {
+    METRICS.ingest_queue_depth.set(depth as i64);
+}
+
+pub fn set_spool_usage(bytes: u64, segments: usize) {
+    METRICS.spool_bytes.set(bytes as i64);
+    METRICS.spool_segments.set(segments as i64);
+}
+
+pub fn spool_samples(outcome: &str, count: u64) {
+    METRICS
+        .spool_samples
+        .with_label_values(&[outcome])
+        .inc_by(count);
+}
+
+/// Renders every metric in the Prometheus text exposition format.
+pub fn render() -> anyhow::Result<String> {
+    let mut buf = Vec::new();
+    TextEncoder::new().encode(&METRICS.registry.gather(), &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_includes_recorded_series() {
+        sample_rejected("TEMPERATURE", "out_of_range");
+        observe_redis("TS.ADD", Duration::from_millis(2), true);
+
+        let text = render().unwrap();
+        assert!(text.contains(
+            "signalstash_samples_rejected_total{domain=\"TEMPERATURE\",reason=\"out_of_range\"}"
+        ));
+        assert!(text.contains("signalstash_redis_errors_total{command=\"TS.ADD\"}"));
+        assert!(text.contains("# TYPE signalstash_redis_command_duration_seconds histogram"));
+    }
+}
@@ -1,38 +1,490 @@
-use redis::Client;
-use redis::aio::MultiplexedConnection;
-use std::sync::Arc;
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::cluster::ClusterClient;
+use redis::cluster_async::ClusterConnection;
+use redis::cluster_routing::{Route, RoutingInfo, SingleNodeRoutingInfo, SlotAddr};
+use redis::sentinel::{Sentinel, SentinelNodeConnectionInfo};
+use redis::{
+    Arg, Client, Cmd, ConnectionAddr, ErrorKind, FromRedisValue, IntoConnectionInfo, Pipeline,
+    RedisError, RedisFuture, RedisResult, Script, TlsMode, Value,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::{Instrument, warn};
+
+use crate::consts::redis::ACQUIRE_LOCK_SCRIPT;
+use crate::metrics;
+
+/// `command` label for pipelined commands, which are timed as one round trip.
+const PIPELINE_LABEL: &str = "PIPELINE";
+
+/// How the application reaches Redis, chosen with `REDIS_MODE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedisMode {
+    #[default]
+    Standalone,
+    Sentinel,
+    Cluster,
+}
+
+impl RedisMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RedisMode::Standalone => "standalone",
+            RedisMode::Sentinel => "sentinel",
+            RedisMode::Cluster => "cluster",
+        }
+    }
+}
+
+impl FromStr for RedisMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standalone" => Ok(RedisMode::Standalone),
+            "sentinel" => Ok(RedisMode::Sentinel),
+            "cluster" => Ok(RedisMode::Cluster),
+            _ => Err(format!(
+                "expected standalone, sentinel or cluster, got {s:?}"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RedisMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The Redis deployment to connect to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedisTopology {
+    Standalone {
+        url: String,
+    },
+    /// A master found through Sentinel. `url` supplies the credentials, database and TLS used for
+    /// the master; its host is ignored.
+    Sentinel {
+        master_name: String,
+        sentinels: Vec<String>,
+        url: String,
+    },
+    /// A Redis Cluster discovered from any of its `nodes`.
+    Cluster {
+        nodes: Vec<String>,
+    },
+}
 
 #[derive(Clone)]
 pub struct RedisStore {
-    client: Arc<Client>,
+    backend: Arc<Backend>,
+}
+
+enum Backend {
+    Standalone(Client),
+    Sentinel(Arc<SentinelMaster>),
+    Cluster {
+        client: ClusterClient,
+        /// Cluster connections discover the slot map and talk to every node, so one is opened on
+        /// first use and shared; it follows slot migrations and failovers by itself.
+        connection: OnceCell<ClusterConnection>,
+        nodes: Vec<String>,
+    },
 }
 
 impl RedisStore {
     pub async fn new(url: &str) -> anyhow::Result<Self> {
-        let client = Client::open(url)?;
+        Self::connect(&RedisTopology::Standalone {
+            url: url.to_string(),
+        })
+        .await
+    }
+
+    pub async fn connect(topology: &RedisTopology) -> anyhow::Result<Self> {
+        let backend = match topology {
+            RedisTopology::Standalone { url } => Backend::Standalone(Client::open(url.as_str())?),
+            RedisTopology::Sentinel {
+                master_name,
+                sentinels,
+                url,
+            } => {
+                let info = url.as_str().into_connection_info()?;
+                Backend::Sentinel(Arc::new(SentinelMaster {
+                    sentinel: Mutex::new(Sentinel::build(sentinels.clone())?),
+                    master_name: master_name.clone(),
+                    node_info: SentinelNodeConnectionInfo {
+                        tls_mode: tls_mode(&info.addr),
+                        redis_connection_info: Some(info.redis),
+                    },
+                    master: RwLock::new(None),
+                }))
+            }
+            RedisTopology::Cluster { nodes } => Backend::Cluster {
+                client: ClusterClient::new(nodes.clone())?,
+                connection: OnceCell::new(),
+                nodes: nodes.clone(),
+            },
+        };
         Ok(Self {
-            client: Arc::new(client),
+            backend: Arc::new(backend),
         })
     }
 
-    pub async fn get_connection_manager(&self) -> anyhow::Result<MultiplexedConnection> {
-        let conn = self
-            .client
-            .clone()
-            .get_multiplexed_tokio_connection()
-            .await?;
-        Ok(conn)
+    #[tracing::instrument(name = "redis_connect", skip_all)]
+    pub async fn get_connection_manager(&self) -> anyhow::Result<InstrumentedConnection> {
+        let (inner, master) = match self.backend.as_ref() {
+            Backend::Standalone(client) => (
+                Inner::Single(client.get_multiplexed_tokio_connection().await?),
+                None,
+            ),
+            Backend::Sentinel(master) => {
+                (Inner::Single(master.connect().await?), Some(master.clone()))
+            }
+            Backend::Cluster {
+                client, connection, ..
+            } => {
+                let connection = connection
+                    .get_or_try_init(|| client.get_async_connection())
+                    .await?;
+                (Inner::Cluster(connection.clone()), None)
+            }
+        };
+        Ok(InstrumentedConnection { inner, master })
+    }
+
+    /// Opens a dedicated connection for pub/sub subscriptions. On Cluster it subscribes on the
+    /// first reachable node, since published messages reach every node.
+    pub async fn get_pubsub(&self) -> anyhow::Result<redis::aio::PubSub> {
+        match self.backend.as_ref() {
+            Backend::Standalone(client) => Ok(client.get_async_pubsub().await?),
+            Backend::Sentinel(master) => Ok(master.client().await?.get_async_pubsub().await?),
+            Backend::Cluster { nodes, .. } => {
+                let mut last_error = None;
+                for node in nodes {
+                    match Client::open(node.as_str())?.get_async_pubsub().await {
+                        Ok(pubsub) => return Ok(pubsub),
+                        Err(e) => last_error = Some(e),
+                    }
+                }
+                Err(last_error.map_or_else(
+                    || anyhow::anyhow!("no cluster nodes configured"),
+                    Into::into,
+                ))
+            }
+        }
     }
 
-    pub async fn check_connectivity(&self) -> anyhow::Result<()> {
+    /// Splits `items` into groups whose keys share a slot, since every key of a pipeline or
+    /// multi-key command must on Redis Cluster. Elsewhere everything stays in one group.
+    pub fn group_by_slot<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        key: impl Fn(&T) -> &str,
+    ) -> Vec<Vec<T>> {
+        if !matches!(self.backend.as_ref(), Backend::Cluster { .. }) {
+            let items: Vec<T> = items.into_iter().collect();
+            return if items.is_empty() {
+                Vec::new()
+            } else {
+                vec![items]
+            };
+        }
+        let mut groups: BTreeMap<u16, Vec<T>> = BTreeMap::new();
+        for item in items {
+            let slot = redis::cluster_routing::get_slot(key(&item).as_bytes());
+            groups.entry(slot).or_default().push(item);
+        }
+        groups.into_values().collect()
+    }
+
+    /// Takes or renews a lock shared by every replica, returning whether `holder` holds it. An
+    /// unrenewed lock expires after `ttl`, so another replica takes over. Taking, checking and
+    /// renewing run as one script, so a lock that expires meanwhile is never extended for a
+    /// replica that no longer holds it.
+    pub async fn acquire_lock(
+        &self,
+        key: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<bool> {
         let mut conn = self.get_connection_manager().await?;
-        let pong: String = redis::cmd(crate::consts::redis::PING_CMD)
-            .query_async(&mut conn)
+        let held: i64 = Script::new(ACQUIRE_LOCK_SCRIPT)
+            .key(key)
+            .arg(holder)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async(&mut conn)
             .await?;
-        if pong == crate::consts::redis::PONG_CMD {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Unexpected PING response: {}", pong))
+        Ok(held == 1)
+    }
+}
+
+/// The current master of a Sentinel-monitored deployment. Its address is cached until connecting
+/// to it fails or it answers as a read-only replica, which is what a failover looks like from
+/// here, and then asked of the sentinels again.
+struct SentinelMaster {
+    sentinel: Mutex<Sentinel>,
+    master_name: String,
+    node_info: SentinelNodeConnectionInfo,
+    master: RwLock<Option<Client>>,
+}
+
+impl SentinelMaster {
+    async fn client(&self) -> RedisResult<Client> {
+        if let Some(client) = self
+            .master
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+        {
+            return Ok(client);
+        }
+        let client = self
+            .sentinel
+            .lock()
+            .await
+            .async_master_for(&self.master_name, Some(&self.node_info))
+            .await?;
+        *self.master.write().unwrap_or_else(PoisonError::into_inner) = Some(client.clone());
+        Ok(client)
+    }
+
+    async fn connect(&self) -> RedisResult<MultiplexedConnection> {
+        match self
+            .client()
+            .await?
+            .get_multiplexed_tokio_connection()
+            .await
+        {
+            Ok(conn) => Ok(conn),
+            Err(e) => {
+                warn!(master = %self.master_name, error = %e, "Redis master unreachable, asking Sentinel again");
+                self.forget();
+                self.client()
+                    .await?
+                    .get_multiplexed_tokio_connection()
+                    .await
+            }
         }
     }
+
+    fn forget(&self) {
+        *self.master.write().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+/// A connection that records the latency and failures of every command it sends, and traces each
+/// one as a client span.
+#[derive(Clone)]
+pub struct InstrumentedConnection {
+    inner: Inner,
+    /// Set on Sentinel deployments, to look the master up again after a failover.
+    master: Option<Arc<SentinelMaster>>,
+}
+
+#[derive(Clone)]
+enum Inner {
+    Single(MultiplexedConnection),
+    Cluster(ClusterConnection),
+}
+
+impl InstrumentedConnection {
+    /// Sends `cmd`, which names no key, to the node serving `key`'s slot on Redis Cluster, e.g. a
+    /// `TS.MGET` filtered to the series sharing a device's hash tag. Elsewhere it is sent as usual.
+    pub async fn query_routed<T: FromRedisValue>(
+        &mut self,
+        cmd: &Cmd,
+        key: &str,
+    ) -> RedisResult<T> {
+        let Inner::Cluster(conn) = &mut self.inner else {
+            return cmd.query_async(self).await;
+        };
+        let name = command_name(cmd);
+        let slot = redis::cluster_routing::get_slot(key.as_bytes());
+        let routing = RoutingInfo::SingleNode(SingleNodeRoutingInfo::SpecificNode(Route::new(
+            slot,
+            SlotAddr::Master,
+        )));
+        let started = Instant::now();
+        let result = conn
+            .route_command(cmd, routing)
+            .instrument(redis_span(&name))
+            .await;
+        metrics::observe_redis(&name, started.elapsed(), result.is_err());
+        T::from_owned_redis_value(result?)
+    }
+
+    /// Forgets the Sentinel master after an error showing it is gone or was demoted.
+    fn check_failover<T>(&self, result: &RedisResult<T>) {
+        if let (Some(master), Err(e)) = (&self.master, result)
+            && (e.kind() == ErrorKind::ReadOnly || e.is_connection_dropped())
+        {
+            master.forget();
+        }
+    }
+}
+
+/// Whether an error means Redis could not be reached, as opposed to it refusing a command.
+pub fn is_unreachable(e: &RedisError) -> bool {
+    e.is_io_error() || e.is_connection_refusal() || e.is_connection_dropped() || e.is_timeout()
+}
+
+/// [`is_unreachable`] for an error that wraps a Redis error somewhere in its chain.
+pub fn is_unreachable_error(e: &anyhow::Error) -> bool {
+    e.chain()
+        .any(|cause| cause.downcast_ref().is_some_and(is_unreachable))
+}
+
+/// How to reach the nodes a sentinel names: over TLS when `REDIS_URL` is `rediss://`, verifying
+/// certificates unless it ends in `#insecure`.
+fn tls_mode(addr: &ConnectionAddr) -> Option<TlsMode> {
+    match addr {
+        ConnectionAddr::TcpTls { insecure: true, .. } => Some(TlsMode::Insecure),
+        ConnectionAddr::TcpTls {
+            insecure: false, ..
+        } => Some(TlsMode::Secure),
+        ConnectionAddr::Tcp(..) | ConnectionAddr::Unix(_) => None,
+    }
+}
+
+/// The command name of `cmd`, e.g. `TS.ADD`.
+fn command_name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => String::from_utf8_lossy(name).to_ascii_uppercase(),
+        _ => "UNKNOWN".to_string(),
+    }
+}
+
+/// A client span for one round trip to Redis.
+fn redis_span(command: &str) -> tracing::Span {
+    tracing::info_span!(
+        "redis",
+        otel.name = %command,
+        otel.kind = "client",
+        db.system = "redis",
+        db.operation.name = %command,
+    )
+}
+
+impl ConnectionLike for Inner {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match self {
+            Inner::Single(conn) => conn.req_packed_command(cmd),
+            Inner::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Inner::Single(conn) => conn.req_packed_commands(cmd, offset, count),
+            Inner::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Inner::Single(conn) => conn.get_db(),
+            Inner::Cluster(conn) => conn.get_db(),
+        }
+    }
+}
+
+impl ConnectionLike for InstrumentedConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        let name = command_name(cmd);
+        let span = redis_span(&name);
+        Box::pin(
+            async move {
+                let started = Instant::now();
+                let result = self.inner.req_packed_command(cmd).await;
+                metrics::observe_redis(&name, started.elapsed(), result.is_err());
+                self.check_failover(&result);
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        let span = redis_span(PIPELINE_LABEL);
+        Box::pin(
+            async move {
+                let started = Instant::now();
+                let result = self.inner.req_packed_commands(cmd, offset, count).await;
+                metrics::observe_redis(PIPELINE_LABEL, started.elapsed(), result.is_err());
+                self.check_failover(&result);
+                result
+            }
+            .instrument(span),
+        )
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn groups_keys_by_slot_only_on_cluster() {
+        let keys = ["a:{dev1}:x", "b:{dev2}:x", "c:{dev1}:y"];
+
+        let standalone = RedisStore::new("redis://localhost:6379").await.unwrap();
+        assert_eq!(standalone.group_by_slot(keys, |k| *k), vec![keys.to_vec()]);
+
+        let cluster = RedisStore::connect(&RedisTopology::Cluster {
+            nodes: vec!["redis://localhost:7000".to_string()],
+        })
+        .await
+        .unwrap();
+        let mut groups = cluster.group_by_slot(keys, |k| *k);
+        groups.sort();
+        assert_eq!(
+            groups,
+            vec![vec!["a:{dev1}:x", "c:{dev1}:y"], vec!["b:{dev2}:x"]]
+        );
+    }
+
+    #[test]
+    fn sentinel_nodes_use_tls_like_the_redis_url() {
+        let tls = |insecure| ConnectionAddr::TcpTls {
+            host: "redis".to_string(),
+            port: 6380,
+            insecure,
+            tls_params: None,
+        };
+        assert!(matches!(tls_mode(&tls(false)), Some(TlsMode::Secure)));
+        assert!(matches!(tls_mode(&tls(true)), Some(TlsMode::Insecure)));
+        let plain = ConnectionAddr::Tcp("redis".to_string(), 6379);
+        assert!(tls_mode(&plain).is_none());
+    }
+
+    #[test]
+    fn only_connection_failures_are_unreachable() {
+        let refused = RedisError::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        let error = anyhow::Error::from(refused).context("write failed");
+        assert!(is_unreachable_error(&error));
+
+        let wrong_type = RedisError::from((ErrorKind::ResponseError, "WRONGTYPE"));
+        let error = anyhow::Error::from(wrong_type).context("write failed");
+        assert!(!is_unreachable_error(&error));
+        assert!(!is_unreachable_error(&anyhow::anyhow!("not a Redis error")));
+    }
 }
@@ -0,0 +1,73 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+/// Header a request ID is accepted from and echoed in.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest caller-supplied request ID that is kept; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The ID of the request being handled on this task, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Keeps a caller-supplied ID only if it is short, printable ASCII, so it is safe to log and
+/// echo back.
+fn accept(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_graphic());
+    valid.then(|| id.to_string())
+}
+
+/// Middleware that takes the request's `X-Request-Id` or generates one, makes it available to
+/// logging for the rest of the request and echoes it in the response.
+pub async fn propagate(req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(accept)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_short_printable_ids() {
+        let accepted = |v: &str| accept(&HeaderValue::from_str(v).unwrap());
+        assert_eq!(
+            accepted("dev-1:batch-42").as_deref(),
+            Some("dev-1:batch-42")
+        );
+        assert_eq!(accepted(""), None);
+        assert_eq!(accepted("has space"), None);
+        assert_eq!(accepted(&"a".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+    }
+
+    #[tokio::test]
+    async fn current_is_scoped_to_the_request() {
+        assert_eq!(current(), None);
+        let seen = REQUEST_ID
+            .scope("abc".to_string(), async { current() })
+            .await;
+        assert_eq!(seen.as_deref(), Some("abc"));
+    }
+}
@@ -0,0 +1,205 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::acoustics::DailyIndicators;
+use crate::acoustics::levels::{self, Levels, WindowLevels};
+use crate::app_state::AppState;
+use crate::consts::errors::ERR_ACOUSTICS_READ;
+use crate::consts::routes::{ACOUSTICS_DAILY_PATH, ACOUSTICS_LEVELS_PATH};
+use crate::error_utils::log_and_response;
+use crate::sensor::Domain;
+use crate::series;
+use crate::time_utils::parse_duration;
+
+/// Longest time range a single levels query may cover.
+const MAX_RANGE_DAYS: i64 = 31;
+/// Most windows a single levels query may return.
+const MAX_WINDOWS: u64 = 10_000;
+
+#[derive(Deserialize)]
+pub struct LevelsQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Window length such as `15m` or `1h`. Omit for a single window covering the whole range.
+    pub window: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DailyQuery {
+    /// First reporting day, inclusive.
+    pub from: NaiveDate,
+    /// Last reporting day, inclusive.
+    pub to: NaiveDate,
+}
+
+#[derive(Serialize)]
+struct DailyResponse {
+    device_id: String,
+    timezone: String,
+    days: Vec<DailyIndicators>,
+}
+
+#[derive(Serialize)]
+struct LevelsResponse {
+    device_id: String,
+    domain: &'static str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    window_secs: Option<u64>,
+    overall: Option<Levels>,
+    windows: Vec<WindowLevels>,
+}
+
+/// Returns a new `Router` with the acoustic metrics endpoints:
+///
+/// * `GET /api/acoustics/:device_id/levels?from&to&window`: Leq, Lmax, Lmin, L10, L50 and L90
+///   over the whole range and per window, computed from the device's SPL samples.
+/// * `GET /api/acoustics/:device_id/daily?from&to`: Ld, Le, Ln, Lden and Ldn for each reporting
+///   day from `from` to `to`, inclusive.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(ACOUSTICS_LEVELS_PATH, get(get_levels))
+        .route(ACOUSTICS_DAILY_PATH, get(get_daily))
+        .with_state(state)
+}
+
+/// Checks a query's range and window, returning the parsed window length.
+fn check_query(query: &LevelsQuery) -> Result<Option<Duration>, String> {
+    if query.from >= query.to {
+        return Err("from must be before to".to_string());
+    }
+    if query.to - query.from > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return Err(format!("range must not exceed {MAX_RANGE_DAYS} days"));
+    }
+    let Some(window) = &query.window else {
+        return Ok(None);
+    };
+    let window = parse_duration(window)?;
+    if window.is_zero() {
+        return Err("window must be greater than zero".to_string());
+    }
+    let range_secs = (query.to - query.from).num_seconds().max(0) as u64;
+    if range_secs.div_ceil(window.as_secs()) > MAX_WINDOWS {
+        return Err(format!(
+            "query would return more than {MAX_WINDOWS} windows"
+        ));
+    }
+    Ok(Some(window))
+}
+
+async fn get_levels(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Query(query): Query<LevelsQuery>,
+) -> Response {
+    let window = match check_query(&query) {
+        Ok(window) => window,
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
+
+    let domain = Domain::SoundPressureLevel.as_str_name();
+    let key = state.series_keys.series(&device_id, domain);
+    let samples = match series::range(&state.redis, &key, query.from, query.to).await {
+        Ok(Some(samples)) => samples,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return log_and_response(ERR_ACOUSTICS_READ, e),
+    };
+
+    // TS.RANGE is inclusive of `to`; windows are half-open, so keep the overall set consistent.
+    let in_range: Vec<f64> = samples
+        .iter()
+        .filter(|s| s.timestamp < query.to)
+        .map(|s| s.value)
+        .collect();
+    let windows = match window {
+        Some(window) => levels::windowed(&samples, query.from, query.to, window),
+        None => Vec::new(),
+    };
+
+    Json(LevelsResponse {
+        device_id,
+        domain,
+        from: query.from,
+        to: query.to,
+        window_secs: window.map(|w| w.as_secs()),
+        overall: levels::levels(&in_range),
+        windows,
+    })
+    .into_response()
+}
+
+async fn get_daily(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Query(query): Query<DailyQuery>,
+) -> Response {
+    if query.from > query.to {
+        return (StatusCode::BAD_REQUEST, "from must not be after to").into_response();
+    }
+    if (query.to - query.from).num_days() >= MAX_RANGE_DAYS {
+        let msg = format!("range must not exceed {MAX_RANGE_DAYS} days");
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+
+    let periods = state.noise_periods;
+    let (start, _) = periods.day_bounds(query.from);
+    let (_, end) = periods.day_bounds(query.to);
+    let domain = Domain::SoundPressureLevel.as_str_name();
+    let key = state.series_keys.series(&device_id, domain);
+    let samples = match series::range(&state.redis, &key, start, end).await {
+        Ok(Some(samples)) => samples,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return log_and_response(ERR_ACOUSTICS_READ, e),
+    };
+
+    let days = query
+        .from
+        .iter_days()
+        .take_while(|date| *date <= query.to)
+        .map(|date| periods.indicators(date, &samples))
+        .collect();
+
+    Json(DailyResponse {
+        device_id,
+        timezone: periods.timezone.name().to_string(),
+        days,
+    })
+    .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn query(hours: i64, window: Option<&str>) -> LevelsQuery {
+        let from = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        LevelsQuery {
+            from,
+            to: from + chrono::Duration::hours(hours),
+            window: window.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn check_query_limits() {
+        assert_eq!(check_query(&query(1, None)), Ok(None));
+        assert_eq!(
+            check_query(&query(1, Some("15m"))),
+            Ok(Some(Duration::from_secs(900)))
+        );
+        assert!(check_query(&query(0, None)).is_err());
+        assert!(check_query(&query(24 * 32, None)).is_err());
+        assert!(check_query(&query(24, Some("1s"))).is_err());
+        assert!(check_query(&query(1, Some("0m"))).is_err());
+    }
+}
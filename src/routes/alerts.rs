@@ -0,0 +1,99 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::alerts::rules::{self, AlertRule, Condition, CreateAlertRule};
+use crate::alerts::state::{self, AlertState};
+use crate::app_state::AppState;
+use crate::consts::errors::{
+    ERR_ALERT_RULE_DELETE, ERR_ALERT_RULE_READ, ERR_ALERT_RULE_WRITE, ERR_ALERT_STATE_READ,
+    ERR_DOMAIN_LOOKUP,
+};
+use crate::consts::routes::{ALERT_RULE_PATH, ALERT_RULES_PATH, ALERTS_PATH};
+use crate::domains::store;
+use crate::error_utils::log_and_response;
+
+/// Returns a new `Router` with the alerting endpoints:
+///
+/// * `GET /api/alerts`: current pending, firing and resolved alerts.
+/// * `GET /api/alerts/rules`: list alerting rules.
+/// * `POST /api/alerts/rules`: create an alerting rule.
+/// * `GET /api/alerts/rules/:id`: fetch a rule.
+/// * `DELETE /api/alerts/rules/:id`: remove a rule and its alerts.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(ALERTS_PATH, get(list_alerts))
+        .route(ALERT_RULES_PATH, get(list_rules).post(create_rule))
+        .route(ALERT_RULE_PATH, get(get_rule).delete(delete_rule))
+        .with_state(state)
+}
+
+async fn list_alerts(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<AlertState>>, Response> {
+    state::list_states(&state.redis)
+        .await
+        .map(Json)
+        .map_err(|e| log_and_response(ERR_ALERT_STATE_READ, e))
+}
+
+async fn list_rules(State(state): State<Arc<AppState>>) -> Result<Json<Vec<AlertRule>>, Response> {
+    rules::list_rules(&state.redis)
+        .await
+        .map(Json)
+        .map_err(|e| log_and_response(ERR_ALERT_RULE_READ, e))
+}
+
+async fn create_rule(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateAlertRule>,
+) -> Result<(StatusCode, Json<AlertRule>), Response> {
+    if let Err(msg) = payload.check() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, msg).into_response());
+    }
+    if let Condition::Threshold { domain, .. } = &payload.condition {
+        let spec = store::lookup(&state.redis, &state.domains, domain)
+            .await
+            .map_err(|e| log_and_response(ERR_DOMAIN_LOOKUP, e))?;
+        if spec.is_none() {
+            let msg = format!("unknown domain {domain}");
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, msg).into_response());
+        }
+    }
+
+    let rule = AlertRule::from_create(payload, chrono::Utc::now());
+    rules::save_rule(&state.redis, &rule)
+        .await
+        .map_err(|e| log_and_response(ERR_ALERT_RULE_WRITE, e))?;
+
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+async fn get_rule(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AlertRule>, Response> {
+    match rules::get_rule(&state.redis, &id).await {
+        Ok(Some(rule)) => Ok(Json(rule)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_ALERT_RULE_READ, e)),
+    }
+}
+
+async fn delete_rule(State(state): State<Arc<AppState>>, Path(id): Path<Uuid>) -> Response {
+    match rules::delete_rule(&state.redis, &id).await {
+        Ok(true) => {}
+        Ok(false) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return log_and_response(ERR_ALERT_RULE_DELETE, e),
+    }
+    if let Err(e) = state::delete_rule_states(&state.redis, &id).await {
+        return log_and_response(ERR_ALERT_RULE_DELETE, e);
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
@@ -9,9 +9,9 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 use crate::app_state::AppState;
+use crate::auth::api_key::ALL_API_KEYS;
 
 const API_KEY_PREFIX: &str = "api_key:";
-const ALL_API_KEYS: &str = "all_api_keys";
 
 #[derive(Serialize)]
 struct ApiKey {
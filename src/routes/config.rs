@@ -0,0 +1,20 @@
+use axum::{Json, Router, extract::State, routing::get};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::config::reload::ConfigSnapshot;
+use crate::consts::routes::CONFIG_PATH;
+
+/// Returns a new `Router` with the admin view of the configuration:
+///
+/// * `GET /api/config`: the running settings and configured custom domains, which of them reload
+///   on SIGHUP or a configuration file change, and changes waiting for a restart.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(CONFIG_PATH, get(active_config))
+        .with_state(state)
+}
+
+async fn active_config(State(state): State<Arc<AppState>>) -> Json<ConfigSnapshot> {
+    Json(state.config.snapshot())
+}
@@ -0,0 +1,207 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::consts::errors::{
+    ERR_DEVICE_DELETE, ERR_DEVICE_HEALTH, ERR_DEVICE_READ, ERR_DEVICE_STATUS, ERR_DEVICE_WRITE,
+    ERR_DOMAIN_READ, MSG_DEVICE_ID_NOT_FILTERABLE,
+};
+use crate::consts::routes::{
+    DEVICE_CALIBRATIONS_PATH, DEVICE_HEALTH_PATH, DEVICE_PATH, DEVICE_STATUS_PATH, DEVICES_PATH,
+};
+use crate::devices::calibration::{self, Calibration, CreateCalibration};
+use crate::devices::health::{self, DeviceHealth};
+use crate::devices::registry::{self, CreateDevice, Device, UpdateDevice};
+use crate::devices::status::{self, DeviceStatus};
+use crate::domains::store as domain_store;
+use crate::error_utils::log_and_response;
+
+/// Returns a new `Router` with the device registry endpoints:
+///
+/// * `GET /api/devices`: list all registered devices.
+/// * `POST /api/devices`: register a new device.
+/// * `GET /api/devices/:device_id`: fetch a single device.
+/// * `PATCH /api/devices/:device_id`: update some of a device's metadata.
+/// * `DELETE /api/devices/:device_id`: remove a device from the registry.
+/// * `GET /api/devices/:device_id/status`: last-seen time, sample count and online/stale/offline.
+/// * `GET /api/devices/:device_id/health`: latest battery, RSSI, uptime, memory and buffer readings.
+/// * `GET /api/devices/:device_id/calibrations`: the device's calibration history.
+/// * `POST /api/devices/:device_id/calibrations`: add a new calibration version for a domain.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(DEVICES_PATH, get(list_devices).post(create_device))
+        .route(
+            DEVICE_PATH,
+            get(get_device).patch(update_device).delete(delete_device),
+        )
+        .route(DEVICE_STATUS_PATH, get(get_device_status))
+        .route(DEVICE_HEALTH_PATH, get(get_device_health))
+        .route(
+            DEVICE_CALIBRATIONS_PATH,
+            get(list_calibrations).post(create_calibration),
+        )
+        .with_state(state)
+}
+
+async fn list_devices(State(state): State<Arc<AppState>>) -> Result<Json<Vec<Device>>, Response> {
+    registry::list_devices(&state.redis)
+        .await
+        .map(Json)
+        .map_err(|e| log_and_response(ERR_DEVICE_READ, e))
+}
+
+async fn create_device(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateDevice>,
+) -> Result<(StatusCode, Json<Device>), Response> {
+    if payload.device_id.is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+    // Its health summary could never be read back otherwise.
+    if !health::is_filterable(&payload.device_id) {
+        return Err((StatusCode::BAD_REQUEST, MSG_DEVICE_ID_NOT_FILTERABLE).into_response());
+    }
+
+    let device = Device::from_create(payload, chrono::Utc::now());
+    let created = registry::create_device(&state.redis, &device)
+        .await
+        .map_err(|e| log_and_response(ERR_DEVICE_WRITE, e))?;
+    if !created {
+        return Err(StatusCode::CONFLICT.into_response());
+    }
+
+    Ok((StatusCode::CREATED, Json(device)))
+}
+
+async fn get_device(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<Json<Device>, Response> {
+    match registry::get_device(&state.redis, &device_id).await {
+        Ok(Some(device)) => Ok(Json(device)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_DEVICE_READ, e)),
+    }
+}
+
+async fn update_device(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(payload): Json<UpdateDevice>,
+) -> Result<Json<Device>, Response> {
+    let mut device = match registry::get_device(&state.redis, &device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => return Err(log_and_response(ERR_DEVICE_READ, e)),
+    };
+
+    device.apply(payload, chrono::Utc::now());
+    registry::save_device(&state.redis, &device)
+        .await
+        .map_err(|e| log_and_response(ERR_DEVICE_WRITE, e))?;
+
+    Ok(Json(device))
+}
+
+async fn delete_device(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Response {
+    match registry::delete_device(&state.redis, &device_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => log_and_response(ERR_DEVICE_DELETE, e),
+    }
+}
+
+async fn get_device_status(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<Json<DeviceStatus>, Response> {
+    match status::get_activity(&state.redis, &device_id).await {
+        Ok(Some(activity)) => Ok(Json(DeviceStatus::new(
+            device_id,
+            activity,
+            &state.liveness,
+            chrono::Utc::now(),
+        ))),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_DEVICE_STATUS, e)),
+    }
+}
+
+async fn get_device_health(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<Json<DeviceHealth>, Response> {
+    if !health::is_filterable(&device_id) {
+        return Err((StatusCode::BAD_REQUEST, MSG_DEVICE_ID_NOT_FILTERABLE).into_response());
+    }
+    let device = registry::get_device(&state.redis, &device_id)
+        .await
+        .map_err(|e| log_and_response(ERR_DEVICE_READ, e))?;
+    let readings = health::latest_readings(&state.redis, &device_id)
+        .await
+        .map_err(|e| log_and_response(ERR_DEVICE_HEALTH, e))?;
+
+    if device.is_none() && readings.is_empty() {
+        return Err(StatusCode::NOT_FOUND.into_response());
+    }
+
+    Ok(Json(DeviceHealth {
+        device_id,
+        firmware_version: device.and_then(|d| d.firmware_version),
+        readings,
+    }))
+}
+
+async fn list_calibrations(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+) -> Result<Json<Vec<Calibration>>, Response> {
+    match registry::get_device(&state.redis, &device_id).await {
+        Ok(Some(device)) => Ok(Json(device.calibrations)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_DEVICE_READ, e)),
+    }
+}
+
+async fn create_calibration(
+    State(state): State<Arc<AppState>>,
+    Path(device_id): Path<String>,
+    Json(payload): Json<CreateCalibration>,
+) -> Result<(StatusCode, Json<Calibration>), Response> {
+    if let Err(msg) = payload.check() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, msg).into_response());
+    }
+
+    let domain = domain_store::lookup(&state.redis, &state.domains, &payload.domain)
+        .await
+        .map_err(|e| log_and_response(ERR_DOMAIN_READ, e))?;
+    if domain.is_none() {
+        let msg = format!("unknown domain: {}", payload.domain);
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, msg).into_response());
+    }
+
+    let mut device = match registry::get_device(&state.redis, &device_id).await {
+        Ok(Some(device)) => device,
+        Ok(None) => return Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => return Err(log_and_response(ERR_DEVICE_READ, e)),
+    };
+
+    let now = chrono::Utc::now();
+    let calibration = calibration::next_version(&device.calibrations, payload, now);
+    device.calibrations.push(calibration.clone());
+    device.updated_at = now;
+    registry::save_device(&state.redis, &device)
+        .await
+        .map_err(|e| log_and_response(ERR_DEVICE_WRITE, e))?;
+
+    Ok((StatusCode::CREATED, Json(calibration)))
+}
@@ -0,0 +1,84 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::consts::errors::{ERR_DOMAIN_DELETE, ERR_DOMAIN_READ, ERR_DOMAIN_WRITE};
+use crate::consts::routes::{DOMAIN_PATH, DOMAINS_PATH};
+use crate::domains::{DomainSpec, store};
+use crate::error_utils::log_and_response;
+
+/// Returns a new `Router` with the domain catalog endpoints:
+///
+/// * `GET /api/domains`: list built-in and custom domains.
+/// * `POST /api/domains`: register a custom domain.
+/// * `GET /api/domains/:name`: fetch a single domain.
+/// * `DELETE /api/domains/:name`: remove a custom domain.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(DOMAINS_PATH, get(list_domains).post(create_domain))
+        .route(DOMAIN_PATH, get(get_domain).delete(delete_domain))
+        .with_state(state)
+}
+
+async fn list_domains(State(state): State<Arc<AppState>>) -> Json<Vec<DomainSpec>> {
+    Json(state.domains.list())
+}
+
+async fn create_domain(
+    State(state): State<Arc<AppState>>,
+    Json(mut payload): Json<DomainSpec>,
+) -> Result<(StatusCode, Json<DomainSpec>), Response> {
+    payload.builtin = false;
+    if let Err(msg) = payload.check_definition() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, msg).into_response());
+    }
+
+    let existing = store::lookup(&state.redis, &state.domains, &payload.name)
+        .await
+        .map_err(|e| log_and_response(ERR_DOMAIN_READ, e))?;
+    if existing.is_some() {
+        return Err(StatusCode::CONFLICT.into_response());
+    }
+
+    store::save_domain(&state.redis, &payload)
+        .await
+        .map_err(|e| log_and_response(ERR_DOMAIN_WRITE, e))?;
+    state.domains.insert(payload.clone());
+
+    Ok((StatusCode::CREATED, Json(payload)))
+}
+
+async fn get_domain(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<Json<DomainSpec>, Response> {
+    match store::lookup(&state.redis, &state.domains, &name).await {
+        Ok(Some(spec)) => Ok(Json(spec)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_DOMAIN_READ, e)),
+    }
+}
+
+async fn delete_domain(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Response {
+    let spec = match store::lookup(&state.redis, &state.domains, &name).await {
+        Ok(Some(spec)) => spec,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return log_and_response(ERR_DOMAIN_READ, e),
+    };
+    if spec.builtin {
+        return StatusCode::CONFLICT.into_response();
+    }
+
+    if let Err(e) = store::delete_domain(&state.redis, &name).await {
+        return log_and_response(ERR_DOMAIN_DELETE, e);
+    }
+    state.domains.remove(&name);
+
+    StatusCode::NO_CONTENT.into_response()
+}
@@ -0,0 +1,124 @@
+use axum::{
+    Router,
+    extract::{Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::app_state::AppState;
+use crate::consts::errors::ERR_DOMAIN_LOOKUP;
+use crate::consts::routes::EXPORT_PATH;
+use crate::domains::store;
+use crate::error_utils::log_and_response;
+use crate::export::stream::ExportSeries;
+use crate::export::{ExportFormat, ExportPlan, export_body};
+
+/// Most series a single export may include.
+const MAX_SERIES: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    /// Comma-separated device ids.
+    pub device_id: String,
+    /// Comma-separated domain names; every device is exported for every domain.
+    pub domain: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Splits a comma-separated list, dropping empty entries and duplicates but keeping order.
+fn split_list(list: &str) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    for item in list.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        if !items.iter().any(|i| i == item) {
+            items.push(item.to_string());
+        }
+    }
+    items
+}
+
+/// Returns a new `Router` with the export endpoint:
+///
+/// * `GET /api/export?device_id&domain&from&to&format`: every sample of the selected series in
+///   the range, streamed as `csv` (default), `ndjson` or `parquet`.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(EXPORT_PATH, get(export))
+        .with_state(state)
+}
+
+async fn export(State(state): State<Arc<AppState>>, Query(query): Query<ExportQuery>) -> Response {
+    if query.from > query.to {
+        return (StatusCode::BAD_REQUEST, "from must not be after to").into_response();
+    }
+    let device_ids = split_list(&query.device_id);
+    let domains = split_list(&query.domain);
+    if device_ids.is_empty() || domains.is_empty() {
+        let msg = "at least one device_id and one domain are required";
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+    if device_ids.len() * domains.len() > MAX_SERIES {
+        let msg = format!("an export may include at most {MAX_SERIES} series");
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+
+    let mut units = Vec::with_capacity(domains.len());
+    for domain in &domains {
+        match store::lookup(&state.redis, &state.domains, domain).await {
+            Ok(Some(spec)) => units.push(spec.unit),
+            Ok(None) => {
+                let msg = format!("unknown domain {domain}");
+                return (StatusCode::BAD_REQUEST, msg).into_response();
+            }
+            Err(e) => return log_and_response(ERR_DOMAIN_LOOKUP, e),
+        }
+    }
+
+    let series = device_ids
+        .iter()
+        .flat_map(|device_id| {
+            domains
+                .iter()
+                .zip(&units)
+                .map(|(domain, unit)| ExportSeries {
+                    device_id: device_id.clone(),
+                    domain: domain.clone(),
+                    unit: unit.clone(),
+                })
+        })
+        .collect();
+    let format = query.format;
+    let plan = ExportPlan {
+        series,
+        from: query.from,
+        to: query.to,
+        format,
+    };
+
+    let disposition = format!("attachment; filename=\"export.{}\"", format.extension());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        export_body(state, plan),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_list_trims_and_dedups() {
+        assert_eq!(split_list("a, b,,a ,c"), vec!["a", "b", "c"]);
+        assert!(split_list(" , ").is_empty());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::app_state::AppState;
-use crate::consts::health::MSG_REDIS_CONNECTIVITY_ERROR;
-use crate::consts::routes::{HEALTHZ_PATH, READYZ_PATH, STARTZ_PATH};
-use crate::error_utils::log_and_response;
-use axum::{Router, extract::State, response::IntoResponse, routing::get};
+use crate::consts::health::{MSG_NOT_READY, MSG_SHUTTING_DOWN};
+use crate::consts::routes::{HEALTH_DETAILS_PATH, HEALTHZ_PATH, READYZ_PATH, STARTZ_PATH};
+use crate::health::{self, CheckStatus, HealthReport};
+use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
 use std::sync::Arc;
+use tracing::warn;
 
 /// Returns a new `Router` containing endpoints for health-checking and startup synchronization.
 ///
@@ -24,6 +25,18 @@ pub fn routes(state: Arc<AppState>) -> Router {
         .route(STARTZ_PATH, get(startz))
 }
 
+/// Returns a new `Router` with the admin view of the application's health:
+///
+/// * `GET /healthz/details`: every check with its status, latency and details (Redis
+///   connectivity, the RedisTimeSeries module, Redis memory and persistence, shutdown, background
+///   tasks shutdown waits for, such as import jobs) and the outcome of each background job's
+///   recent runs.
+pub fn details_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(HEALTH_DETAILS_PATH, get(health_details))
+        .with_state(state)
+}
+
 /// Returns "ok" if the application is still alive.
 ///
 /// This is intended to be used by load balancers, service meshes, or other external systems to determine
@@ -37,14 +50,29 @@ async fn healthz() -> &'static str {
 ///
 /// This is intended to be used by a load balancer or service mesh to determine if the application is ready
 /// to receive traffic. The application should return a success response (200) if it is ready, and a
-/// failure response (500) if it is not.
+/// failure response (503) if it is not.
 ///
-/// External dependencies should be checked before returning "ready".
+/// Redis must answer and have the RedisTimeSeries module loaded; otherwise the failing checks are
+/// returned as JSON, telling a network failure from a missing module. Once shutdown has started it
+/// answers 503 without checking anything, so traffic moves to other replicas while this one drains.
 async fn readyz(State(state): State<Arc<AppState>>) -> axum::response::Response {
-    match state.redis.check_connectivity().await {
-        Ok(()) => crate::consts::messages::READY.into_response(),
-        Err(e) => log_and_response(MSG_REDIS_CONNECTIVITY_ERROR, e),
+    if state.lifecycle.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, MSG_SHUTTING_DOWN).into_response();
+    }
+    let checks = health::readiness(&state).await;
+    let failed: Vec<_> = checks
+        .into_iter()
+        .filter(|c| c.status != CheckStatus::Ok)
+        .collect();
+    if failed.is_empty() {
+        return crate::consts::messages::READY.into_response();
     }
+    warn!(checks = ?failed, "{MSG_NOT_READY}");
+    (StatusCode::SERVICE_UNAVAILABLE, Json(failed)).into_response()
+}
+
+async fn health_details(State(state): State<Arc<AppState>>) -> Json<HealthReport> {
+    Json(health::report(&state).await)
 }
 
 /// Returns "started" if the application has started successfully.
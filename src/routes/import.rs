@@ -0,0 +1,92 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::consts::errors::{ERR_IMPORT_JOB_READ, ERR_IMPORT_JOB_WRITE};
+use crate::consts::routes::{IMPORT_JOB_PATH, IMPORT_PATH};
+use crate::error_utils::log_and_response;
+use crate::import::job::{self, ImportJob};
+use crate::import::{DuplicatePolicy, ImportFormat, spawn_import};
+
+/// Largest import body accepted in one request.
+const MAX_IMPORT_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: ImportFormat,
+    #[serde(default)]
+    pub on_duplicate: DuplicatePolicy,
+    /// Apply the calibration in effect at each sample's timestamp, as ingest would have.
+    #[serde(default = "default_calibrate")]
+    pub calibrate: bool,
+}
+
+fn default_calibrate() -> bool {
+    true
+}
+
+/// Returns a new `Router` with the bulk import endpoints:
+///
+/// * `POST /api/import?format&on_duplicate&calibrate`: start importing the body as `csv`
+///   (default), `ndjson` or length-delimited `protobuf`; answers 202 with the job.
+/// * `GET /api/import/:id`: an import job's progress and rejected records.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(
+            IMPORT_PATH,
+            post(start_import).layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES)),
+        )
+        .route(IMPORT_JOB_PATH, get(get_import))
+        .with_state(state)
+}
+
+async fn start_import(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Response {
+    if body.is_empty() {
+        return (StatusCode::BAD_REQUEST, "import body is empty").into_response();
+    }
+
+    let job = ImportJob::new(
+        query.format,
+        query.on_duplicate,
+        query.calibrate,
+        body.len(),
+        chrono::Utc::now(),
+    );
+    if let Err(e) = job::save_job(&state.redis, &job).await {
+        return log_and_response(ERR_IMPORT_JOB_WRITE, e);
+    }
+    spawn_import(state.clone(), job.clone(), body);
+
+    let location = format!("{IMPORT_PATH}/{}", job.id);
+    (
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    )
+        .into_response()
+}
+
+async fn get_import(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ImportJob>, Response> {
+    match job::get_job(&state.redis, &id).await {
+        Ok(Some(job)) => Ok(Json(job)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_IMPORT_JOB_READ, e)),
+    }
+}
@@ -1,27 +1,37 @@
 use crate::app_state::AppState;
+use crate::auth::client_cert::AuthenticatedDevice;
+use crate::domains::catalog;
 use crate::error_utils::log_and_response;
-use crate::sensor::{Domain, SensorData};
+use crate::ingest::{self, IngestError, Sample, Source, UNKNOWN_DOMAIN_LABEL};
+use crate::metrics;
+use crate::sensor::SensorData;
 use axum::body::Bytes;
-use axum::{Router, routing::post};
-use axum::{extract::State, http::StatusCode, response::IntoResponse, response::Response};
+use axum::{Extension, Router, routing::post};
+use axum::{
+    extract::State, http::StatusCode, http::header, response::IntoResponse, response::Response,
+};
 use prost::Message;
-use std::convert::TryFrom;
 use std::sync::Arc;
 
 use crate::consts::errors::{
-    ERR_DECODE_PROTOBUF, ERR_INVALID_UTF8_DEVICE_ID, ERR_REDIS_CONN, ERR_REDIS_WRITE,
-};
-use crate::consts::redis::{
-    REDIS_CMD_TS_ADD, REDIS_LABEL_DEVICE_ID, REDIS_LABEL_DOMAIN, REDIS_LABELS_LABEL,
+    ERR_DECODE_PROTOBUF, ERR_INGEST_QUEUE_CLOSED, ERR_INVALID_UTF8_DEVICE_ID,
+    MSG_CERTIFICATE_DEVICE_MISMATCH, MSG_INGEST_QUEUE_FULL, MSG_UNREGISTERED_DEVICE,
 };
 
+/// Seconds a device is asked to wait when the write queue is full.
+const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 1;
+
 pub fn routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route(crate::consts::routes::INGEST_PATH, post(ingest))
         .with_state(state)
 }
 
-async fn ingest(State(state): State<Arc<AppState>>, body: Bytes) -> Response {
+async fn ingest(
+    State(state): State<Arc<AppState>>,
+    authenticated: Option<Extension<AuthenticatedDevice>>,
+    body: Bytes,
+) -> Response {
     // Check content-type
     // (axum does not enforce this for us, so we check manually)
     // Accept only application/x-protobuf
@@ -36,48 +46,85 @@ async fn ingest(State(state): State<Arc<AppState>>, body: Bytes) -> Response {
     tracing::debug!("Received {} bytes", body.len());
 
     // Parse protobuf body
-    let sensor_data = SensorData::decode(body.as_ref());
+    let sensor_data = tracing::info_span!("decode", bytes = body.len())
+        .in_scope(|| SensorData::decode(body.as_ref()));
     let sensor_data = match sensor_data {
         Ok(msg) => msg,
-        Err(e) => return log_and_response(ERR_DECODE_PROTOBUF, e),
+        Err(e) => {
+            metrics::sample_rejected(UNKNOWN_DOMAIN_LABEL, "decode_error");
+            return log_and_response(ERR_DECODE_PROTOBUF, e);
+        }
     };
 
     let device_id = match std::str::from_utf8(&sensor_data.device_id) {
         Ok(s) => s.to_owned(),
-        Err(e) => return log_and_response(ERR_INVALID_UTF8_DEVICE_ID, e),
+        Err(e) => {
+            metrics::sample_rejected(UNKNOWN_DOMAIN_LABEL, "invalid_device_id");
+            return log_and_response(ERR_INVALID_UTF8_DEVICE_ID, e);
+        }
     };
 
-    let domain = Domain::try_from(sensor_data.domain)
-        .map(|d| d.as_str_name())
-        .unwrap_or("UNKNOWN");
-
-    let key = format!("{}:{}:{}", state.sensor_datum_prefix, device_id, domain);
-
-    // TODO(steve): PUT THIS BACK
-    // let timestamp = sensor_data.timestamp;
-    let timestamp = chrono::Utc::now().timestamp();
-
-    let datum = sensor_data.datum;
+    // A client certificate only vouches for the device it names.
+    if let Some(Extension(AuthenticatedDevice(certified))) = &authenticated
+        && *certified != device_id
+    {
+        tracing::warn!(device_id = %device_id, certificate = %certified, "{MSG_CERTIFICATE_DEVICE_MISMATCH}");
+        metrics::sample_rejected(UNKNOWN_DOMAIN_LABEL, "certificate_mismatch");
+        return (StatusCode::FORBIDDEN, MSG_CERTIFICATE_DEVICE_MISMATCH).into_response();
+    }
 
-    let mut conn = match state.redis.get_connection_manager().await {
-        Ok(conn) => conn,
-        Err(e) => return log_and_response(ERR_REDIS_CONN, e),
+    let sample = Sample {
+        device_id,
+        domain: catalog::sample_domain_name(&sensor_data),
+        value: f64::from(sensor_data.datum),
+        received_at: chrono::Utc::now(),
     };
-
-    let res: redis::RedisResult<()> = redis::cmd(REDIS_CMD_TS_ADD)
-        .arg(&key)
-        .arg(timestamp)
-        .arg(datum)
-        .arg(REDIS_LABELS_LABEL)
-        .arg(REDIS_LABEL_DEVICE_ID)
-        .arg(&device_id)
-        .arg(REDIS_LABEL_DOMAIN)
-        .arg(domain)
-        .query_async(&mut conn)
-        .await;
-    if let Err(e) = res {
-        return log_and_response(ERR_REDIS_WRITE, e);
-    }
+    let event = match ingest::store_sample(&state, &sample, Source::Ingest).await {
+        Ok(event) => event,
+        Err(IngestError::UnregisteredDevice) => {
+            return (StatusCode::FORBIDDEN, MSG_UNREGISTERED_DEVICE).into_response();
+        }
+        Err(IngestError::Rejected(rejection)) => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, rejection.to_string()).into_response();
+        }
+        Err(IngestError::Busy) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, QUEUE_FULL_RETRY_AFTER_SECS.to_string())],
+                MSG_INGEST_QUEUE_FULL,
+            )
+                .into_response();
+        }
+        Err(IngestError::Closed) => {
+            return log_and_response(
+                ERR_INGEST_QUEUE_CLOSED,
+                "no writer is left to store the sample",
+            );
+        }
+        Err(IngestError::Redis {
+            context,
+            error,
+            unreachable: true,
+        }) => {
+            return spool_or_fail(&state, sample, context, error).await;
+        }
+        Err(IngestError::Redis { context, error, .. }) => return log_and_response(context, error),
+    };
+    ingest::record_stored(&state, event).await;
 
     StatusCode::NO_CONTENT.into_response()
 }
+
+/// Answers a sample Redis could not be reached for: spooled for replay with 202 Accepted when the
+/// spool is enabled and has room, otherwise the Redis failure as a 500.
+async fn spool_or_fail(
+    state: &AppState,
+    sample: Sample,
+    context: &'static str,
+    error: anyhow::Error,
+) -> Response {
+    match ingest::spool_sample(state, sample, context, error).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(error) => log_and_response(context, error),
+    }
+}
@@ -0,0 +1,151 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::consts::errors::{
+    ERR_ACOUSTICS_READ, ERR_INTERVENTION_DELETE, ERR_INTERVENTION_READ, ERR_INTERVENTION_WRITE,
+};
+use crate::consts::routes::{INTERVENTION_COMPARISON_PATH, INTERVENTION_PATH, INTERVENTIONS_PATH};
+use crate::error_utils::log_and_response;
+use crate::interventions::comparison::{self, Comparison};
+use crate::interventions::registry::{self, CreateIntervention, Intervention};
+use crate::sensor::Domain;
+use crate::series;
+use crate::time_utils::parse_duration;
+
+/// Comparison window used when the query does not give one.
+const DEFAULT_WINDOW: &str = "7d";
+/// Longest comparison window; each side is read into memory in full.
+const MAX_WINDOW_DAYS: i64 = 28;
+
+#[derive(Deserialize)]
+pub struct ComparisonQuery {
+    /// Length of each side, such as `14d`. Defaults to seven days.
+    pub window: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ComparisonResponse {
+    intervention: Intervention,
+    #[serde(flatten)]
+    comparison: Comparison,
+}
+
+/// Returns a new `Router` with the intervention endpoints:
+///
+/// * `GET /api/interventions`: list interventions.
+/// * `POST /api/interventions`: record an intervention.
+/// * `GET /api/interventions/:id`: fetch an intervention.
+/// * `DELETE /api/interventions/:id`: remove an intervention.
+/// * `GET /api/interventions/:id/comparison?window`: before/after noise comparison report.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(
+            INTERVENTIONS_PATH,
+            get(list_interventions).post(create_intervention),
+        )
+        .route(
+            INTERVENTION_PATH,
+            get(get_intervention).delete(delete_intervention),
+        )
+        .route(INTERVENTION_COMPARISON_PATH, get(get_comparison))
+        .with_state(state)
+}
+
+async fn list_interventions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Intervention>>, Response> {
+    registry::list_interventions(&state.redis)
+        .await
+        .map(Json)
+        .map_err(|e| log_and_response(ERR_INTERVENTION_READ, e))
+}
+
+async fn create_intervention(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateIntervention>,
+) -> Result<(StatusCode, Json<Intervention>), Response> {
+    if payload.device_id.is_empty() {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+
+    let intervention = Intervention::from_create(payload, chrono::Utc::now());
+    registry::save_intervention(&state.redis, &intervention)
+        .await
+        .map_err(|e| log_and_response(ERR_INTERVENTION_WRITE, e))?;
+
+    Ok((StatusCode::CREATED, Json(intervention)))
+}
+
+async fn get_intervention(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Intervention>, Response> {
+    match registry::get_intervention(&state.redis, &id).await {
+        Ok(Some(intervention)) => Ok(Json(intervention)),
+        Ok(None) => Err(StatusCode::NOT_FOUND.into_response()),
+        Err(e) => Err(log_and_response(ERR_INTERVENTION_READ, e)),
+    }
+}
+
+async fn delete_intervention(State(state): State<Arc<AppState>>, Path(id): Path<Uuid>) -> Response {
+    match registry::delete_intervention(&state.redis, &id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => log_and_response(ERR_INTERVENTION_DELETE, e),
+    }
+}
+
+async fn get_comparison(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ComparisonQuery>,
+) -> Response {
+    let window = match parse_duration(query.window.as_deref().unwrap_or(DEFAULT_WINDOW)) {
+        Ok(window) => window,
+        Err(msg) => return (StatusCode::BAD_REQUEST, msg).into_response(),
+    };
+    let window = match chrono::Duration::from_std(window) {
+        Ok(window) if window > chrono::Duration::zero() => window,
+        _ => return (StatusCode::BAD_REQUEST, "window must be greater than zero").into_response(),
+    };
+    if window > chrono::Duration::days(MAX_WINDOW_DAYS) {
+        let msg = format!("window must not exceed {MAX_WINDOW_DAYS} days");
+        return (StatusCode::BAD_REQUEST, msg).into_response();
+    }
+
+    let intervention = match registry::get_intervention(&state.redis, &id).await {
+        Ok(Some(intervention)) => intervention,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return log_and_response(ERR_INTERVENTION_READ, e),
+    };
+
+    let domain = Domain::SoundPressureLevel.as_str_name();
+    let key = state.series_keys.series(&intervention.device_id, domain);
+    let (from, to) = (intervention.at - window, intervention.at + window);
+    let samples = match series::range(&state.redis, &key, from, to).await {
+        Ok(Some(samples)) => samples,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return log_and_response(ERR_ACOUSTICS_READ, e),
+    };
+
+    let comparison = comparison::compare(
+        &samples,
+        intervention.at,
+        window,
+        state.noise_periods.timezone,
+    );
+    Json(ComparisonResponse {
+        intervention,
+        comparison,
+    })
+    .into_response()
+}
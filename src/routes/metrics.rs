@@ -0,0 +1,39 @@
+use axum::{
+    Router,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::app_state::AppState;
+use crate::auth::api_key;
+use crate::consts::errors::{ERR_API_KEY_COUNT, ERR_METRICS_RENDER};
+use crate::consts::routes::METRICS_PATH;
+use crate::error_utils::log_and_response;
+use crate::metrics;
+
+/// Content type of the Prometheus text exposition format.
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Returns a new `Router` with the Prometheus scrape endpoint:
+///
+/// * `GET /metrics`: request, ingest, Redis and auth metrics in the text exposition format.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(METRICS_PATH, get(scrape))
+        .with_state(state)
+}
+
+async fn scrape(State(state): State<Arc<AppState>>) -> Response {
+    // A stale key count is better than failing the whole scrape.
+    if let Err(e) = api_key::record_key_counts(&state).await {
+        warn!(error = %e, "{ERR_API_KEY_COUNT}");
+    }
+    match metrics::render() {
+        Ok(body) => ([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], body).into_response(),
+        Err(e) => log_and_response(ERR_METRICS_RENDER, e),
+    }
+}
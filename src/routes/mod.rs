@@ -1,3 +1,13 @@
+pub mod acoustics;
+pub mod alerts;
 pub mod apikeys;
+pub mod config;
+pub mod devices;
+pub mod domains;
+pub mod export;
 pub mod health;
+pub mod import;
 pub mod ingest;
+pub mod interventions;
+pub mod metrics;
+pub mod stream;
@@ -0,0 +1,180 @@
+use axum::{
+    Router,
+    extract::{
+        Query, State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+
+use crate::app_state::AppState;
+use crate::consts::routes::{STREAM_PATH, STREAM_WS_PATH};
+use crate::events::SampleEvent;
+use crate::lifecycle::Lifecycle;
+
+/// Close reason sent to WebSocket clients when the server starts draining.
+const SHUTTING_DOWN_REASON: &str = "server shutting down";
+
+/// Optional filters on a live stream; a sample must match every one given.
+#[derive(Clone, Debug, Deserialize)]
+pub struct StreamQuery {
+    pub device_id: Option<String>,
+    pub domain: Option<String>,
+}
+
+impl StreamQuery {
+    pub fn matches(&self, event: &SampleEvent) -> bool {
+        self.device_id
+            .as_ref()
+            .is_none_or(|d| *d == event.device_id)
+            && self.domain.as_ref().is_none_or(|d| *d == event.domain)
+    }
+}
+
+/// Returns a new `Router` with the live sample endpoints:
+///
+/// * `GET /api/stream?device_id&domain`: Server-Sent Events, one `sample` event per sample.
+/// * `GET /api/stream/ws?device_id&domain`: WebSocket, one JSON text message per sample.
+///
+/// Both end once the server starts draining, so open streams do not hold up shutdown.
+pub fn routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route(STREAM_PATH, get(sse_stream))
+        .route(STREAM_WS_PATH, get(ws_stream))
+        .with_state(state)
+}
+
+async fn sse_stream(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StreamQuery>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    // A lagging client skips the samples it missed rather than being disconnected.
+    let samples = BroadcastStream::new(state.live.subscribe()).filter_map(move |received| {
+        let event = received.ok()?;
+        query
+            .matches(&event)
+            .then(|| Event::default().event("sample").json_data(&event))
+    });
+    Sse::new(until_draining(samples, state.lifecycle.clone())).keep_alive(KeepAlive::default())
+}
+
+/// Ends `stream` once the process starts draining.
+fn until_draining<S>(stream: S, lifecycle: Lifecycle) -> impl Stream<Item = S::Item>
+where
+    S: Stream,
+{
+    let drained = tokio_stream::once(()).then(move |()| {
+        let lifecycle = lifecycle.clone();
+        async move {
+            lifecycle.draining().await;
+            None
+        }
+    });
+    stream.map(Some).merge(drained).map_while(|item| item)
+}
+
+async fn ws_stream(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StreamQuery>,
+) -> Response {
+    let receiver = state.live.subscribe();
+    let lifecycle = state.lifecycle.clone();
+    ws.on_upgrade(move |socket| forward_samples(socket, receiver, query, lifecycle))
+        .into_response()
+}
+
+/// Sends matching samples until the client disconnects or the server starts draining. Messages
+/// from the client are ignored.
+async fn forward_samples(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<SampleEvent>,
+    query: StreamQuery,
+    lifecycle: Lifecycle,
+) {
+    loop {
+        tokio::select! {
+            () = lifecycle.draining() => {
+                let close = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: SHUTTING_DOWN_REASON.into(),
+                };
+                let _ = socket.send(Message::Close(Some(close))).await;
+                break;
+            }
+            received = receiver.recv() => match received {
+                Ok(event) if query.matches(&event) => {
+                    let Ok(text) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    fn event(device_id: &str, domain: &str) -> SampleEvent {
+        SampleEvent {
+            device_id: device_id.to_string(),
+            domain: domain.to_string(),
+            value: 55.0,
+            raw_value: None,
+            unit: "dB".to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn query_filters_by_device_and_domain() {
+        let all = StreamQuery {
+            device_id: None,
+            domain: None,
+        };
+        assert!(all.matches(&event("a", "SOUND_PRESSURE_LEVEL")));
+
+        let one = StreamQuery {
+            device_id: Some("a".to_string()),
+            domain: Some("SOUND_PRESSURE_LEVEL".to_string()),
+        };
+        assert!(one.matches(&event("a", "SOUND_PRESSURE_LEVEL")));
+        assert!(!one.matches(&event("b", "SOUND_PRESSURE_LEVEL")));
+        assert!(!one.matches(&event("a", "TEMPERATURE")));
+    }
+
+    #[tokio::test]
+    async fn streams_end_once_draining() {
+        let lifecycle = Lifecycle::new();
+        let stream = until_draining(tokio_stream::pending::<()>(), lifecycle.clone());
+        let collected = tokio::spawn(stream.collect::<Vec<_>>());
+
+        lifecycle.begin_drain();
+        let items = tokio::time::timeout(Duration::from_secs(1), collected)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(items.is_empty());
+    }
+}
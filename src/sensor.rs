@@ -10,6 +10,10 @@ pub struct SensorData {
     pub domain: i32,
     #[prost(bytes = "vec", tag = "4")]
     pub device_id: ::prost::alloc::vec::Vec<u8>,
+    /// Name of a domain registered in the server's domain catalog. When set, it takes precedence
+    /// over `domain`, which lets devices report custom domains without a schema change.
+    #[prost(string, tag = "5")]
+    pub domain_name: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -17,11 +21,94 @@ pub struct SensorDataBatch {
     #[prost(message, repeated, tag = "1")]
     pub samples: ::prost::alloc::vec::Vec<SensorData>,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PushResponse {
+    /// Samples stored.
+    #[prost(uint32, tag = "1")]
+    pub accepted: u32,
+    /// Samples kept on disk while Redis is unreachable, to be stored once it recovers.
+    #[prost(uint32, tag = "2")]
+    pub spooled: u32,
+    /// Samples not stored, such as out-of-range values or unknown domains, or ones the server was too
+    /// busy to take.
+    #[prost(message, repeated, tag = "3")]
+    pub rejected: ::prost::alloc::vec::Vec<RejectedSample>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectedSample {
+    /// Position of the sample in the batch or stream, from 0.
+    #[prost(uint32, tag = "1")]
+    pub index: u32,
+    #[prost(string, tag = "2")]
+    pub reason: ::prost::alloc::string::String,
+    /// Whether the sample may succeed if sent again later; the others never will.
+    #[prost(bool, tag = "3")]
+    pub retry: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryRequest {
+    #[prost(string, tag = "1")]
+    pub device_id: ::prost::alloc::string::String,
+    /// Name of a domain in the server's domain catalog, e.g. "TEMPERATURE".
+    #[prost(string, tag = "2")]
+    pub domain: ::prost::alloc::string::String,
+    /// Unix seconds, inclusive.
+    #[prost(int64, tag = "3")]
+    pub from: i64,
+    #[prost(int64, tag = "4")]
+    pub to: i64,
+    /// Most samples to return, oldest first; 0 returns up to the server's limit.
+    #[prost(uint32, tag = "5")]
+    pub limit: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QueryResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub points: ::prost::alloc::vec::Vec<Point>,
+    #[prost(string, tag = "2")]
+    pub unit: ::prost::alloc::string::String,
+    /// Whether more samples fall in the range than were returned; query again from one past the
+    /// last timestamp for the rest.
+    #[prost(bool, tag = "3")]
+    pub truncated: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Point {
+    /// Unix seconds.
+    #[prost(int64, tag = "1")]
+    pub timestamp: i64,
+    #[prost(double, tag = "2")]
+    pub value: f64,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum Domain {
     Unspecified = 0,
     SoundPressureLevel = 1,
+    /// Device health telemetry
+    ///
+    /// volts
+    BatteryVoltage = 2,
+    /// dBm
+    WifiRssi = 3,
+    /// seconds since boot
+    Uptime = 4,
+    /// bytes
+    FreeMemory = 5,
+    /// fraction of the unsent-sample buffer in use, 0.0-1.0
+    RingBufferFill = 6,
+    /// Environmental measurements
+    Temperature = 7,
+    Humidity = 8,
+    Pressure = 9,
+    Pm25 = 10,
+    Co2 = 11,
+    Illuminance = 12,
 }
 impl Domain {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -32,6 +119,17 @@ impl Domain {
         match self {
             Domain::Unspecified => "UNSPECIFIED",
             Domain::SoundPressureLevel => "SOUND_PRESSURE_LEVEL",
+            Domain::BatteryVoltage => "BATTERY_VOLTAGE",
+            Domain::WifiRssi => "WIFI_RSSI",
+            Domain::Uptime => "UPTIME",
+            Domain::FreeMemory => "FREE_MEMORY",
+            Domain::RingBufferFill => "RING_BUFFER_FILL",
+            Domain::Temperature => "TEMPERATURE",
+            Domain::Humidity => "HUMIDITY",
+            Domain::Pressure => "PRESSURE",
+            Domain::Pm25 => "PM2_5",
+            Domain::Co2 => "CO2",
+            Domain::Illuminance => "ILLUMINANCE",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -39,7 +137,278 @@ impl Domain {
         match value {
             "UNSPECIFIED" => Some(Self::Unspecified),
             "SOUND_PRESSURE_LEVEL" => Some(Self::SoundPressureLevel),
+            "BATTERY_VOLTAGE" => Some(Self::BatteryVoltage),
+            "WIFI_RSSI" => Some(Self::WifiRssi),
+            "UPTIME" => Some(Self::Uptime),
+            "FREE_MEMORY" => Some(Self::FreeMemory),
+            "RING_BUFFER_FILL" => Some(Self::RingBufferFill),
+            "TEMPERATURE" => Some(Self::Temperature),
+            "HUMIDITY" => Some(Self::Humidity),
+            "PRESSURE" => Some(Self::Pressure),
+            "PM2_5" => Some(Self::Pm25),
+            "CO2" => Some(Self::Co2),
+            "ILLUMINANCE" => Some(Self::Illuminance),
             _ => None,
         }
     }
 }
+/// Generated server implementations.
+pub mod sensor_ingest_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with SensorIngestServer.
+    #[async_trait]
+    pub trait SensorIngest: Send + Sync + 'static {
+        /// Stores every sample of a batch, answering once each is stored, spooled or refused.
+        async fn push(
+            &self,
+            request: tonic::Request<super::SensorDataBatch>,
+        ) -> std::result::Result<tonic::Response<super::PushResponse>, tonic::Status>;
+        /// Stores samples as they arrive on a long-lived stream, answering with the totals once the
+        /// client closes it.
+        async fn stream_samples(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::SensorData>>,
+        ) -> std::result::Result<tonic::Response<super::PushResponse>, tonic::Status>;
+        /// Reads stored samples of one device and domain.
+        async fn query(
+            &self,
+            request: tonic::Request<super::QueryRequest>,
+        ) -> std::result::Result<tonic::Response<super::QueryResponse>, tonic::Status>;
+    }
+    /// Ingest and query over gRPC, for gateways that keep a connection open rather than POST each
+    /// sample. Calls authenticate like HTTP: an `authorization: SignalStash <key>` metadata entry, or
+    /// for Push and StreamSamples a verified client certificate naming the device.
+    #[derive(Debug)]
+    pub struct SensorIngestServer<T: SensorIngest> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: SensorIngest> SensorIngestServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for SensorIngestServer<T>
+    where
+        T: SensorIngest,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/sensor.SensorIngest/Push" => {
+                    #[allow(non_camel_case_types)]
+                    struct PushSvc<T: SensorIngest>(pub Arc<T>);
+                    impl<T: SensorIngest> tonic::server::UnaryService<super::SensorDataBatch> for PushSvc<T> {
+                        type Response = super::PushResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SensorDataBatch>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as SensorIngest>::push(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PushSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sensor.SensorIngest/StreamSamples" => {
+                    #[allow(non_camel_case_types)]
+                    struct StreamSamplesSvc<T: SensorIngest>(pub Arc<T>);
+                    impl<T: SensorIngest> tonic::server::ClientStreamingService<super::SensorData>
+                        for StreamSamplesSvc<T>
+                    {
+                        type Response = super::PushResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::SensorData>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SensorIngest>::stream_samples(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamSamplesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sensor.SensorIngest/Query" => {
+                    #[allow(non_camel_case_types)]
+                    struct QuerySvc<T: SensorIngest>(pub Arc<T>);
+                    impl<T: SensorIngest> tonic::server::UnaryService<super::QueryRequest> for QuerySvc<T> {
+                        type Response = super::QueryResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::QueryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as SensorIngest>::query(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = QuerySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: SensorIngest> Clone for SensorIngestServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: SensorIngest> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: SensorIngest> tonic::server::NamedService for SensorIngestServer<T> {
+        const NAME: &'static str = "sensor.SensorIngest";
+    }
+}
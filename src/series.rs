@@ -0,0 +1,263 @@
+use chrono::{DateTime, TimeZone, Utc};
+use redis::{AsyncCommands, Value};
+
+use crate::consts::redis::{
+    REDIS_CMD_TS_GET, REDIS_CMD_TS_RANGE, REDIS_COUNT_LABEL, REDIS_LABEL_AGGREGATION,
+    REDIS_LABEL_DEVICE_ID, REDIS_LABEL_DOMAIN, REDIS_LABEL_KIND, REDIS_LABEL_UNIT,
+    REDIS_LABEL_VARIANT, REDIS_LABELS_LABEL, REDIS_RETENTION_LABEL,
+};
+use crate::domains::DomainSpec;
+use crate::redis::RedisStore;
+
+/// Value of the `variant` label on the uncalibrated copy of a calibrated series.
+pub const VARIANT_RAW: &str = "raw";
+
+/// Names the RedisTimeSeries keys a device's series are stored under, `<prefix>:<device_id>:...`.
+///
+/// With `hash_tags` the device ID is wrapped in a hash tag (`<prefix>:{<device_id>}:...`), so on
+/// Redis Cluster every series of a device lands in the same slot and the pipelines writing them
+/// stay on one node. Turning it on renames every series, so it is required on Cluster but off by
+/// default elsewhere.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeriesKeys {
+    pub prefix: String,
+    pub hash_tags: bool,
+}
+
+impl SeriesKeys {
+    /// The key a device's samples for one domain are stored under.
+    pub fn series(&self, device_id: &str, domain: &str) -> String {
+        format!("{}:{domain}", self.device(device_id))
+    }
+
+    /// The key for the uncalibrated copy of a series, kept when a calibration asks for it.
+    pub fn raw_series(&self, device_id: &str, domain: &str) -> String {
+        format!("{}:raw", self.series(device_id, domain))
+    }
+
+    /// The key for a device's daily acoustic indicator series, e.g. `lden`.
+    pub fn daily_indicator(&self, device_id: &str, metric: &str) -> String {
+        format!("{}:daily:{metric}", self.device(device_id))
+    }
+
+    fn device(&self, device_id: &str) -> String {
+        if self.hash_tags {
+            format!("{}:{}", self.prefix, hash_tag(device_id))
+        } else {
+            format!("{}:{device_id}", self.prefix)
+        }
+    }
+}
+
+/// The hash tag that places keys in a device's cluster slot.
+pub fn hash_tag(device_id: &str) -> String {
+    format!("{{{device_id}}}")
+}
+
+/// Appends the retention and labels a sample series is created with to a command that may create
+/// it, such as `TS.ADD` or `TS.CREATE`. `variant` labels secondary copies such as the
+/// uncalibrated `raw` series.
+pub fn append_series_options(
+    cmd: &mut redis::Cmd,
+    spec: &DomainSpec,
+    device_id: &str,
+    variant: Option<&str>,
+) {
+    if let Some(retention_ms) = spec.retention_ms {
+        cmd.arg(REDIS_RETENTION_LABEL).arg(retention_ms);
+    }
+    cmd.arg(REDIS_LABELS_LABEL)
+        .arg(REDIS_LABEL_DEVICE_ID)
+        .arg(device_id)
+        .arg(REDIS_LABEL_DOMAIN)
+        .arg(&spec.name)
+        .arg(REDIS_LABEL_KIND)
+        .arg(spec.kind.as_str())
+        .arg(REDIS_LABEL_AGGREGATION)
+        .arg(spec.aggregation.as_str());
+    if !spec.unit.is_empty() {
+        cmd.arg(REDIS_LABEL_UNIT).arg(&spec.unit);
+    }
+    if let Some(variant) = variant {
+        cmd.arg(REDIS_LABEL_VARIANT).arg(variant);
+    }
+}
+
+/// Converts a wall-clock time to the timestamp unit samples are stored with.
+///
+/// Ingest currently stores the server receive time in whole seconds (see the TODO in
+/// `ingest::sample::store_sample`), so every read and write goes through this pair of functions.
+pub fn to_series_timestamp(at: DateTime<Utc>) -> i64 {
+    at.timestamp()
+}
+
+/// Converts a stored sample timestamp back to wall-clock time.
+pub fn from_series_timestamp(timestamp: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(timestamp, 0).single()
+}
+
+/// A single stored sample.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// Reads every sample of a series between `from` and `to`, inclusive.
+///
+/// Returns `None` if the series does not exist.
+pub async fn range(
+    redis: &RedisStore,
+    key: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> anyhow::Result<Option<Vec<Sample>>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let exists: bool = conn.exists(key).await?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let value: Value = redis::cmd(REDIS_CMD_TS_RANGE)
+        .arg(key)
+        .arg(to_series_timestamp(from))
+        .arg(to_series_timestamp(to))
+        .query_async(&mut conn)
+        .await?;
+    parse_samples(&value).map(Some)
+}
+
+/// Reads the newest sample of a series.
+///
+/// Returns `None` if the series does not exist or holds no samples.
+pub async fn latest(redis: &RedisStore, key: &str) -> anyhow::Result<Option<Sample>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let exists: bool = conn.exists(key).await?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let value: Value = redis::cmd(REDIS_CMD_TS_GET)
+        .arg(key)
+        .query_async(&mut conn)
+        .await?;
+    match value {
+        Value::Bulk(sample) if sample.is_empty() => Ok(None),
+        sample => Ok(parse_samples(&Value::Bulk(vec![sample]))?.pop()),
+    }
+}
+
+/// Reads up to `count` samples of a series with stored timestamps between `from` and `to`,
+/// inclusive, oldest first. Page through a long range by starting the next call one past the
+/// last timestamp returned.
+///
+/// Returns `None` if the series does not exist.
+pub async fn range_page(
+    redis: &RedisStore,
+    key: &str,
+    from: i64,
+    to: i64,
+    count: usize,
+) -> anyhow::Result<Option<Vec<Sample>>> {
+    let mut conn = redis.get_connection_manager().await?;
+    let exists: bool = conn.exists(key).await?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let value: Value = redis::cmd(REDIS_CMD_TS_RANGE)
+        .arg(key)
+        .arg(from)
+        .arg(to)
+        .arg(REDIS_COUNT_LABEL)
+        .arg(count)
+        .query_async(&mut conn)
+        .await?;
+    parse_samples(&value).map(Some)
+}
+
+/// Parses a `[[timestamp, "value"], ...]` reply as returned by `TS.RANGE`.
+pub fn parse_samples(value: &Value) -> anyhow::Result<Vec<Sample>> {
+    let pairs: Vec<Vec<Value>> = redis::from_redis_value(value)?;
+    let mut samples = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        let [timestamp, value] = pair.as_slice() else {
+            anyhow::bail!("unexpected sample in range reply: {pair:?}");
+        };
+        let timestamp: i64 = redis::from_redis_value(timestamp)?;
+        let value: String = redis::from_redis_value(value)?;
+        if let Some(timestamp) = from_series_timestamp(timestamp) {
+            samples.push(Sample {
+                timestamp,
+                value: value.parse()?,
+            });
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_key_format() {
+        let keys = SeriesKeys {
+            prefix: "signalstashrs".to_string(),
+            hash_tags: false,
+        };
+        assert_eq!(
+            keys.series("testdevice", "SOUND_PRESSURE_LEVEL"),
+            "signalstashrs:testdevice:SOUND_PRESSURE_LEVEL"
+        );
+        assert_eq!(
+            keys.raw_series("testdevice", "SOUND_PRESSURE_LEVEL"),
+            "signalstashrs:testdevice:SOUND_PRESSURE_LEVEL:raw"
+        );
+        assert_eq!(
+            keys.daily_indicator("testdevice", "lden"),
+            "signalstashrs:testdevice:daily:lden"
+        );
+    }
+
+    #[test]
+    fn hash_tagged_keys_share_the_device_slot() {
+        let keys = SeriesKeys {
+            prefix: "signalstashrs".to_string(),
+            hash_tags: true,
+        };
+        let series = keys.series("testdevice", "SOUND_PRESSURE_LEVEL");
+        assert_eq!(series, "signalstashrs:{testdevice}:SOUND_PRESSURE_LEVEL");
+
+        let slot = redis::cluster_routing::get_slot(hash_tag("testdevice").as_bytes());
+        for key in [
+            series,
+            keys.raw_series("testdevice", "SOUND_PRESSURE_LEVEL"),
+            keys.daily_indicator("testdevice", "lden"),
+        ] {
+            assert_eq!(redis::cluster_routing::get_slot(key.as_bytes()), slot);
+        }
+    }
+
+    #[test]
+    fn series_timestamp_round_trips() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(from_series_timestamp(to_series_timestamp(at)), Some(at));
+    }
+
+    #[test]
+    fn parse_samples_reads_pairs() {
+        let data = |s: &str| Value::Data(s.as_bytes().to_vec());
+        let reply = Value::Bulk(vec![
+            Value::Bulk(vec![Value::Int(1_735_732_800), data("55.5")]),
+            Value::Bulk(vec![Value::Int(1_735_732_801), data("60")]),
+        ]);
+        let samples = parse_samples(&reply).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(
+            samples[0].timestamp,
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()
+        );
+        assert_eq!(samples[1].value, 60.0);
+    }
+}
@@ -0,0 +1,330 @@
+pub mod replay;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::ingest::Sample;
+use crate::metrics;
+
+// Re-export commonly used items
+pub use replay::spawn_spool_replayer;
+
+/// Extension of segment files; anything else in the directory is left alone.
+const SEGMENT_EXTENSION: &str = "spool";
+
+/// Where samples are spooled while Redis is unreachable, and how much disk they may take.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpoolSettings {
+    pub dir: PathBuf,
+    /// Once the segments add up to this, further samples are refused.
+    pub max_bytes: u64,
+    /// A segment is closed and a new one started once it reaches this size.
+    pub segment_bytes: u64,
+}
+
+#[derive(Debug)]
+pub enum SpoolError {
+    /// The spool has reached its size limit.
+    Full {
+        max_bytes: u64,
+    },
+    Io(io::Error),
+}
+
+impl fmt::Display for SpoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full { max_bytes } => write!(f, "spool is full ({max_bytes} bytes)"),
+            Self::Io(e) => write!(f, "spool I/O failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SpoolError {}
+
+impl From<io::Error> for SpoolError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// An on-disk write-ahead log of samples accepted while Redis could not store them.
+///
+/// Samples are appended as JSON lines to numbered segment files, each flushed to disk before the
+/// append returns. The replayer drains whole segments oldest first and deletes each once every
+/// sample in it is stored, so samples are replayed in the order they arrived, and a crash part way
+/// through a segment replays it again rather than losing it.
+#[derive(Clone)]
+pub struct Spool {
+    settings: Arc<SpoolSettings>,
+    segments: Arc<Mutex<Segments>>,
+}
+
+struct Segments {
+    /// Oldest first.
+    files: VecDeque<Segment>,
+    /// Open for appends to the newest segment; `None` once that segment is sealed.
+    writer: Option<File>,
+    next_seq: u64,
+    total_bytes: u64,
+}
+
+struct Segment {
+    seq: u64,
+    bytes: u64,
+}
+
+impl Spool {
+    /// Opens the spool in `settings.dir`, creating the directory if needed. Segments left by an
+    /// earlier run are kept for replay but never appended to.
+    pub fn open(settings: SpoolSettings) -> io::Result<Self> {
+        fs::create_dir_all(&settings.dir)?;
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&settings.dir)? {
+            let path = entry?.path();
+            if let Some(seq) = segment_seq(&path) {
+                files.push(Segment {
+                    seq,
+                    bytes: fs::metadata(&path)?.len(),
+                });
+            }
+        }
+        files.sort_by_key(|segment| segment.seq);
+
+        let segments = Segments {
+            next_seq: files.last().map_or(0, |segment| segment.seq + 1),
+            total_bytes: files.iter().map(|segment| segment.bytes).sum(),
+            files: files.into(),
+            writer: None,
+        };
+        segments.update_metrics();
+        Ok(Self {
+            settings: Arc::new(settings),
+            segments: Arc::new(Mutex::new(segments)),
+        })
+    }
+
+    /// Appends a sample, durably, without blocking the runtime.
+    pub async fn push(&self, sample: Sample) -> Result<(), SpoolError> {
+        let spool = self.clone();
+        tokio::task::spawn_blocking(move || spool.append(&sample))
+            .await
+            .map_err(|e| SpoolError::Io(io::Error::other(e)))?
+    }
+
+    /// Appends a sample and flushes it to disk, starting a new segment when the current one is
+    /// full.
+    pub fn append(&self, sample: &Sample) -> Result<(), SpoolError> {
+        let mut line = serde_json::to_vec(sample).map_err(io::Error::from)?;
+        line.push(b'\n');
+        let len = line.len() as u64;
+
+        let mut segments = self.lock();
+        if segments.total_bytes + len > self.settings.max_bytes {
+            return Err(SpoolError::Full {
+                max_bytes: self.settings.max_bytes,
+            });
+        }
+        let segment_bytes = self.settings.segment_bytes;
+        let mut writer = match (segments.writer.take(), segments.files.back()) {
+            (Some(writer), Some(newest))
+                if newest.bytes == 0 || newest.bytes + len <= segment_bytes =>
+            {
+                writer
+            }
+            _ => {
+                let seq = segments.next_seq;
+                let file = OpenOptions::new()
+                    .create_new(true)
+                    .append(true)
+                    .open(segment_path(&self.settings.dir, seq))?;
+                segments.next_seq += 1;
+                segments.files.push_back(Segment { seq, bytes: 0 });
+                file
+            }
+        };
+        let written = writer.write_all(&line).and_then(|()| writer.sync_data());
+        if let Err(e) = written {
+            // A partial line may have been written; the segment stays sealed so it ends there.
+            segments.update_metrics();
+            return Err(e.into());
+        }
+        segments.writer = Some(writer);
+        if let Some(newest) = segments.files.back_mut() {
+            newest.bytes += len;
+        }
+        segments.total_bytes += len;
+        segments.update_metrics();
+        Ok(())
+    }
+
+    /// The oldest segment waiting for replay, sealing it first if it is still being appended to
+    /// so it no longer changes.
+    pub fn oldest(&self) -> Option<u64> {
+        let mut segments = self.lock();
+        let oldest = segments.files.front()?.seq;
+        if segments.files.len() == 1 {
+            segments.writer = None;
+        }
+        Some(oldest)
+    }
+
+    /// Reads every sample in a segment. Lines that do not parse, such as one torn by a crash
+    /// mid-write, are logged and skipped.
+    pub fn read(&self, seq: u64) -> io::Result<Vec<Sample>> {
+        let contents = fs::read(segment_path(&self.settings.dir, seq))?;
+        let mut samples = Vec::new();
+        for (number, line) in contents.split(|&b| b == b'\n').enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_slice(line) {
+                Ok(sample) => samples.push(sample),
+                Err(e) => {
+                    tracing::warn!(segment = seq, line = number + 1, error = %e, "Skipped unreadable spooled sample");
+                }
+            }
+        }
+        Ok(samples)
+    }
+
+    /// Deletes a replayed segment.
+    pub fn remove(&self, seq: u64) -> io::Result<()> {
+        let mut segments = self.lock();
+        match fs::remove_file(segment_path(&self.settings.dir, seq)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        if let Some(index) = segments.files.iter().position(|s| s.seq == seq) {
+            let removed = segments.files.remove(index).map_or(0, |s| s.bytes);
+            segments.total_bytes -= removed;
+            if segments.files.is_empty() {
+                segments.writer = None;
+            }
+        }
+        segments.update_metrics();
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().files.is_empty()
+    }
+
+    /// Bytes waiting for replay, and the limit.
+    pub fn usage(&self) -> (u64, u64) {
+        (self.lock().total_bytes, self.settings.max_bytes)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Segments> {
+        self.segments.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Segments {
+    fn update_metrics(&self) {
+        metrics::set_spool_usage(self.total_bytes, self.files.len());
+    }
+}
+
+fn segment_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{seq:020}.{SEGMENT_EXTENSION}"))
+}
+
+fn segment_seq(path: &Path) -> Option<u64> {
+    if path.extension()? != SEGMENT_EXTENSION {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn sample(value: f64) -> Sample {
+        Sample {
+            device_id: "sensor-1".to_string(),
+            domain: "TEMPERATURE".to_string(),
+            value,
+            received_at: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+        }
+    }
+
+    fn open(dir: &Path, max_bytes: u64, segment_bytes: u64) -> Spool {
+        Spool::open(SpoolSettings {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            segment_bytes,
+        })
+        .unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spool-{name}-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn replays_segments_in_order_and_survives_reopening() {
+        let dir = temp_dir("order");
+        let spool = open(&dir, 1 << 20, 200);
+        for value in 0..6 {
+            spool.append(&sample(f64::from(value))).unwrap();
+        }
+
+        // Reopening keeps the segments but never appends to them.
+        let spool = open(&dir, 1 << 20, 200);
+        spool.append(&sample(6.0)).unwrap();
+
+        let mut replayed = Vec::new();
+        while let Some(seq) = spool.oldest() {
+            replayed.extend(spool.read(seq).unwrap().into_iter().map(|s| s.value));
+            spool.remove(seq).unwrap();
+        }
+        assert_eq!(replayed, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert!(spool.is_empty());
+        assert_eq!(spool.usage().0, 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_samples_beyond_the_limit() {
+        let dir = temp_dir("full");
+        let spool = open(&dir, 150, 1 << 20);
+        spool.append(&sample(1.0)).unwrap();
+        assert!(matches!(
+            spool.append(&sample(2.0)),
+            Err(SpoolError::Full { max_bytes: 150 })
+        ));
+
+        // Replaying frees the space.
+        let seq = spool.oldest().unwrap();
+        spool.remove(seq).unwrap();
+        spool.append(&sample(2.0)).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn skips_a_torn_last_line() {
+        let dir = temp_dir("torn");
+        let spool = open(&dir, 1 << 20, 1 << 20);
+        spool.append(&sample(1.0)).unwrap();
+        let seq = spool.oldest().unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(segment_path(&dir, seq))
+            .unwrap();
+        file.write_all(br#"{"device_id":"sensor-1","dom"#).unwrap();
+
+        let samples = spool.read(seq).unwrap();
+        assert_eq!(samples, vec![sample(1.0)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::Spool;
+use crate::app_state::AppState;
+use crate::consts::errors::{ERR_DEVICE_STATUS_UPDATE, ERR_SAMPLE_STREAM, ERR_SPOOL_REPLAY};
+use crate::consts::health::JOB_SPOOL_REPLAY;
+use crate::devices::status;
+use crate::events::stream;
+use crate::ingest::{self, IngestError, Source};
+use crate::metrics;
+
+/// How often the replayer looks for spooled samples.
+const REPLAY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Spawns a background task that drains the spool into RedisTimeSeries, oldest segment first,
+/// whenever Redis accepts writes again. Does nothing when spooling is disabled.
+pub fn spawn_spool_replayer(state: Arc<AppState>) -> Option<tokio::task::JoinHandle<()>> {
+    let spool = state.spool.clone()?;
+    state.jobs.register(JOB_SPOOL_REPLAY, Some(REPLAY_INTERVAL));
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(REPLAY_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let result = replay(&state, &spool).await;
+            state.jobs.record(JOB_SPOOL_REPLAY, &result);
+            if let Err(e) = result {
+                warn!(error = format!("{e:#}"), "{ERR_SPOOL_REPLAY}");
+            }
+        }
+    }))
+}
+
+/// Replays segments until the spool is empty or Redis becomes unreachable again, in which case the
+/// failed segment is kept and retried from its start on the next run. Samples Redis refuses are
+/// dropped.
+async fn replay(state: &AppState, spool: &Spool) -> anyhow::Result<()> {
+    while let Some(seq) = spool.oldest() {
+        let reader = spool.clone();
+        let samples = tokio::task::spawn_blocking(move || reader.read(seq)).await??;
+
+        let mut replayed = 0;
+        let mut dropped = 0;
+        let mut seen: HashMap<&str, (u64, DateTime<Utc>)> = HashMap::new();
+        for sample in &samples {
+            let event = match ingest::store_sample(state, sample, Source::Spool).await {
+                Ok(event) => event,
+                // Already logged and counted; retrying cannot change the outcome.
+                Err(IngestError::UnregisteredDevice | IngestError::Rejected(_)) => continue,
+                Err(IngestError::Redis {
+                    context,
+                    error,
+                    unreachable: true,
+                }) => return Err(error.context(context)),
+                // Redis would refuse the sample on every run, holding up the rest of the spool.
+                Err(IngestError::Redis { context, error, .. }) => {
+                    warn!(device_id = %sample.device_id, error = format!("{error:#}"), "{context}; dropped spooled sample");
+                    dropped += 1;
+                    continue;
+                }
+                // Replay writes directly rather than through the queue, so this cannot happen.
+                Err(IngestError::Busy | IngestError::Closed) => {
+                    anyhow::bail!("ingest write queue refused a replayed sample")
+                }
+            };
+            replayed += 1;
+            let device = seen
+                .entry(&sample.device_id)
+                .or_insert((0, sample.received_at));
+            device.0 += 1;
+            device.1 = device.1.max(sample.received_at);
+
+            if let Some(sample_stream) = &state.sample_stream
+                && let Err(e) = stream::publish(&state.redis, sample_stream, &event).await
+            {
+                warn!(device_id = %sample.device_id, error = %e, "{ERR_SAMPLE_STREAM}");
+            }
+        }
+
+        for (device_id, (samples, last_seen)) in seen {
+            if let Err(e) = record_replayed(state, device_id, samples, last_seen).await {
+                warn!(device_id = %device_id, error = %e, "{ERR_DEVICE_STATUS_UPDATE}");
+            }
+        }
+
+        let remover = spool.clone();
+        tokio::task::spawn_blocking(move || remover.remove(seq)).await??;
+        metrics::spool_samples("replayed", replayed);
+        metrics::spool_samples("dropped", dropped);
+        info!(
+            segment = seq,
+            samples = samples.len(),
+            replayed,
+            dropped,
+            "Replayed spooled samples"
+        );
+    }
+    Ok(())
+}
+
+/// Counts replayed samples towards a device's status without moving its last-seen time back past
+/// a sample stored directly since Redis recovered.
+async fn record_replayed(
+    state: &AppState,
+    device_id: &str,
+    samples: u64,
+    last_seen: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let last_seen = match status::get_activity(&state.redis, device_id).await? {
+        Some(activity) => activity.last_seen.max(last_seen),
+        None => last_seen,
+    };
+    status::record_samples(&state.redis, device_id, samples, last_seen).await
+}
@@ -0,0 +1,224 @@
+use axum::{extract::Request, http::HeaderMap, middleware::Next, response::Response};
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::fmt;
+use std::str::FromStr;
+use tracing::{Instrument, field};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::config::Settings;
+use crate::metrics;
+use crate::request_id;
+
+/// Path the OTLP/HTTP protocol accepts traces on, relative to the collector's base URL.
+const OTLP_TRACES_PATH: &str = "/v1/traces";
+/// Instrumentation scope spans are reported under.
+const TRACER_NAME: &str = "signalstashrs";
+
+/// How log lines are written to stdout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable single lines.
+    #[default]
+    Compact,
+    /// One JSON object per line, including the fields of the spans the event occurred in.
+    Json,
+}
+
+/// A `LOG_FORMAT` value other than `compact` or `json`.
+#[derive(Debug)]
+pub struct UnknownLogFormat(String);
+
+impl fmt::Display for UnknownLogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown log format {:?}, expected compact or json",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownLogFormat {}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Compact => "compact",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = UnknownLogFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "compact" | "text" => Ok(LogFormat::Compact),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(UnknownLogFormat(s.to_string())),
+        }
+    }
+}
+
+/// The process's tracing pipeline: log output, plus span export when a collector is configured.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+    log_filter: LogFilter,
+}
+
+/// Handle for swapping the log filter of the installed subscriber at runtime.
+#[derive(Clone)]
+pub struct LogFilter(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilter {
+    /// Applies `filter`, built with [`env_filter`], to every subsequent event.
+    pub fn apply(&self, filter: EnvFilter) -> anyhow::Result<()> {
+        self.0.reload(filter)?;
+        Ok(())
+    }
+}
+
+impl Telemetry {
+    /// Installs the global tracing subscriber and the W3C trace context propagator.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the OTLP exporter cannot be built or a global subscriber is already installed.
+    pub fn init(settings: &Settings) -> anyhow::Result<Self> {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let provider = settings
+            .otlp_endpoint
+            .as_deref()
+            .map(|endpoint| tracer_provider(endpoint, &settings.otel_service_name))
+            .transpose()?;
+        let otel_layer = provider
+            .as_ref()
+            .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer(TRACER_NAME)));
+
+        let (filter, handle) = reload::Layer::new(env_filter(settings)?);
+        let (compact, json) = match settings.log_format {
+            LogFormat::Compact => (
+                Some(
+                    tracing_subscriber::fmt::layer()
+                        .with_target(false)
+                        .compact(),
+                ),
+                None,
+            ),
+            LogFormat::Json => (None, Some(tracing_subscriber::fmt::layer().json())),
+        };
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(compact)
+            .with(json)
+            .with(otel_layer)
+            .try_init()?;
+        Ok(Self {
+            provider,
+            log_filter: LogFilter(handle),
+        })
+    }
+
+    pub fn log_filter(&self) -> LogFilter {
+        self.log_filter.clone()
+    }
+
+    /// Exports any spans still buffered. Call before the process exits.
+    pub fn shutdown(&self) {
+        if let Some(provider) = &self.provider
+            && let Err(e) = provider.shutdown()
+        {
+            tracing::warn!(error = %e, "Failed to flush traces on shutdown");
+        }
+    }
+}
+
+/// `RUST_LOG`-style directives when set, otherwise everything at `log_level` and above.
+pub fn env_filter(settings: &Settings) -> anyhow::Result<EnvFilter> {
+    Ok(match &settings.log_filter {
+        Some(directives) => EnvFilter::try_new(directives)?,
+        None => {
+            EnvFilter::default().add_directive(LevelFilter::from_level(settings.log_level).into())
+        }
+    })
+}
+
+/// Builds a tracer provider that batches spans to the OTLP/HTTP collector at `endpoint`, e.g.
+/// `http://otel-collector:4318`.
+pub fn tracer_provider(endpoint: &str, service_name: &str) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!(
+            "{}{OTLP_TRACES_PATH}",
+            endpoint.trim_end_matches('/')
+        ))
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// Reads propagation headers such as `traceparent` from a request.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+/// Middleware wrapping each request in a server span, continuing the caller's trace when the
+/// request carries a W3C `traceparent` header. The span carries the request ID, so every line
+/// logged while handling the request does too.
+pub async fn trace_http(req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = metrics::matched_route(&req);
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(req.headers())));
+
+    let span = tracing::info_span!(
+        "http_request",
+        request_id = field::Empty,
+        otel.name = %format!("{method} {route}"),
+        otel.kind = "server",
+        otel.status_code = field::Empty,
+        http.request.method = %method,
+        http.route = %route,
+        http.response.status_code = field::Empty,
+    );
+    if let Some(id) = request_id::current() {
+        span.record("request_id", id);
+    }
+    // Only fails if the span is disabled, in which case there is nothing to link.
+    let _ = span.set_parent(parent);
+
+    let response = next.run(req).instrument(span.clone()).await;
+
+    let status = response.status();
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+    response
+}
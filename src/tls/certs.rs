@@ -0,0 +1,151 @@
+use super::TlsSettings;
+use anyhow::Context;
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use rustls::sign::CertifiedKey;
+use rustls::{RootCertStore, ServerConfig};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+use crate::consts::errors::ERR_TLS_RELOAD;
+
+/// Hands out the current server certificate, which `spawn_reloader` swaps when its files change,
+/// so renewed certificates are picked up without dropping open connections.
+pub struct CertificateResolver {
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+}
+
+impl fmt::Debug for CertificateResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertificateResolver")
+            .finish_non_exhaustive()
+    }
+}
+
+impl CertificateResolver {
+    pub fn load(settings: &TlsSettings, provider: Arc<CryptoProvider>) -> anyhow::Result<Self> {
+        let key = load_certified_key(&settings.cert_path, &settings.key_path, &provider)?;
+        Ok(Self {
+            provider,
+            current: RwLock::new(Arc::new(key)),
+        })
+    }
+
+    /// Re-reads the certificate and key, keeping the current pair if the new one does not load.
+    pub fn reload(&self, settings: &TlsSettings) -> anyhow::Result<()> {
+        let key = load_certified_key(&settings.cert_path, &settings.key_path, &self.provider)?;
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(key);
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.current
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+}
+
+/// Builds the rustls configuration: HTTP/2 and HTTP/1.1 over the resolver's certificate and, with
+/// a client CA configured, optional client certificates. Clients without one still connect, so
+/// devices using API keys keep working; those that present one must chain to the CA.
+pub fn server_config(
+    settings: &TlsSettings,
+    resolver: Arc<CertificateResolver>,
+) -> anyhow::Result<ServerConfig> {
+    let provider = resolver.provider.clone();
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &settings.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(path)? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("invalid CA certificate in {}", path.display()))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder.with_cert_resolver(resolver);
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// Spawns a background task that reloads the certificate whenever its or the key's modification
+/// time changes, checking every `reload_interval`.
+pub fn spawn_reloader(
+    resolver: Arc<CertificateResolver>,
+    settings: TlsSettings,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut loaded = modified(&settings);
+        let mut ticker = tokio::time::interval(settings.reload_interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let current = modified(&settings);
+            if current == loaded {
+                continue;
+            }
+            match resolver.reload(&settings) {
+                Ok(()) => {
+                    info!(cert_path = %settings.cert_path.display(), "Reloaded TLS certificate");
+                    loaded = current;
+                }
+                // `loaded` is left alone so the next tick retries, e.g. after the key is written.
+                Err(e) => warn!(error = %e, "{ERR_TLS_RELOAD}"),
+            }
+        }
+    })
+}
+
+fn modified(settings: &TlsSettings) -> (Option<SystemTime>, Option<SystemTime>) {
+    let mtime = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    (mtime(&settings.cert_path), mtime(&settings.key_path))
+}
+
+fn load_certified_key(
+    cert_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> anyhow::Result<CertifiedKey> {
+    let certs = load_certs(cert_path)?;
+    let file =
+        File::open(key_path).with_context(|| format!("failed to open {}", key_path.display()))?;
+    let key: PrivateKeyDer<'static> = rustls_pemfile::private_key(&mut BufReader::new(file))?
+        .with_context(|| format!("no private key in {}", key_path.display()))?;
+    CertifiedKey::from_der(certs, key, provider).with_context(|| {
+        format!(
+            "{} does not match {}",
+            key_path.display(),
+            cert_path.display()
+        )
+    })
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid PEM in {}", path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificates in {}", path.display());
+    }
+    Ok(certs)
+}
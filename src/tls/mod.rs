@@ -0,0 +1,31 @@
+pub mod certs;
+pub mod server;
+
+use rustls::ServerConfig;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Re-export commonly used items
+pub use certs::{CertificateResolver, server_config, spawn_reloader};
+pub use server::serve;
+
+/// Where the server certificate, its key and, for mutual TLS, the client CA bundle are read from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsSettings {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// CA bundle client certificates are verified against; `None` does not ask clients for one.
+    pub client_ca_path: Option<PathBuf>,
+    /// How often the certificate and key files are checked for changes.
+    pub reload_interval: Duration,
+}
+
+/// Loads the certificate, key and client CA and builds the server configuration around a resolver
+/// that `spawn_reloader` can refresh.
+pub fn load(settings: &TlsSettings) -> anyhow::Result<(Arc<CertificateResolver>, ServerConfig)> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let resolver = Arc::new(CertificateResolver::load(settings, provider)?);
+    let config = server_config(settings, resolver.clone())?;
+    Ok((resolver, config))
+}
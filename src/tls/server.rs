@@ -0,0 +1,108 @@
+use axum::Router;
+use axum::http::Request;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use rustls::ServerConfig;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+use tower::ServiceExt;
+use tracing::{debug, warn};
+
+use crate::auth::client_cert::ClientCertificate;
+use crate::consts::errors::{ERR_TLS_ACCEPT, ERR_TLS_HANDSHAKE};
+
+/// Connections that have not finished the handshake by then are dropped, so idle sockets cannot
+/// pile up.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves `router` over TLS until `signal` resolves, then stops accepting and waits for open
+/// connections to finish their in-flight requests, like `axum::serve(..).with_graceful_shutdown`.
+///
+/// A verified client certificate is attached to each of its connection's requests as a
+/// [`ClientCertificate`] extension.
+pub async fn serve<F>(
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    router: Router,
+    signal: F,
+) -> std::io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let acceptor = TlsAcceptor::from(config);
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(signal);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "{ERR_TLS_ACCEPT}");
+                    continue;
+                }
+            },
+            () = &mut signal => break,
+        };
+
+        let acceptor = acceptor.clone();
+        let router = router.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let Some((stream, certificate)) = handshake(&acceptor, stream, peer).await else {
+                return;
+            };
+
+            let service = hyper::service::service_fn(move |mut req: Request<Incoming>| {
+                if let Some(certificate) = &certificate {
+                    req.extensions_mut().insert(certificate.clone());
+                }
+                router.clone().oneshot(req)
+            });
+            let connection = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .into_owned();
+            if let Err(e) = watcher.watch(connection).await {
+                debug!(peer = %peer, error = %e, "Connection closed with an error");
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    Ok(())
+}
+
+/// Completes the TLS handshake within [`HANDSHAKE_TIMEOUT`], returning the stream and the client
+/// certificate it verified, if any. Failures are logged at debug level, as clients cause most.
+pub async fn handshake(
+    acceptor: &TlsAcceptor,
+    stream: TcpStream,
+    peer: SocketAddr,
+) -> Option<(TlsStream<TcpStream>, Option<ClientCertificate>)> {
+    let stream = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            debug!(peer = %peer, error = %e, "{ERR_TLS_HANDSHAKE}");
+            return None;
+        }
+        Err(_) => {
+            debug!(peer = %peer, "{ERR_TLS_HANDSHAKE}: timed out");
+            return None;
+        }
+    };
+    let certificate = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|chain| chain.first())
+        .and_then(ClientCertificate::from_der);
+    Some((stream, certificate))
+}
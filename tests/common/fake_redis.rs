@@ -0,0 +1,97 @@
+//! A stand-in Redis server that answers each command with whatever the test says, for exercising
+//! failures a real Redis will not produce on demand.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const OK: &str = "+OK\r\n";
+pub const NIL: &str = "$-1\r\n";
+
+/// Decides the raw RESP reply to a command, or `None` to stop answering that connection.
+pub type Handler = dyn Fn(&[String]) -> Option<String> + Send + Sync;
+
+pub struct FakeRedis {
+    pub url: String,
+    commands: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl FakeRedis {
+    /// Listens on a free local port until the test ends.
+    pub async fn start(
+        handler: impl Fn(&[String]) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+        let log = commands.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, handler.clone(), log.clone()));
+            }
+        });
+        Self { url, commands }
+    }
+
+    /// Every command received so far, oldest first.
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.commands
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// The received commands named `name`, e.g. `TS.ADD`.
+    pub fn commands_named(&self, name: &str) -> Vec<Vec<String>> {
+        self.commands()
+            .into_iter()
+            .filter(|command| command[0].eq_ignore_ascii_case(name))
+            .collect()
+    }
+}
+
+/// An error reply.
+pub fn error(message: &str) -> String {
+    format!("-{message}\r\n")
+}
+
+async fn serve(stream: TcpStream, handler: Arc<Handler>, log: Arc<Mutex<Vec<Vec<String>>>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(command) = read_command(&mut reader).await {
+        log.lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(command.clone());
+        match handler(&command) {
+            Some(reply) => {
+                if writer.write_all(reply.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+            None => return std::future::pending().await,
+        }
+    }
+}
+
+/// Reads one command, sent as an array of bulk strings. `None` once the client hangs up.
+async fn read_command<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Option<Vec<String>> {
+    let count: usize = read_line(reader).await?.strip_prefix('*')?.parse().ok()?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len: usize = read_line(reader).await?.strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        args.push(String::from_utf8_lossy(&arg).into_owned());
+    }
+    Some(args)
+}
+
+async fn read_line<R: AsyncBufReadExt + Unpin>(reader: &mut R) -> Option<String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim_end().to_string()),
+    }
+}
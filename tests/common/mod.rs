@@ -0,0 +1,69 @@
+//! Fixtures shared by the integration tests. Each test crate uses only part of them.
+#![allow(dead_code)]
+
+pub mod fake_redis;
+
+use signalstashrs::acoustics::NoisePeriods;
+use signalstashrs::alerts::AlertingPolicy;
+use signalstashrs::app_state::AppState;
+use signalstashrs::config::ActiveConfig;
+use signalstashrs::devices::LivenessPolicy;
+use signalstashrs::domains::DomainCatalog;
+use signalstashrs::events::LiveFeed;
+use signalstashrs::health::JobMonitor;
+use signalstashrs::ingest::{WriteQueue, WriteQueueSettings};
+use signalstashrs::lifecycle::Lifecycle;
+use signalstashrs::redis::RedisStore;
+use signalstashrs::series::SeriesKeys;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The Redis the tests that need one run against: `REDIS_URL`, or one on localhost.
+pub fn redis_url() -> String {
+    std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string())
+}
+
+/// [`app_state`] on the Redis at [`redis_url`], with a small write queue that flushes quickly.
+pub async fn test_app_state() -> Arc<AppState> {
+    let redis = Arc::new(RedisStore::new(&redis_url()).await.unwrap());
+    let write_queue = WriteQueue::start(
+        redis.clone(),
+        &WriteQueueSettings {
+            depth: 16,
+            writers: 1,
+            batch_size: 16,
+            flush_interval: Duration::from_millis(5),
+        },
+    );
+    Arc::new(app_state(redis, write_queue))
+}
+
+/// The state the server would run with, reading and writing through `redis` except for the
+/// samples `write_queue` stores.
+pub fn app_state(redis: Arc<RedisStore>, write_queue: WriteQueue) -> AppState {
+    AppState {
+        alerting: AlertingPolicy {
+            evaluation_interval: Duration::from_secs(30),
+            webhook_max_attempts: 5,
+        },
+        config: ActiveConfig::default(),
+        domains: Arc::new(DomainCatalog::with_builtins()),
+        jobs: JobMonitor::default(),
+        lifecycle: Lifecycle::new(),
+        live: LiveFeed::new(None),
+        liveness: LivenessPolicy {
+            expected_batch_interval: Duration::from_secs(60),
+            offline_after_intervals: 5,
+        },
+        noise_periods: NoisePeriods::default(),
+        redis,
+        reject_unregistered_devices: false,
+        sample_stream: None,
+        series_keys: SeriesKeys {
+            prefix: "test-prefix".to_string(),
+            hash_tags: false,
+        },
+        spool: None,
+        write_queue,
+    }
+}
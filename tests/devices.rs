@@ -0,0 +1,71 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::fake_redis::{FakeRedis, OK};
+use signalstashrs::ingest::{WriteQueue, WriteQueueSettings};
+use signalstashrs::redis::RedisStore;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower::util::ServiceExt;
+
+/// A Redis that registers each key once, as `SETNX` would.
+async fn registry() -> Arc<RedisStore> {
+    let keys = Mutex::new(HashSet::new());
+    let redis = FakeRedis::start(move |command| {
+        Some(match command[0].as_str() {
+            "SETNX" => format!(
+                ":{}\r\n",
+                u8::from(keys.lock().unwrap().insert(command[1].clone()))
+            ),
+            "SADD" => ":1\r\n".to_string(),
+            _ => OK.to_string(),
+        })
+    })
+    .await;
+    Arc::new(RedisStore::new(&redis.url).await.unwrap())
+}
+
+fn register(device_id: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/api/devices")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            serde_json::json!({ "device_id": device_id, "name": "Back fence" }).to_string(),
+        ))
+        .unwrap()
+}
+
+async fn app() -> axum::Router {
+    let redis = registry().await;
+    let write_queue = WriteQueue::start(
+        redis.clone(),
+        &WriteQueueSettings {
+            depth: 1,
+            writers: 1,
+            batch_size: 1,
+            flush_interval: Duration::from_millis(5),
+        },
+    );
+    signalstashrs::routes::devices::routes(Arc::new(common::app_state(redis, write_queue)))
+}
+
+#[tokio::test]
+async fn registering_a_taken_id_conflicts() {
+    let app = app().await;
+    let (first, second) = tokio::join!(
+        app.clone().oneshot(register("sensor-1")),
+        app.clone().oneshot(register("sensor-1"))
+    );
+    let mut statuses = [first.unwrap().status(), second.unwrap().status()];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::CREATED, StatusCode::CONFLICT]);
+}
+
+#[tokio::test]
+async fn registering_an_id_a_label_filter_cannot_match_is_refused() {
+    let response = app().await.oneshot(register("sensor(1)")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,255 @@
+mod common;
+
+use signalstashrs::app_state::AppState;
+use signalstashrs::auth::api_key::{API_KEY_FORMAT_PREFIX, API_KEY_PREFIX, generate_api_key};
+use signalstashrs::auth::client_cert::ClientCertificate;
+use signalstashrs::grpc::{PeerInfo, SensorIngestService};
+use signalstashrs::sensor::sensor_ingest_server::SensorIngest;
+use signalstashrs::sensor::{PushResponse, QueryRequest, SensorData, SensorDataBatch};
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+use tonic::{Code, Request, Status};
+
+const TIMESTAMP: i64 = 1_735_732_800;
+
+async fn test_service() -> SensorIngestService {
+    SensorIngestService::new(common::test_app_state().await)
+}
+
+/// Stores a standard API key, which authenticates every call.
+async fn api_key(state: &AppState) -> String {
+    let key = generate_api_key(API_KEY_FORMAT_PREFIX);
+    let mut conn = state.redis.get_connection_manager().await.unwrap();
+    redis::cmd("SET")
+        .arg(format!("{API_KEY_PREFIX}{key}"))
+        .arg("grpc-tests")
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .unwrap();
+    key
+}
+
+fn authorized<T>(message: T, key: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert(
+        "authorization",
+        format!("SignalStash {key}").parse().unwrap(),
+    );
+    request
+}
+
+/// A device ID no other test run has written to.
+fn new_device() -> String {
+    format!("grpc-test-{}", uuid::Uuid::new_v4())
+}
+
+fn sample(device_id: &str, domain_name: &str, datum: f32) -> SensorData {
+    SensorData {
+        datum,
+        device_id: device_id.as_bytes().to_vec(),
+        domain_name: domain_name.to_string(),
+        ..Default::default()
+    }
+}
+
+fn query(device_id: &str, from: i64, to: i64, limit: u32) -> QueryRequest {
+    QueryRequest {
+        device_id: device_id.to_string(),
+        domain: "TEMPERATURE".to_string(),
+        from,
+        to,
+        limit,
+    }
+}
+
+fn batch(device_id: &str) -> SensorDataBatch {
+    SensorDataBatch {
+        samples: vec![sample(device_id, "TEMPERATURE", 21.5)],
+    }
+}
+
+fn certified(request: &mut Request<SensorDataBatch>, device_id: &str) {
+    request.extensions_mut().insert(PeerInfo {
+        remote_addr: "127.0.0.1:50000".parse().unwrap(),
+        certificate: Some(ClientCertificate {
+            device_id: device_id.to_string(),
+        }),
+    });
+}
+
+#[tokio::test]
+async fn push_without_api_key_is_unauthenticated() {
+    let service = test_service().await;
+    let status = service
+        .push(Request::new(batch("sensor-1")))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(status.message(), "missing_header");
+}
+
+#[tokio::test]
+async fn push_with_other_scheme_is_unauthenticated() {
+    let service = test_service().await;
+    let mut request = Request::new(batch("sensor-1"));
+    request
+        .metadata_mut()
+        .insert("authorization", "Bearer sk-sigstash-x".parse().unwrap());
+    let status = service.push(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(status.message(), "malformed_header");
+}
+
+#[tokio::test]
+async fn push_refuses_samples_for_another_device_than_the_certificate() {
+    let service = test_service().await;
+    let mut request = Request::new(batch("sensor-2"));
+    certified(&mut request, "sensor-1");
+    let status = service.push(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn query_does_not_accept_a_client_certificate() {
+    let service = test_service().await;
+    let mut request = Request::new(query("sensor-1", 0, 60, 0));
+    request.extensions_mut().insert(PeerInfo {
+        remote_addr: "127.0.0.1:50000".parse().unwrap(),
+        certificate: Some(ClientCertificate {
+            device_id: "sensor-1".to_string(),
+        }),
+    });
+    let status = service.query(request).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn push_stores_samples_and_rejects_the_rest_by_position() {
+    let state = common::test_app_state().await;
+    let service = SensorIngestService::new(state.clone());
+    let key = api_key(&state).await;
+    let (first, second) = (new_device(), new_device());
+    let mut invalid = sample("", "TEMPERATURE", 20.0);
+    invalid.device_id = vec![0xff, 0xfe];
+
+    let batch = SensorDataBatch {
+        samples: vec![
+            sample(&first, "TEMPERATURE", 21.5),
+            invalid,
+            sample(&second, "TEMPERATURE", 22.5),
+        ],
+    };
+    let response = service
+        .push(authorized(batch, &key))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.accepted, 2);
+    assert_eq!(response.spooled, 0);
+    assert_eq!(response.rejected.len(), 1);
+    assert_eq!(response.rejected[0].index, 1);
+    assert!(!response.rejected[0].retry);
+
+    let now = chrono::Utc::now().timestamp();
+    for (device_id, datum) in [(&first, 21.5), (&second, 22.5)] {
+        let points = service
+            .query(authorized(query(device_id, 0, now + 60, 0), &key))
+            .await
+            .unwrap()
+            .into_inner()
+            .points;
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].value, datum);
+    }
+}
+
+#[tokio::test]
+async fn stream_samples_tallies_every_sample() {
+    let state = common::test_app_state().await;
+    let key = api_key(&state).await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(signalstashrs::grpc::serve(
+        state,
+        listener,
+        None,
+        std::future::pending(),
+    ));
+
+    let samples = vec![
+        sample(&new_device(), "TEMPERATURE", 21.5),
+        sample(&new_device(), "NOT_A_DOMAIN", 1.0),
+        sample(&new_device(), "TEMPERATURE", 22.5),
+    ];
+    let response = stream_samples(&addr.to_string(), &key, samples)
+        .await
+        .unwrap();
+    assert_eq!(response.accepted, 2);
+    assert_eq!(response.rejected.len(), 1);
+    assert_eq!(response.rejected[0].index, 1);
+}
+
+/// Calls `StreamSamples` over a connection, as the service has no generated client.
+async fn stream_samples(
+    addr: &str,
+    key: &str,
+    samples: Vec<SensorData>,
+) -> Result<PushResponse, Status> {
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await.unwrap();
+    let request = authorized(tokio_stream::iter(samples), key);
+    let path = PathAndQuery::from_static("/sensor.SensorIngest/StreamSamples");
+    grpc.client_streaming(request, path, ProstCodec::default())
+        .await
+        .map(tonic::Response::into_inner)
+}
+
+#[tokio::test]
+async fn query_returns_points_in_unix_seconds_and_sets_truncated() {
+    let state = common::test_app_state().await;
+    let service = SensorIngestService::new(state.clone());
+    let key = api_key(&state).await;
+    let device_id = new_device();
+
+    let series = state.series_keys.series(&device_id, "TEMPERATURE");
+    let mut conn = state.redis.get_connection_manager().await.unwrap();
+    for minute in 0..3 {
+        redis::cmd("TS.ADD")
+            .arg(&series)
+            .arg(TIMESTAMP + minute * 60)
+            .arg(20.0 + minute as f64)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .unwrap();
+    }
+
+    let all = service
+        .query(authorized(
+            query(&device_id, TIMESTAMP, TIMESTAMP + 120, 0),
+            &key,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    let timestamps: Vec<_> = all.points.iter().map(|p| p.timestamp).collect();
+    assert_eq!(timestamps, [TIMESTAMP, TIMESTAMP + 60, TIMESTAMP + 120]);
+    assert!(!all.truncated);
+
+    let page = service
+        .query(authorized(
+            query(&device_id, TIMESTAMP, TIMESTAMP + 120, 2),
+            &key,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(page.points.len(), 2);
+    assert_eq!(page.points[1].value, 21.0);
+    assert!(page.truncated);
+}
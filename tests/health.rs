@@ -1,22 +1,13 @@
+mod common;
+
 use axum::{
     body::{Body, to_bytes},
     http::{Request, StatusCode},
 };
-use signalstashrs::app_state::AppState;
-use signalstashrs::redis::RedisStore;
-use std::sync::Arc;
+use common::test_app_state;
+use std::time::Duration;
 use tower::util::ServiceExt;
 
-async fn test_app_state() -> Arc<AppState> {
-    let redis_url =
-        std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string());
-    let redis = RedisStore::new(&redis_url).await.unwrap();
-    Arc::new(AppState {
-        sensor_datum_prefix: "test-prefix".to_string(),
-        redis: Arc::new(redis),
-    })
-}
-
 #[tokio::test]
 async fn healthz_returns_200() {
     let app = signalstashrs::routes::health::routes(test_app_state().await);
@@ -78,3 +69,72 @@ async fn startz_returns_200() {
     let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
     assert_eq!(body, "started");
 }
+
+#[tokio::test]
+async fn readyz_returns_503_while_draining() {
+    let state = test_app_state().await;
+    state.lifecycle.begin_drain();
+    let app = signalstashrs::routes::health::routes(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/readyz")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(body, "shutting down");
+}
+
+#[tokio::test]
+async fn health_details_reports_every_check() {
+    let state = test_app_state().await;
+    state.lifecycle.begin_drain();
+    state
+        .jobs
+        .register("test_job", Some(Duration::from_secs(60)));
+    let app = signalstashrs::routes::health::details_routes(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/healthz/details")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let checks: Vec<&str> = report["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        checks,
+        [
+            "redis",
+            "timeseries_module",
+            "redis_memory",
+            "redis_persistence",
+            "shutdown",
+            "background_tasks",
+            "ingest_queue"
+        ]
+    );
+    // Draining fails the report whatever Redis says.
+    assert_eq!(report["status"], "failed");
+    assert_eq!(report["jobs"][0]["name"], "test_job");
+    assert_eq!(report["jobs"][0]["status"], "ok");
+}
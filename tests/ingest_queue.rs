@@ -0,0 +1,208 @@
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use common::fake_redis::{self, FakeRedis, NIL, OK};
+use prost::Message;
+use signalstashrs::domains::DomainCatalog;
+use signalstashrs::ingest::queue::{QueueError, SeriesWrite};
+use signalstashrs::ingest::{WriteQueue, WriteQueueSettings};
+use signalstashrs::redis::RedisStore;
+use signalstashrs::sensor::SensorData;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+use tower::util::ServiceExt;
+
+/// Long enough that a test passing means the interval did not trigger the flush.
+const NEVER: Duration = Duration::from_secs(3600);
+const TIMESTAMP: i64 = 1_735_732_800;
+
+async fn store(redis: &FakeRedis) -> Arc<RedisStore> {
+    Arc::new(RedisStore::new(&redis.url).await.unwrap())
+}
+
+fn settings(depth: usize, batch_size: usize, flush_interval: Duration) -> WriteQueueSettings {
+    WriteQueueSettings {
+        depth,
+        writers: 1,
+        batch_size,
+        flush_interval,
+    }
+}
+
+fn write(key: &str, value: f64) -> SeriesWrite {
+    SeriesWrite {
+        key: key.to_string(),
+        timestamp: TIMESTAMP,
+        value,
+        spec: DomainCatalog::with_builtins().get("TEMPERATURE").unwrap(),
+        device_id: "sensor-1".to_string(),
+        variant: None,
+    }
+}
+
+fn ingest_request() -> Request<Body> {
+    let data = SensorData {
+        datum: 21.5,
+        device_id: b"sensor-1".to_vec(),
+        domain_name: "TEMPERATURE".to_string(),
+        ..Default::default()
+    };
+    Request::builder()
+        .method("POST")
+        .uri("/ingest")
+        .header(header::CONTENT_TYPE, "application/x-protobuf")
+        .body(Body::from(data.encode_to_vec()))
+        .unwrap()
+}
+
+/// A Redis whose series keep the default BLOCK duplicate policy and refuse writes to "expired".
+async fn series_redis() -> FakeRedis {
+    let series = Mutex::new(HashMap::new());
+    FakeRedis::start(move |command| {
+        let mut series = series.lock().unwrap();
+        let key = command.get(1).cloned().unwrap_or_default();
+        Some(match command[0].as_str() {
+            "TS.ADD" if key == "expired" => {
+                fake_redis::error("ERR TSDB: Timestamp is older than retention")
+            }
+            "TS.ADD" if series.contains_key(&key) => fake_redis::error(
+                "ERR TSDB: Error at upsert, update is not supported when DUPLICATE_POLICY is set to BLOCK mode",
+            ),
+            "TS.ADD" => {
+                series.insert(key, command[3].clone());
+                format!(":{TIMESTAMP}\r\n")
+            }
+            "TS.RANGE" => match series.get(&key) {
+                Some(value) => format!(
+                    "*1\r\n*2\r\n:{TIMESTAMP}\r\n${}\r\n{value}\r\n",
+                    value.len()
+                ),
+                None => "*0\r\n".to_string(),
+            },
+            _ => OK.to_string(),
+        })
+    })
+    .await
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn writes_a_batch_once_it_is_full() {
+    let redis = FakeRedis::start(|_| Some(OK.to_string())).await;
+    let queue = WriteQueue::start(store(&redis).await, &settings(16, 2, NEVER));
+
+    let (first, second) = timeout(Duration::from_secs(5), async {
+        tokio::join!(
+            queue.write(vec![write("a", 21.5)]),
+            queue.write(vec![write("b", 21.5)])
+        )
+    })
+    .await
+    .expect("a full batch is written without waiting for the flush interval");
+    first.unwrap();
+    second.unwrap();
+    assert_eq!(redis.commands_named("TS.ADD").len(), 2);
+}
+
+#[tokio::test]
+async fn writes_a_partial_batch_after_the_flush_interval() {
+    let redis = FakeRedis::start(|_| Some(OK.to_string())).await;
+    let flush_interval = Duration::from_millis(200);
+    let queue = WriteQueue::start(store(&redis).await, &settings(16, 100, flush_interval));
+
+    let started = Instant::now();
+    timeout(Duration::from_secs(5), queue.write(vec![write("a", 21.5)]))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(started.elapsed() >= flush_interval);
+    assert_eq!(redis.commands_named("TS.ADD").len(), 1);
+}
+
+#[tokio::test]
+async fn refused_batch_is_retried_one_request_at_a_time() {
+    let redis = series_redis().await;
+    let queue = WriteQueue::start(store(&redis).await, &settings(16, 2, NEVER));
+
+    let (stored, expired) = tokio::join!(
+        queue.write(vec![write("stored", 21.5)]),
+        queue.write(vec![write("expired", 21.5)])
+    );
+    stored.unwrap();
+    assert!(matches!(
+        expired,
+        Err(QueueError::Redis {
+            unreachable: false,
+            ..
+        })
+    ));
+
+    // The pipeline stored the first write before Redis refused the second; the retry finds it
+    // there rather than overwriting it.
+    let writes = redis.commands_named("TS.ADD");
+    assert_eq!(writes.iter().filter(|c| c[1] == "stored").count(), 2);
+    assert!(!writes.iter().flatten().any(|arg| arg == "ON_DUPLICATE"));
+}
+
+#[tokio::test]
+async fn retry_does_not_overwrite_another_request_at_the_same_timestamp() {
+    let redis = series_redis().await;
+    let queue = WriteQueue::start(store(&redis).await, &settings(16, 2, NEVER));
+
+    let (first, second) = tokio::join!(
+        queue.write(vec![write("shared", 21.5)]),
+        queue.write(vec![write("shared", 22.5)])
+    );
+    first.unwrap();
+    assert!(matches!(
+        second,
+        Err(QueueError::Redis {
+            unreachable: false,
+            ..
+        })
+    ));
+    assert!(
+        !redis
+            .commands_named("TS.ADD")
+            .iter()
+            .flatten()
+            .any(|arg| arg == "ON_DUPLICATE")
+    );
+}
+
+#[tokio::test]
+async fn full_queue_answers_503_with_retry_after() {
+    let lookups =
+        FakeRedis::start(|command| Some(if command[0] == "GET" { NIL } else { OK }.to_string()))
+            .await;
+    // Never answers a write, so the writer stays busy with the first sample.
+    let writes =
+        FakeRedis::start(|command| (!command[0].starts_with("TS.")).then(|| OK.to_string())).await;
+    let write_queue = WriteQueue::start(store(&writes).await, &settings(1, 1, NEVER));
+    let state = Arc::new(common::app_state(store(&lookups).await, write_queue));
+    let app = signalstashrs::routes::ingest::routes(state.clone());
+
+    // The first sample occupies the only writer, the second the only slot in the queue.
+    let first = tokio::spawn(app.clone().oneshot(ingest_request()));
+    wait_until(|| writes.commands_named("TS.ADD").len() == 1).await;
+    let second = tokio::spawn(app.clone().oneshot(ingest_request()));
+    wait_until(|| state.write_queue.depth() == 1).await;
+
+    let response = app.oneshot(ingest_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+    first.abort();
+    second.abort();
+}
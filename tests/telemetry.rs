@@ -0,0 +1,142 @@
+use axum::{
+    Router,
+    body::{Body, Bytes, to_bytes},
+    http::{HeaderMap, Request, StatusCode, header},
+    middleware,
+    routing::{get, post},
+};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use signalstashrs::error_utils::log_and_response;
+use signalstashrs::{request_id, telemetry};
+use std::future::IntoFuture;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tower::util::ServiceExt;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// Starts a stand-in OTLP/HTTP collector that reports the content type and size of every
+/// trace export it receives.
+async fn local_collector() -> (String, mpsc::UnboundedReceiver<(String, usize)>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/v1/traces",
+        post(move |headers: HeaderMap, body: Bytes| {
+            let tx = tx.clone();
+            async move {
+                let content_type = headers
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                let _ = tx.send((content_type, body.len()));
+                StatusCode::OK
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::serve(listener, app).into_future());
+    (format!("http://{addr}"), rx)
+}
+
+#[tokio::test]
+async fn spans_are_exported_to_collector() {
+    let (endpoint, mut exports) = local_collector().await;
+    let provider = telemetry::tracer_provider(&endpoint, "signalstashrs-test").unwrap();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::info_span!("ingest").in_scope(|| {
+            tracing::info_span!("redis", otel.name = "TS.ADD").in_scope(|| {});
+        });
+    });
+
+    let flushing = provider.clone();
+    tokio::task::spawn_blocking(move || flushing.force_flush())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (content_type, bytes) = tokio::time::timeout(Duration::from_secs(5), exports.recv())
+        .await
+        .expect("collector received an export")
+        .unwrap();
+    assert_eq!(content_type, "application/x-protobuf");
+    assert!(bytes > 0);
+    provider.shutdown().unwrap();
+}
+
+#[tokio::test]
+async fn request_span_continues_incoming_trace() {
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = Router::new()
+        .route(
+            "/trace",
+            get(|| async {
+                let context = tracing::Span::current().context();
+                context.span().span_context().trace_id().to_string()
+            }),
+        )
+        .layer(middleware::from_fn(telemetry::trace_http));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/trace")
+                .header("traceparent", TRACEPARENT)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(body, "4bf92f3577b34da6a3ce929d0e0e4736");
+}
+
+#[tokio::test]
+async fn request_id_is_echoed_and_used_for_errors() {
+    let app = Router::new()
+        .route(
+            "/fail",
+            get(|| async { log_and_response("Test failure", "boom") }),
+        )
+        .layer(middleware::from_fn(request_id::propagate));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/fail")
+                .header("x-request-id", "dev-1:batch-42")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["x-request-id"], "dev-1:batch-42");
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(body, "internal error (correlation id: dev-1:batch-42)");
+
+    let response = app
+        .oneshot(Request::builder().uri("/fail").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let generated = response.headers()["x-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(uuid::Uuid::parse_str(&generated).is_ok());
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains(&generated));
+}
@@ -0,0 +1,166 @@
+use axum::{Extension, Router, routing::get};
+use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair, SanType};
+use signalstashrs::auth::client_cert::ClientCertificate;
+use signalstashrs::tls::{self, CertificateResolver, TlsSettings};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A throwaway CA able to sign server and client certificates.
+struct Authority {
+    cert: rcgen::Certificate,
+    key: KeyPair,
+}
+
+impl Authority {
+    fn new() -> Self {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        Self { cert, key }
+    }
+
+    /// Returns the certificate and key PEM for `name`.
+    fn issue(&self, name: &str, usage: ExtendedKeyUsagePurpose) -> (String, String) {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params
+            .subject_alt_names
+            .push(SanType::DnsName(name.try_into().unwrap()));
+        params.extended_key_usages = vec![usage];
+        let key = KeyPair::generate().unwrap();
+        let cert = params.signed_by(&key, &self.cert, &self.key).unwrap();
+        (cert.pem(), key.serialize_pem())
+    }
+}
+
+struct Server {
+    addr: SocketAddr,
+    dir: PathBuf,
+    settings: TlsSettings,
+    resolver: Arc<CertificateResolver>,
+}
+
+fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Serves a route answering with the device ID of the caller's client certificate, or `none`.
+async fn start_server(server_ca: &Authority, client_ca: &Authority) -> Server {
+    let dir = std::env::temp_dir().join(format!("signalstashrs-tls-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (cert, key) = server_ca.issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    let settings = TlsSettings {
+        cert_path: write(&dir, "tls.crt", &cert),
+        key_path: write(&dir, "tls.key", &key),
+        client_ca_path: Some(write(&dir, "ca.crt", &client_ca.cert.pem())),
+        reload_interval: Duration::from_secs(30),
+    };
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let resolver = Arc::new(CertificateResolver::load(&settings, provider).unwrap());
+    let config = Arc::new(tls::server_config(&settings, resolver.clone()).unwrap());
+    let router = Router::new().route(
+        "/whoami",
+        get(
+            |certificate: Option<Extension<ClientCertificate>>| async move {
+                certificate.map_or_else(|| "none".to_string(), |c| c.0.device_id)
+            },
+        ),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(tls::serve(listener, config, router, std::future::pending()));
+    Server {
+        addr,
+        dir,
+        settings,
+        resolver,
+    }
+}
+
+fn client(trusted: &Authority, identity: Option<(String, String)>) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder()
+        .use_rustls_tls()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(
+            reqwest::Certificate::from_pem(trusted.cert.pem().as_bytes()).unwrap(),
+        );
+    if let Some((cert, key)) = identity {
+        let pem = format!("{cert}{key}");
+        builder = builder.identity(reqwest::Identity::from_pem(pem.as_bytes()).unwrap());
+    }
+    builder.build().unwrap()
+}
+
+async fn whoami(client: &reqwest::Client, addr: SocketAddr) -> reqwest::Result<String> {
+    client
+        .get(format!("https://localhost:{}/whoami", addr.port()))
+        .send()
+        .await?
+        .text()
+        .await
+}
+
+#[tokio::test]
+async fn client_certificate_names_the_device() {
+    let server_ca = Authority::new();
+    let client_ca = Authority::new();
+    let server = start_server(&server_ca, &client_ca).await;
+
+    let identity = client_ca.issue("sensor-42", ExtendedKeyUsagePurpose::ClientAuth);
+    let device = client(&server_ca, Some(identity));
+    assert_eq!(whoami(&device, server.addr).await.unwrap(), "sensor-42");
+
+    let anonymous = client(&server_ca, None);
+    assert_eq!(whoami(&anonymous, server.addr).await.unwrap(), "none");
+
+    // A certificate from another CA is refused during the handshake.
+    let stranger = Authority::new().issue("sensor-42", ExtendedKeyUsagePurpose::ClientAuth);
+    let impostor = client(&server_ca, Some(stranger));
+    assert!(whoami(&impostor, server.addr).await.is_err());
+
+    std::fs::remove_dir_all(server.dir).unwrap();
+}
+
+#[tokio::test]
+async fn reload_serves_the_new_certificate() {
+    let server_ca = Authority::new();
+    let client_ca = Authority::new();
+    let server = start_server(&server_ca, &client_ca).await;
+
+    let renewed_ca = Authority::new();
+    let (cert, key) = renewed_ca.issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+
+    // A key that does not belong to the certificate is rejected and the old pair kept.
+    let (_, other_key) = renewed_ca.issue("localhost", ExtendedKeyUsagePurpose::ServerAuth);
+    std::fs::write(&server.settings.cert_path, &cert).unwrap();
+    std::fs::write(&server.settings.key_path, &other_key).unwrap();
+    assert!(server.resolver.reload(&server.settings).is_err());
+    assert_eq!(
+        whoami(&client(&server_ca, None), server.addr)
+            .await
+            .unwrap(),
+        "none"
+    );
+
+    std::fs::write(&server.settings.key_path, &key).unwrap();
+    server.resolver.reload(&server.settings).unwrap();
+    assert!(
+        whoami(&client(&server_ca, None), server.addr)
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        whoami(&client(&renewed_ca, None), server.addr)
+            .await
+            .unwrap(),
+        "none"
+    );
+
+    std::fs::remove_dir_all(server.dir).unwrap();
+}